@@ -0,0 +1,105 @@
+use chip8::builder::RomBuilder;
+use chip8::device::{Config, Device};
+use chip8::display::{Display, Resolution};
+use chip8::palette::Palette;
+use chip8::screen::Screen;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+// One second of emulation
+const FRAMES: u32 = 60;
+
+fn device(rom: &[u8]) -> Device {
+    let mut device = Device::new(Config::default());
+    device.load_rom(rom);
+    device
+}
+
+// A loop of ALU instructions that never draws, so every frame runs a full
+// batch and the time is all fetching, decoding and dispatching
+fn dispatch(c: &mut Criterion) {
+    let rom = RomBuilder::new()
+        .ld_v(0, 1)
+        .add_v(1, 3)
+        .add_vv(2, 1)
+        .xor(3, 2)
+        .shr(4, 3)
+        .sub(5, 0)
+        .se_v(6, 0xFF)
+        .jump(0x202)
+        .build();
+
+    c.bench_function("dispatch", |b| {
+        let mut device = device(&rom);
+
+        b.iter(|| {
+            for _ in 0..FRAMES {
+                device.run_frame();
+            }
+        })
+    });
+}
+
+fn sprites(c: &mut Criterion) {
+    let sprite = [0xFF; 15];
+
+    let mut group = c.benchmark_group("sprites");
+
+    for (name, resolution) in [("lores", Resolution::LORES), ("hires", Resolution::HIRES)] {
+        group.bench_function(name, |b| {
+            let mut display = Display::new(resolution);
+            let mut position = 0u8;
+
+            b.iter(|| {
+                position = position.wrapping_add(7);
+                black_box(display.draw(position, position / 2, &sprite))
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// Every digit of the font drawn across the screen, then cleared, like the
+// title screen of a typical game
+fn frames(c: &mut Criterion) {
+    let mut rom = RomBuilder::new();
+    rom.cls().ld_v(0, 0).ld_v(1, 0).ld_v(2, 0);
+    let draw = rom.here();
+    rom.ld_f(2)
+        .drw(0, 1, 5)
+        .add_v(0, 5)
+        .add_v(2, 1)
+        .se_v(2, 16)
+        .jump(draw)
+        .jump(0x200);
+
+    let rom = rom.build();
+
+    c.bench_function("frames", |b| {
+        let mut device = device(&rom);
+
+        b.iter(|| {
+            for _ in 0..FRAMES {
+                device.run_frame();
+            }
+        })
+    });
+}
+
+fn screen(c: &mut Criterion) {
+    let mut display = Display::new(Resolution::HIRES);
+    let palette = Palette::default();
+    let mut frame = vec![0; display.buffer().len() * 4];
+
+    for x in (0..128).step_by(9) {
+        display.draw(x, x / 2, &[0xAA; 15]);
+    }
+
+    c.bench_function("screen", |b| {
+        b.iter(|| black_box(Screen::convert(&display, &palette, &[], &mut frame)))
+    });
+}
+
+criterion_group!(benches, dispatch, sprites, frames, screen);
+criterion_main!(benches);
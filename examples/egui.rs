@@ -0,0 +1,85 @@
+// Embeds the emulator in an egui application, showing the display as a
+// texture next to an on-screen keypad
+//
+//     cargo run --example egui -- <rom>
+use chip8::device::{Config, Device};
+use chip8::platform::Platform;
+use eframe::egui;
+use std::env;
+use std::path::Path;
+
+struct Emulator {
+    device: Device,
+    texture: Option<egui::TextureHandle>,
+    keys: [bool; 16],
+}
+
+impl Emulator {
+    fn image(&self) -> egui::ColorImage {
+        let frame = self.device.capture_frame(1);
+
+        egui::ColorImage::from_rgba_unmultiplied([frame.width, frame.height], &frame.pixels)
+    }
+}
+
+impl eframe::App for Emulator {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // eframe repaints at the monitor refresh rate, close enough to 60Hz
+        // for an example
+        self.device.run_frame();
+
+        let image = self.image();
+        let texture = self.texture.get_or_insert_with(|| {
+            ctx.load_texture("display", image.clone(), egui::TextureOptions::NEAREST)
+        });
+        texture.set(image, egui::TextureOptions::NEAREST);
+
+        let texture = texture.clone();
+        let mut keys = [false; 16];
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let width = ui.available_width();
+            ui.image((texture.id(), egui::vec2(width, width / 2.0)));
+
+            egui::Grid::new("keypad").show(ui, |ui| {
+                for (index, &key) in Platform::Chip8.keypad().iter().enumerate() {
+                    let button =
+                        ui.add_sized([48.0, 48.0], egui::Button::new(format!("{:X}", key)));
+                    keys[usize::from(key)] = button.is_pointer_button_down_on();
+
+                    if index % 4 == 3 {
+                        ui.end_row();
+                    }
+                }
+            });
+        });
+
+        for (key, (&held, was_held)) in keys.iter().zip(self.keys.iter_mut()).enumerate() {
+            if held != *was_held {
+                self.device.handle_key(key as u8, held);
+                *was_held = held;
+            }
+        }
+
+        ctx.request_repaint();
+    }
+}
+
+fn main() -> eframe::Result {
+    let rom = env::args().nth(1).expect("Must provide ROM path");
+
+    let mut device = Device::new(Config::default());
+    device.load(Path::new(&rom));
+
+    eframe::run_native(
+        "CHIP8",
+        eframe::NativeOptions::default(),
+        Box::new(|_| {
+            Ok(Box::new(Emulator {
+                device,
+                texture: None,
+                keys: [false; 16],
+            }))
+        }),
+    )
+}
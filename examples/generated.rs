@@ -0,0 +1,31 @@
+// Assembles a ROM with `RomBuilder` and runs it, printing the screen
+//
+//     cargo run --example generated
+use chip8::builder::RomBuilder;
+use chip8::device::{Config, Device};
+
+fn main() {
+    let mut rom = RomBuilder::new();
+
+    // Draw the digits 0-7 across the screen with the built-in font
+    rom.ld_v(0, 0).ld_v(1, 2).ld_v(2, 12);
+
+    let draw = rom.here();
+
+    rom.ld_f(0)
+        .drw(1, 2, 5)
+        .add_v(0, 1)
+        .add_v(1, 7)
+        .se_v(0, 8)
+        .jump(draw)
+        .halt();
+
+    let mut device = Device::new(Config::default());
+    device.load_rom(&rom.build());
+
+    for _ in 0..60 {
+        device.run_frame();
+    }
+
+    print!("{}", device.display().ascii());
+}
@@ -0,0 +1,32 @@
+// Runs a ROM without a window and dumps the final frame as a PBM or PNG
+// image, depending on the extension of the output
+//
+//     cargo run --example headless -- <rom> [frames] [output.pbm|output.png]
+use chip8::device::{Config, Device};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let rom = args.next().expect("Must provide ROM path");
+    let frames: u32 = args.next().map_or(600, |frames| frames.parse().unwrap());
+    let output = args.next().unwrap_or_else(|| "frame.pbm".to_owned());
+
+    let mut device = Device::new(Config::default());
+    device.load(Path::new(&rom));
+
+    for _ in 0..frames {
+        device.run_frame();
+    }
+
+    let image = if output.ends_with(".png") {
+        device.display().png()
+    } else {
+        device.display().pbm()
+    };
+
+    fs::write(&output, image).unwrap();
+
+    println!("Wrote frame {} to '{}'", frames, output);
+}
@@ -0,0 +1,52 @@
+// Feeds a scripted key sequence into a ROM, the same format written by
+// macro recording, and prints the screen once the script has finished
+//
+//     cargo run --example replay -- <rom> [script]
+use chip8::device::{Config, Device};
+use chip8::input::{InputLog, Playback};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// Tap 5 a couple of times, then hold 6 for a second
+const SCRIPT: &str = "\
+30 5 down
+35 5 up
+90 5 down
+95 5 up
+120 6 down
+180 6 up
+";
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let rom = args.next().expect("Must provide ROM path");
+    let script = args.next().map_or_else(
+        || SCRIPT.to_owned(),
+        |path| fs::read_to_string(path).unwrap(),
+    );
+
+    let log = InputLog::parse(&script).unwrap_or_else(|err| panic!("Invalid script: {}", err));
+
+    let mut device = Device::new(Config::default());
+    device.load(Path::new(&rom));
+
+    let mut playback = Playback::new(log, 0);
+    let mut frame = 0;
+
+    for (key, &held) in playback.initial_keys().iter().enumerate() {
+        device.handle_key(key as u8, held);
+    }
+
+    while !playback.is_finished() {
+        for event in playback.due(frame) {
+            device.handle_key(event.key, event.pressed);
+        }
+
+        device.run_frame();
+        frame += 1;
+    }
+
+    println!("Replayed {} frames", frame);
+    print!("{}", device.display().ascii());
+}
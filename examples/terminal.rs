@@ -0,0 +1,92 @@
+// A custom renderer that draws the display in the terminal with block
+// characters instead of opening a window
+//
+//     cargo run --example terminal -- <rom> [seconds]
+//
+// The terminal bell rings along with the sound timer, using the timer hook
+use chip8::clock::{Pacer, RealClock};
+use chip8::device::{Config, Device};
+use chip8::display::Display;
+use std::env;
+use std::io::{self, Write};
+use std::path::Path;
+
+struct TerminalRenderer {
+    out: io::Stdout,
+}
+
+impl TerminalRenderer {
+    fn new() -> Self {
+        // clear the terminal and hide the cursor
+        print!("\x1b[2J\x1b[?25l");
+
+        Self { out: io::stdout() }
+    }
+
+    // Two display rows are packed into each line using half blocks
+    fn refresh(&mut self, display: &Display) {
+        let resolution = display.resolution();
+        let buffer = display.buffer();
+        let mut frame = String::from("\x1b[H");
+
+        for y in (0..resolution.height).step_by(2) {
+            for x in 0..resolution.width {
+                let top = buffer[y * resolution.width + x] != 0;
+                let bottom = buffer
+                    .get((y + 1) * resolution.width + x)
+                    .is_some_and(|&pixel| pixel != 0);
+
+                frame.push(match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+
+            frame.push('\n');
+        }
+
+        let mut out = self.out.lock();
+        out.write_all(frame.as_bytes()).unwrap();
+        out.flush().unwrap();
+    }
+}
+
+impl Drop for TerminalRenderer {
+    fn drop(&mut self) {
+        print!("\x1b[?25h");
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let rom = args.next().expect("Must provide ROM path");
+    let seconds: u64 = args.next().map_or(10, |seconds| seconds.parse().unwrap());
+
+    let mut device = Device::new(Config::default());
+    device.load(Path::new(&rom));
+
+    // ring the terminal bell whenever a sound starts
+    let mut beeping = false;
+
+    device.on_timer(move |tick| {
+        if tick.sound > 0 && !beeping {
+            print!("\x07");
+        }
+
+        beeping = tick.sound > 0;
+    });
+
+    let mut renderer = TerminalRenderer::new();
+    let mut pacer = Pacer::new(RealClock::new());
+
+    for _ in 0..seconds * 60 {
+        while !pacer.frame_due() {
+            pacer.wait();
+        }
+
+        device.run_frame();
+        renderer.refresh(device.display());
+    }
+}
@@ -1,45 +1,302 @@
 use crate::device;
-use crate::screen;
+use crate::display::Resolution;
+use crate::keymap::{self, Keymap};
+use crate::placement::Placement;
+use crate::platform::Platform;
+use crate::screen::Screen;
+use crate::storage::Storage;
 
 use log::info;
+use std::collections::HashMap;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
+use std::time::Instant;
 use winit::application::ApplicationHandler;
-use winit::dpi::LogicalSize;
-use winit::event::{ElementState, KeyEvent, WindowEvent};
-use winit::event_loop::ActiveEventLoop;
+use winit::dpi::{LogicalSize, PhysicalPosition};
+use winit::event::{
+    DeviceEvent, DeviceId, ElementState, KeyEvent, MouseButton, RawKeyEvent, WindowEvent,
+};
+use winit::event_loop::{ActiveEventLoop, DeviceEvents};
 use winit::keyboard::{KeyCode, PhysicalKey};
-use winit::window::{Window, WindowAttributes, WindowButtons, WindowId};
+use winit::window::{Window, WindowAttributes, WindowButtons, WindowId, WindowLevel};
+
+// Size of the window, as the size of a lores pixel in logical pixels
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scale {
+    // The largest that fits on the monitor
+    Auto,
+    Fixed(u32),
+}
+
+impl Scale {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "auto" => Self::Auto,
+            _ => value
+                .parse()
+                .ok()
+                .filter(|&scale| scale > 0)
+                .map(Self::Fixed)
+                .unwrap_or_else(|| panic!("Invalid scale '{}', expected auto or a number", value)),
+        }
+    }
+
+    // Largest scale showing `resolution` within `area`, and at least 1
+    pub fn fit(area: LogicalSize<f64>, resolution: Resolution) -> u32 {
+        (1..)
+            .take_while(|&scale| {
+                let size = Screen::window_size(resolution, scale);
+
+                f64::from(size.width) <= area.width && f64::from(size.height) <= area.height
+            })
+            .last()
+            .unwrap_or(1)
+    }
+}
+
+// Share of the monitor an automatically scaled window may cover. winit
+// can't tell how much room taskbars and docks take, so leave a margin.
+const MONITOR_SHARE: f64 = 0.8;
+// Scale used when the monitor is unknown, such as on Wayland
+const FALLBACK_SCALE: u32 = 6;
+
+#[derive(Clone)]
+pub struct Settings {
+    pub scale: Scale,
+    // Where the window's last position and scale are remembered
+    pub storage: Storage,
+    // Releases a pending FX0A wait, storing `cancel_value` in its register
+    pub cancel_key: KeyCode,
+    pub cancel_value: u8,
+    // Print the display coordinates of left clicks, for placing sprites
+    pub click_coords: bool,
+    // Read the keypad from raw device events, falling back to window
+    // events on platforms that never send them
+    pub raw_input: bool,
+    // Keep the window above all others
+    pub always_on_top: bool,
+    // Leave out the title bar and borders, dragging the window by its
+    // contents instead
+    pub borderless: bool,
+    // Name of the keymap to start with, otherwise the first one
+    pub keymap: Option<String>,
+    // Stamp keys with when they were seen, for `chip8 latency`
+    pub latency_test: bool,
+    // `PageDown` skips to the next ROM, for `chip8 watch`
+    pub next_rom: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            scale: Scale::Auto,
+            storage: Storage::default(),
+            cancel_key: KeyCode::Escape,
+            cancel_value: 0,
+            click_coords: false,
+            raw_input: false,
+            always_on_top: false,
+            borderless: false,
+            keymap: None,
+            latency_test: false,
+            next_rom: false,
+        }
+    }
+}
+
+// Requests from the device thread to the window
+pub enum AppEvent {
+    // The device has stopped for good, so close the window
+    Close,
+}
 
 pub struct App {
     channel: Sender<device::Event>,
     window: Option<Arc<Window>>,
+    platform: Platform,
+    resolution: Resolution,
+    settings: Settings,
+    cursor: (f64, f64),
+    // CHIP-8 key held down by each physical key, so a release always lifts
+    // the key its press set even if composition swallowed events in between.
+    // The device lifts them all itself when focus is lost.
+    held: HashMap<KeyCode, u8>,
+    focused: bool,
+    // Whether a raw key event has arrived yet, after which window key
+    // events are only used for hotkeys
+    raw_keys: bool,
+    // Where the window is now and the scale it was opened at, saved on exit
+    position: Option<PhysicalPosition<i32>>,
     scale: u32,
+    // Keymaps cycled through with F3, and the one in use
+    keymaps: Vec<Keymap>,
+    keymap: usize,
+    // CHIP-8X's second keypad, played on the `right-hand` keymap, and the
+    // keys held on it
+    second_keymap: Option<Keymap>,
+    second_held: HashMap<KeyCode, u8>,
+    // F2 is held, showing the save slots, and the arrow keys pick one
+    choosing_slot: bool,
 }
 
 impl App {
-    pub fn new(scale: u32, channel: Sender<device::Event>) -> Self {
+    pub fn new(
+        platform: Platform,
+        resolution: Resolution,
+        settings: Settings,
+        channel: Sender<device::Event>,
+    ) -> Self {
+        let keymaps = keymap::load(&settings.storage);
+        let keymap = settings.keymap.as_ref().map_or(0, |name| {
+            keymaps
+                .iter()
+                .position(|keymap| &keymap.name == name)
+                .unwrap_or_else(|| panic!("Unknown keymap '{}'", name))
+        });
+        let second_keymap = (platform == Platform::Chip8X)
+            .then(|| keymaps.iter().find(|keymap| keymap.name == "right-hand"))
+            .flatten()
+            .cloned();
+
         Self {
             window: None,
             channel,
-            scale,
+            platform,
+            resolution,
+            settings,
+            cursor: (0.0, 0.0),
+            held: HashMap::new(),
+            focused: true,
+            raw_keys: false,
+            position: None,
+            scale: FALLBACK_SCALE,
+            keymaps,
+            keymap,
+            second_keymap,
+            second_held: HashMap::new(),
+            choosing_slot: false,
         }
     }
 
-    fn create_window(&mut self, event_loop: &ActiveEventLoop) -> Arc<Window> {
-        let window_size = LogicalSize::new(
-            u32::from(screen::WIDTH) * self.scale,
-            u32::from(screen::HEIGHT) * self.scale,
-        );
+    // Names accepted for configurable hotkeys
+    pub fn parse_key(name: &str) -> KeyCode {
+        match name.to_ascii_lowercase().as_str() {
+            "escape" | "esc" => KeyCode::Escape,
+            "backspace" => KeyCode::Backspace,
+            "tab" => KeyCode::Tab,
+            "space" => KeyCode::Space,
+            "enter" => KeyCode::Enter,
+            "f4" => KeyCode::F4,
+            "f11" => KeyCode::F11,
+            _ => panic!("Unknown or reserved key '{}'", name),
+        }
+    }
+
+    // The placement saved last time, unless the monitor it was on is gone.
+    // An overlay is only ever temporary, so it neither uses nor replaces it.
+    fn saved_placement(&self, event_loop: &ActiveEventLoop) -> Option<Placement> {
+        if self.settings.borderless {
+            return None;
+        }
+
+        let placement = Placement::load(&self.settings.storage)?;
+        let monitors: Vec<_> = event_loop
+            .available_monitors()
+            .map(|monitor| {
+                let (position, size) = (monitor.position(), monitor.size());
+
+                (position.x, position.y, size.width, size.height)
+            })
+            .collect();
+
+        if placement.is_visible(&monitors) {
+            Some(placement)
+        } else {
+            info!("Saved window position is off screen, opening it on the primary monitor");
+            None
+        }
+    }
+
+    fn save_placement(&self) {
+        if self.settings.borderless {
+            return;
+        }
+
+        if let Some(position) = self.position {
+            Placement {
+                x: position.x,
+                y: position.y,
+                scale: self.scale,
+            }
+            .save(&self.settings.storage);
+        }
+    }
+
+    fn scale(&self, event_loop: &ActiveEventLoop, placement: Option<Placement>) -> u32 {
+        if let Scale::Fixed(scale) = self.settings.scale {
+            return scale;
+        }
+
+        if let Some(placement) = placement {
+            return placement.scale;
+        }
+
+        let monitor = event_loop
+            .primary_monitor()
+            .or_else(|| event_loop.available_monitors().next());
+
+        match monitor {
+            Some(monitor) => {
+                let size: LogicalSize<f64> = monitor.size().to_logical(monitor.scale_factor());
+                let area =
+                    LogicalSize::new(size.width * MONITOR_SHARE, size.height * MONITOR_SHARE);
+                let scale = Scale::fit(area, self.resolution);
+
+                info!(
+                    "Scaling by {} to fit a {}x{} monitor",
+                    scale, size.width, size.height
+                );
+
+                scale
+            }
+            None => FALLBACK_SCALE,
+        }
+    }
+
+    fn create_window(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        scale: u32,
+        placement: Option<Placement>,
+    ) -> Arc<Window> {
+        let window_size = Screen::window_size(self.resolution, scale);
 
         let window_attributes = WindowAttributes::default()
             .with_title("CHIP8")
             .with_inner_size(window_size)
             .with_resizable(false)
-            .with_enabled_buttons(WindowButtons::CLOSE | WindowButtons::MINIMIZE);
+            .with_enabled_buttons(WindowButtons::CLOSE | WindowButtons::MINIMIZE)
+            .with_decorations(!self.settings.borderless);
+
+        let window_attributes = if self.settings.always_on_top {
+            window_attributes.with_window_level(WindowLevel::AlwaysOnTop)
+        } else {
+            window_attributes
+        };
+
+        let window_attributes = match placement {
+            Some(placement) => {
+                window_attributes.with_position(PhysicalPosition::new(placement.x, placement.y))
+            }
+            None => window_attributes,
+        };
 
         let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
+        // keys go straight to the keypad, never through an input method
+        window.set_ime_allowed(false);
         self.window = Some(window.clone());
+        self.position = window.outer_position().ok();
+        self.scale = scale;
 
         window.clone()
     }
@@ -48,39 +305,129 @@ impl App {
         self.channel.send(event).unwrap();
     }
 
-    fn physical_to_chip8_key(&self, key: PhysicalKey) -> Option<u8> {
+    fn send_key(&self, key: u8, pressed: bool) {
+        if self.settings.latency_test {
+            self.send_event(device::Event::Probe(Instant::now()));
+        }
+
+        self.send_event(device::Event::Key(key, pressed));
+    }
+
+    fn press(&mut self, key: PhysicalKey, state: ElementState) {
+        let PhysicalKey::Code(code) = key else {
+            return;
+        };
+
+        match state {
+            ElementState::Pressed => {
+                if let Some(mapped_key) = self.physical_to_chip8_key(key) {
+                    self.held.insert(code, mapped_key);
+                    self.send_key(mapped_key, true);
+                } else if let Some(position) = self
+                    .second_keymap
+                    .as_ref()
+                    .and_then(|keymap| keymap.position(code))
+                {
+                    let mapped_key = self.platform.keypad()[position];
+                    self.second_held.insert(code, mapped_key);
+                    self.send_event(device::Event::SecondKey(mapped_key, true));
+                }
+            }
+            ElementState::Released => {
+                if let Some(mapped_key) = self.held.remove(&code) {
+                    self.send_key(mapped_key, false);
+                } else if let Some(mapped_key) = self.second_held.remove(&code) {
+                    self.send_event(device::Event::SecondKey(mapped_key, false));
+                }
+            }
+        }
+    }
+
+    // Move on to the next keymap, lifting any keys held with the old one
+    // first as their releases would no longer map to the same CHIP-8 keys
+    fn switch_keymap(&mut self) {
+        for (_, key) in self.held.drain() {
+            self.channel.send(device::Event::Key(key, false)).unwrap();
+        }
+
+        self.keymap = (self.keymap + 1) % self.keymaps.len();
+
+        let name = self.keymaps[self.keymap].name.clone();
+        info!("Switched to keymap '{}'", name);
+        self.send_event(device::Event::Keymap(name));
+    }
+
+    fn hotkey(&self, key: PhysicalKey, state: ElementState) -> Option<device::Event> {
+        if state != ElementState::Pressed {
+            return None;
+        }
+
+        match key {
+            PhysicalKey::Code(code) if code == self.settings.cancel_key => {
+                Some(device::Event::CancelWait(self.settings.cancel_value))
+            }
+            PhysicalKey::Code(KeyCode::F1) => Some(device::Event::Controls),
+            PhysicalKey::Code(KeyCode::F5) => Some(device::Event::SaveState),
+            PhysicalKey::Code(KeyCode::F6) => Some(device::Event::RecordMacro),
+            PhysicalKey::Code(KeyCode::F7) => Some(device::Event::PlayMacro),
+            PhysicalKey::Code(KeyCode::F8) => Some(device::Event::Retry),
+            PhysicalKey::Code(KeyCode::F9) => Some(device::Event::LoadState),
+            PhysicalKey::Code(KeyCode::F10) => Some(device::Event::Font(None)),
+            PhysicalKey::Code(KeyCode::F12) => Some(device::Event::Screenshot(None)),
+            // anywhere else the device would stop with no ROM to move on to
+            PhysicalKey::Code(KeyCode::PageDown) if self.settings.next_rom => {
+                Some(device::Event::Next)
+            }
+            _ => None,
+        }
+    }
+
+    // The arrow keys move along the save slots while they're shown
+    fn slot_hotkey(&self, key: PhysicalKey, state: ElementState) -> Option<device::Event> {
+        if !self.choosing_slot || state != ElementState::Pressed {
+            return None;
+        }
+
         match key {
-            PhysicalKey::Code(code) => match code {
-                KeyCode::Digit1 => Some(0x1),
-                KeyCode::Digit2 => Some(0x2),
-                KeyCode::Digit3 => Some(0x3),
-                KeyCode::Digit4 => Some(0xc),
-                KeyCode::KeyQ => Some(0x4),
-                KeyCode::KeyW => Some(0x5),
-                KeyCode::KeyE => Some(0x6),
-                KeyCode::KeyR => Some(0xD),
-                KeyCode::KeyA => Some(0x7),
-                KeyCode::KeyS => Some(0x8),
-                KeyCode::KeyD => Some(0x9),
-                KeyCode::KeyF => Some(0xE),
-                KeyCode::KeyZ => Some(0xA),
-                KeyCode::KeyX => Some(0x0),
-                KeyCode::KeyC => Some(0xB),
-                KeyCode::KeyV => Some(0xF),
-                _ => None,
-            },
+            PhysicalKey::Code(KeyCode::ArrowLeft) => Some(device::Event::SelectSlot(-1)),
+            PhysicalKey::Code(KeyCode::ArrowRight) => Some(device::Event::SelectSlot(1)),
             _ => None,
         }
     }
+
+    fn physical_to_chip8_key(&self, key: PhysicalKey) -> Option<u8> {
+        let PhysicalKey::Code(code) = key else {
+            return None;
+        };
+
+        let position = self.keymaps[self.keymap].position(code)?;
+
+        Some(self.platform.keypad()[position])
+    }
 }
 
-impl ApplicationHandler for App {
+impl ApplicationHandler<AppEvent> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         info!("Creating window");
 
-        let window = self.create_window(event_loop);
+        let placement = self.saved_placement(event_loop);
+        let scale = self.scale(event_loop, placement);
+        let window = self.create_window(event_loop, scale, placement);
+
+        if self.settings.raw_input {
+            event_loop.listen_device_events(DeviceEvents::WhenFocused);
+        }
 
-        self.send_event(device::Event::On(window));
+        self.send_event(device::Event::On(window, scale));
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: AppEvent) {
+        match event {
+            AppEvent::Close => {
+                self.save_placement();
+                event_loop.exit();
+            }
+        }
     }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
@@ -95,20 +442,109 @@ impl ApplicationHandler for App {
                     },
                 ..
             } => {
-                if let Some(mapped_key) = self.physical_to_chip8_key(physical_key) {
-                    let pressed = match state {
-                        ElementState::Pressed => true,
-                        ElementState::Released => false,
-                    };
+                if physical_key == PhysicalKey::Code(KeyCode::F3) && state == ElementState::Pressed
+                {
+                    self.switch_keymap();
+                } else if physical_key == PhysicalKey::Code(KeyCode::F2) {
+                    self.choosing_slot = state == ElementState::Pressed;
+                    self.send_event(device::Event::Slots(self.choosing_slot));
+                } else if let Some(event) = self.slot_hotkey(physical_key, state) {
+                    self.send_event(event);
+                } else if let Some(event) = self.hotkey(physical_key, state) {
+                    self.send_event(event);
+                } else if !self.raw_keys {
+                    self.press(physical_key, state);
+                }
+            }
+            WindowEvent::Resized(size) => {
+                self.send_event(device::Event::Resize(size.width, size.height));
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = (position.x, position.y);
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                if self.settings.click_coords {
+                    self.send_event(device::Event::Click(self.cursor.0, self.cursor.1));
+                }
 
-                    self.send_event(device::Event::Key(mapped_key, pressed));
+                if self.settings.borderless {
+                    if let Some(window) = &self.window {
+                        let _ = window.drag_window();
+                    }
                 }
             }
+            WindowEvent::Focused(focused) => {
+                self.focused = focused;
+
+                if !focused {
+                    self.held.clear();
+                    self.second_held.clear();
+
+                    if self.choosing_slot {
+                        self.choosing_slot = false;
+                        self.send_event(device::Event::Slots(false));
+                    }
+                }
+
+                self.send_event(device::Event::Focus(focused));
+            }
+            WindowEvent::Moved(position) => self.position = Some(position),
             WindowEvent::CloseRequested => {
+                self.save_placement();
                 self.send_event(device::Event::Off);
                 event_loop.exit();
             }
             _ => (),
         }
     }
+
+    // Raw events come straight from the keyboard, without going through
+    // the OS's key repeat or text input, and a little sooner than window
+    // events on some platforms
+    fn device_event(&mut self, _event_loop: &ActiveEventLoop, _id: DeviceId, event: DeviceEvent) {
+        let DeviceEvent::Key(RawKeyEvent {
+            physical_key,
+            state,
+        }) = event
+        else {
+            return;
+        };
+
+        if !self.settings.raw_input || !self.focused {
+            return;
+        }
+
+        if !self.raw_keys {
+            info!("Using raw keyboard input");
+            self.raw_keys = true;
+        }
+
+        if self.hotkey(physical_key, state).is_none()
+            && self.slot_hotkey(physical_key, state).is_none()
+        {
+            self.press(physical_key, state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_the_largest_scale() {
+        let laptop = LogicalSize::new(1366.0 * MONITOR_SHARE, 768.0 * MONITOR_SHARE);
+
+        assert_eq!(Scale::fit(laptop, Resolution::LORES), 17);
+        assert_eq!(Scale::fit(laptop, Resolution::TWO_PAGE), 9);
+        assert_eq!(
+            Scale::fit(LogicalSize::new(10.0, 10.0), Resolution::LORES),
+            1
+        );
+        assert_eq!(Scale::parse("4"), Scale::Fixed(4));
+    }
 }
@@ -0,0 +1,103 @@
+use log::info;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+const MIME_TYPE: &str = "application/x-chip8-rom";
+
+// Generate the metadata the OS needs to open `.ch8` files with the emulator
+pub fn install() {
+    let exe = env::current_exe().unwrap();
+
+    if cfg!(target_os = "linux") {
+        install_linux(&exe);
+    } else if cfg!(target_os = "windows") {
+        print_windows(&exe);
+    } else if cfg!(target_os = "macos") {
+        print_macos();
+    } else {
+        panic!("File associations are not supported on this platform");
+    }
+}
+
+// Register a desktop entry and mime type for the current user
+fn install_linux(exe: &Path) {
+    let data = env::var_os("XDG_DATA_HOME")
+        .map(Into::into)
+        .unwrap_or_else(|| Path::new(&env::var_os("HOME").unwrap()).join(".local/share"));
+
+    let applications = data.join("applications");
+    let packages = data.join("mime/packages");
+
+    fs::create_dir_all(&applications).unwrap();
+    fs::create_dir_all(&packages).unwrap();
+
+    let desktop = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=CHIP8\n\
+         Exec=\"{}\" %f\n\
+         MimeType={};\n\
+         Terminal=false\n\
+         Categories=Game;Emulator;\n",
+        exe.display(),
+        MIME_TYPE,
+    );
+
+    let mime = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n\
+         \x20 <mime-type type=\"{}\">\n\
+         \x20   <comment>CHIP-8 ROM</comment>\n\
+         \x20   <glob pattern=\"*.ch8\"/>\n\
+         \x20 </mime-type>\n\
+         </mime-info>\n",
+        MIME_TYPE,
+    );
+
+    fs::write(applications.join("chip8.desktop"), desktop).unwrap();
+    fs::write(packages.join("chip8.xml"), mime).unwrap();
+
+    // Best effort, these tools aren't installed everywhere
+    let _ = process::Command::new("update-mime-database")
+        .arg(data.join("mime"))
+        .status();
+    let _ = process::Command::new("update-desktop-database")
+        .arg(&applications)
+        .status();
+
+    info!("Registered '.ch8' files in {}", data.display());
+}
+
+// Registry entries have to be imported by the user, so print them as a .reg file
+fn print_windows(exe: &Path) {
+    let command = exe.display().to_string().replace('\\', "\\\\");
+
+    println!("Windows Registry Editor Version 5.00");
+    println!();
+    println!("[HKEY_CURRENT_USER\\Software\\Classes\\.ch8]");
+    println!("@=\"chip8.rom\"");
+    println!();
+    println!("[HKEY_CURRENT_USER\\Software\\Classes\\chip8.rom\\shell\\open\\command]");
+    println!("@=\"\\\"{}\\\" \\\"%1\\\"\"", command);
+}
+
+// Document types live in the app bundle's Info.plist
+fn print_macos() {
+    println!(
+        "<key>CFBundleDocumentTypes</key>
+<array>
+  <dict>
+    <key>CFBundleTypeName</key>
+    <string>CHIP-8 ROM</string>
+    <key>CFBundleTypeRole</key>
+    <string>Viewer</string>
+    <key>CFBundleTypeExtensions</key>
+    <array>
+      <string>ch8</string>
+    </array>
+  </dict>
+</array>"
+    );
+}
@@ -0,0 +1,124 @@
+// Opt-in memory banking for homebrew larger than the 3.5KB of program
+// memory. The top 2KB of memory (0x800-0xFFF) is a window that any bank can
+// be switched into with `FXB0` (select bank VX), while the start address
+// (0x200 unless changed) up to 0x7FF always holds the start of the ROM.
+//
+// A banked ROM file is that fixed part (1.5KB from 0x200) followed by each
+// 2KB bank in turn, so a ROM that fits in memory is simply a banked ROM with
+// only bank 0.
+// Banks behave as RAM: anything written into the window is kept when
+// switching away and back.
+pub const WINDOW: usize = 0x800;
+pub const BANK_SIZE: usize = 0x1000 - WINDOW;
+
+pub struct Banks {
+    banks: Vec<Vec<u8>>,
+    current: usize,
+}
+
+impl Banks {
+    // Split a ROM into the part loaded at `start`, below the window, and its
+    // banks
+    pub fn split(rom: &[u8], start: usize) -> (&[u8], Self) {
+        let (fixed, rest) = rom.split_at(rom.len().min(WINDOW - start));
+
+        let mut banks: Vec<Vec<u8>> = rest
+            .chunks(BANK_SIZE)
+            .map(|bank| {
+                let mut bank = bank.to_vec();
+                bank.resize(BANK_SIZE, 0);
+                bank
+            })
+            .collect();
+
+        if banks.is_empty() {
+            banks.push(vec![0; BANK_SIZE]);
+        }
+
+        (fixed, Self { banks, current: 0 })
+    }
+
+    pub fn len(&self) -> usize {
+        self.banks.len()
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    // Copy the current bank into the window of `memory`
+    pub fn map(&self, memory: &mut [u8]) {
+        memory[WINDOW..WINDOW + BANK_SIZE].copy_from_slice(&self.banks[self.current]);
+    }
+
+    // Save the window back to its bank and map in another, returning false
+    // if there is no such bank
+    pub fn switch(&mut self, memory: &mut [u8], bank: usize) -> bool {
+        if bank >= self.banks.len() {
+            return false;
+        }
+
+        self.banks[self.current].copy_from_slice(&memory[WINDOW..WINDOW + BANK_SIZE]);
+        self.current = bank;
+        self.map(memory);
+
+        true
+    }
+
+    // Contents of every bank, with the window saved into the current one
+    pub fn contents<'a>(&'a self, memory: &'a [u8]) -> Vec<&'a [u8]> {
+        self.banks
+            .iter()
+            .enumerate()
+            .map(|(index, bank)| {
+                if index == self.current {
+                    &memory[WINDOW..WINDOW + BANK_SIZE]
+                } else {
+                    &bank[..]
+                }
+            })
+            .collect()
+    }
+
+    pub fn restore(&mut self, current: usize, banks: Vec<Vec<u8>>) {
+        self.current = current;
+        self.banks = banks;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_fixed_area_and_banks() {
+        let rom = vec![1; 0x600 + BANK_SIZE + 3];
+        let (fixed, banks) = Banks::split(&rom, 0x200);
+
+        assert_eq!(fixed.len(), 0x600);
+        assert_eq!(banks.len(), 2);
+        assert_eq!(banks.banks[1][..4], [1, 1, 1, 0]);
+
+        // starting higher leaves less room below the window
+        let (fixed, banks) = Banks::split(&rom, 0x600);
+
+        assert_eq!(fixed.len(), 0x200);
+        assert_eq!(banks.len(), 2);
+        assert_eq!(banks.banks[1][0x400..0x404], [1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn keeps_writes_when_switching() {
+        let rom = vec![0; 0x600 + BANK_SIZE * 2];
+        let (_, mut banks) = Banks::split(&rom, 0x200);
+        let mut memory = [0; 4096];
+
+        memory[WINDOW] = 0x42;
+        assert!(banks.switch(&mut memory, 1));
+        assert_eq!(memory[WINDOW], 0);
+
+        assert!(banks.switch(&mut memory, 0));
+        assert_eq!(memory[WINDOW], 0x42);
+        assert!(!banks.switch(&mut memory, 2));
+    }
+}
@@ -0,0 +1,277 @@
+// Assembles CHIP-8 programs one instruction at a time, so tests and
+// examples can spell out what a ROM does instead of listing raw hex
+//
+//     let rom = RomBuilder::new().ld_v(0, 5).add_v(0, 3).jump(0x200).build();
+#[derive(Clone, Default)]
+pub struct RomBuilder {
+    bytes: Vec<u8>,
+}
+
+impl RomBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Address the next instruction will be loaded at, for use as a label
+    pub fn here(&self) -> u16 {
+        0x200 + self.bytes.len() as u16
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    pub fn opcode(&mut self, opcode: u16) -> &mut Self {
+        self.bytes.extend_from_slice(&opcode.to_be_bytes());
+        self
+    }
+
+    // Inline data such as sprites
+    pub fn data(&mut self, bytes: &[u8]) -> &mut Self {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    fn xkk(&mut self, high: u16, x: u8, kk: u8) -> &mut Self {
+        self.opcode(high << 12 | u16::from(x & 0xF) << 8 | u16::from(kk))
+    }
+
+    fn xyn(&mut self, high: u16, x: u8, y: u8, n: u8) -> &mut Self {
+        self.opcode(
+            high << 12 | u16::from(x & 0xF) << 8 | u16::from(y & 0xF) << 4 | u16::from(n & 0xF),
+        )
+    }
+
+    fn nnn(&mut self, high: u16, nnn: u16) -> &mut Self {
+        self.opcode(high << 12 | (nnn & 0xFFF))
+    }
+
+    // 00E0
+    pub fn cls(&mut self) -> &mut Self {
+        self.opcode(0x00E0)
+    }
+
+    // 00EE
+    pub fn ret(&mut self) -> &mut Self {
+        self.opcode(0x00EE)
+    }
+
+    // 1NNN
+    pub fn jump(&mut self, nnn: u16) -> &mut Self {
+        self.nnn(0x1, nnn)
+    }
+
+    // 2NNN
+    pub fn call(&mut self, nnn: u16) -> &mut Self {
+        self.nnn(0x2, nnn)
+    }
+
+    // 3XKK
+    pub fn se_v(&mut self, x: u8, kk: u8) -> &mut Self {
+        self.xkk(0x3, x, kk)
+    }
+
+    // 4XKK
+    pub fn sne_v(&mut self, x: u8, kk: u8) -> &mut Self {
+        self.xkk(0x4, x, kk)
+    }
+
+    // 5XY0
+    pub fn se_vv(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x5, x, y, 0x0)
+    }
+
+    // 6XKK
+    pub fn ld_v(&mut self, x: u8, kk: u8) -> &mut Self {
+        self.xkk(0x6, x, kk)
+    }
+
+    // 7XKK
+    pub fn add_v(&mut self, x: u8, kk: u8) -> &mut Self {
+        self.xkk(0x7, x, kk)
+    }
+
+    // 8XY0
+    pub fn ld_vv(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x8, x, y, 0x0)
+    }
+
+    // 8XY1
+    pub fn or(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x8, x, y, 0x1)
+    }
+
+    // 8XY2
+    pub fn and(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x8, x, y, 0x2)
+    }
+
+    // 8XY3
+    pub fn xor(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x8, x, y, 0x3)
+    }
+
+    // 8XY4
+    pub fn add_vv(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x8, x, y, 0x4)
+    }
+
+    // 8XY5
+    pub fn sub(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x8, x, y, 0x5)
+    }
+
+    // 8XY6
+    pub fn shr(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x8, x, y, 0x6)
+    }
+
+    // 8XY7
+    pub fn subn(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x8, x, y, 0x7)
+    }
+
+    // 8XYE
+    pub fn shl(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x8, x, y, 0xE)
+    }
+
+    // 9XY0
+    pub fn sne_vv(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x9, x, y, 0x0)
+    }
+
+    // ANNN
+    pub fn ld_i(&mut self, nnn: u16) -> &mut Self {
+        self.nnn(0xA, nnn)
+    }
+
+    // BNNN
+    pub fn jump_v0(&mut self, nnn: u16) -> &mut Self {
+        self.nnn(0xB, nnn)
+    }
+
+    // CXKK
+    pub fn rnd(&mut self, x: u8, kk: u8) -> &mut Self {
+        self.xkk(0xC, x, kk)
+    }
+
+    // DXYN
+    pub fn drw(&mut self, x: u8, y: u8, n: u8) -> &mut Self {
+        self.xyn(0xD, x, y, n)
+    }
+
+    // EX9E
+    pub fn skp(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xE, x, 0x9E)
+    }
+
+    // EXA1
+    pub fn sknp(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xE, x, 0xA1)
+    }
+
+    // FX07
+    pub fn ld_v_dt(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xF, x, 0x07)
+    }
+
+    // FX0A
+    pub fn wait_key(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xF, x, 0x0A)
+    }
+
+    // FX15
+    pub fn ld_dt(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xF, x, 0x15)
+    }
+
+    // FX18
+    pub fn ld_st(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xF, x, 0x18)
+    }
+
+    // FX1E
+    pub fn add_i(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xF, x, 0x1E)
+    }
+
+    // FX29
+    pub fn ld_f(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xF, x, 0x29)
+    }
+
+    // FX30
+    pub fn ld_hf(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xF, x, 0x30)
+    }
+
+    // FX33
+    pub fn ld_b(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xF, x, 0x33)
+    }
+
+    // FX55
+    pub fn store(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xF, x, 0x55)
+    }
+
+    // FX65
+    pub fn load(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xF, x, 0x65)
+    }
+
+    // 5XY2 (XO-CHIP)
+    pub fn store_range(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x5, x, y, 0x2)
+    }
+
+    // 5XY3 (XO-CHIP)
+    pub fn load_range(&mut self, x: u8, y: u8) -> &mut Self {
+        self.xyn(0x5, x, y, 0x3)
+    }
+
+    // F002 (XO-CHIP)
+    pub fn audio(&mut self) -> &mut Self {
+        self.opcode(0xF002)
+    }
+
+    // FX3A (XO-CHIP)
+    pub fn pitch(&mut self, x: u8) -> &mut Self {
+        self.xkk(0xF, x, 0x3A)
+    }
+
+    // Spin forever on the current address, a common way for ROMs to end
+    pub fn halt(&mut self) -> &mut Self {
+        let here = self.here();
+        self.jump(here)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_instructions() {
+        let rom = RomBuilder::new()
+            .ld_v(0, 5)
+            .add_v(0, 3)
+            .drw(1, 2, 5)
+            .wait_key(0xA)
+            .jump(0x200)
+            .build();
+
+        assert_eq!(
+            rom,
+            [0x60, 0x05, 0x70, 0x03, 0xD1, 0x25, 0xFA, 0x0A, 0x12, 0x00]
+        );
+    }
+
+    #[test]
+    fn halts_on_current_address() {
+        let rom = RomBuilder::new().cls().halt().build();
+
+        assert_eq!(rom, [0x00, 0xE0, 0x12, 0x02]);
+    }
+}
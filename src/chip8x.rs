@@ -0,0 +1,91 @@
+use std::ops::Range;
+
+// The VP-590 colour board's colours, in the order of their codes
+const COLORS: [[u8; 3]; 8] = [
+    [0x00, 0x00, 0x00], // black
+    [0xFF, 0x00, 0x00], // red
+    [0x00, 0x00, 0xFF], // blue
+    [0xFF, 0x00, 0xFF], // violet
+    [0x00, 0xFF, 0x00], // green
+    [0xFF, 0xFF, 0x00], // yellow
+    [0x00, 0xFF, 0xFF], // aqua
+    [0xFF, 0xFF, 0xFF], // white
+];
+
+// Background colours, stepped through in this order by `02A0`
+const BACKGROUNDS: [[u8; 3]; 4] = [
+    [0x00, 0x00, 0x80], // blue
+    [0x00, 0x00, 0x00], // black
+    [0x00, 0x80, 0x00], // green
+    [0x80, 0x00, 0x00], // red
+];
+
+// Zones are 8 pixels wide and a single row high, which `BXYN` colours
+// directly and `BXY0` in blocks of 4 rows
+pub const COLUMNS: usize = 8;
+pub const ROWS: usize = 32;
+
+// CHIP-8X's colour map. Lit pixels take the foreground colour of the zone
+// they're in, and every unlit pixel shows the one background colour.
+#[derive(Clone)]
+pub struct Zones {
+    background: usize,
+    foreground: [u8; COLUMNS * ROWS],
+}
+
+impl Default for Zones {
+    fn default() -> Self {
+        Self {
+            background: 0,
+            // red, until the ROM colours the screen
+            foreground: [1; COLUMNS * ROWS],
+        }
+    }
+}
+
+impl Zones {
+    pub fn step_background(&mut self) {
+        self.background = (self.background + 1) % BACKGROUNDS.len();
+    }
+
+    pub fn background(&self) -> [u8; 3] {
+        BACKGROUNDS[self.background]
+    }
+
+    // Colour of a lit pixel
+    pub fn foreground(&self, x: usize, y: usize) -> [u8; 3] {
+        let zone = (y % ROWS) * COLUMNS + (x / 8) % COLUMNS;
+
+        COLORS[usize::from(self.foreground[zone])]
+    }
+
+    // Set the colour of the zones in `columns` and `rows`, clipped to the
+    // screen
+    pub fn fill(&mut self, columns: Range<usize>, rows: Range<usize>, color: u8) {
+        let columns = columns.start.min(COLUMNS)..columns.end.min(COLUMNS);
+
+        for row in rows.start.min(ROWS)..rows.end.min(ROWS) {
+            self.foreground[row * COLUMNS..][columns.clone()].fill(color & 0x7);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colours_zones_and_clips_them() {
+        let mut zones = Zones::default();
+        zones.fill(6..10, 30..40, 4);
+
+        assert_eq!(zones.foreground(47, 31), COLORS[1]);
+        assert_eq!(zones.foreground(48, 30), COLORS[4]);
+        assert_eq!(zones.foreground(63, 31), COLORS[4]);
+        assert_eq!(zones.foreground(63, 29), COLORS[1]);
+
+        zones.step_background();
+
+        assert_eq!(zones.background(), [0, 0, 0]);
+    }
+}
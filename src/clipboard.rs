@@ -0,0 +1,103 @@
+use crate::disasm;
+use crate::display::RgbaImage;
+
+use arboard::{Clipboard, ImageData};
+use log::{info, warn};
+use std::borrow::Cow;
+
+// Something to copy to the host clipboard from the console
+pub enum Selection {
+    // Bytes from an address, as hex
+    Memory(u16, usize),
+    // A number of instructions from an address
+    Disassembly(u16, usize),
+    Registers,
+    // The display as an image
+    Frame,
+}
+
+// Memory as rows of 16 hex bytes, each starting with its address
+pub fn hex(memory: &[u8], address: u16) -> String {
+    memory
+        .chunks(16)
+        .enumerate()
+        .map(|(row, bytes)| {
+            let bytes: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+            format!(
+                "{:03X}: {}\n",
+                usize::from(address) + row * 16,
+                bytes.join(" ")
+            )
+        })
+        .collect()
+}
+
+// `count` instructions from `address`, stopping at anything unknown
+pub fn disassembly(memory: &[u8], address: u16, count: usize) -> String {
+    let mut text = String::new();
+    let mut address = address;
+
+    for _ in 0..count {
+        let Some(instruction) = memory
+            .get(usize::from(address)..)
+            .and_then(disasm::disassemble)
+        else {
+            break;
+        };
+
+        text.push_str(&format!(
+            "{:03X}  {:04X}  {}\n",
+            address, instruction.opcode, instruction.mnemonic
+        ));
+
+        address = address.wrapping_add(instruction.size);
+    }
+
+    text
+}
+
+pub fn copy_text(what: &str, text: String) {
+    match Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)) {
+        Ok(()) => info!("Copied {} to the clipboard", what),
+        Err(err) => warn!("Failed to copy {}: {}", what, err),
+    }
+}
+
+pub fn copy_image(frame: RgbaImage) {
+    let image = ImageData {
+        width: frame.width,
+        height: frame.height,
+        bytes: Cow::Owned(frame.pixels),
+    };
+
+    match Clipboard::new().and_then(|mut clipboard| clipboard.set_image(image)) {
+        Ok(()) => info!("Copied the frame to the clipboard"),
+        Err(err) => warn!("Failed to copy the frame: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_hex_rows() {
+        let memory: Vec<u8> = (0..18).collect();
+
+        assert_eq!(
+            hex(&memory, 0x200),
+            "200: 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F\n210: 10 11\n"
+        );
+    }
+
+    #[test]
+    fn disassembles_until_unknown() {
+        let mut memory = [0; 0x206];
+        memory[0x200..].copy_from_slice(&[0x00, 0xE0, 0x12, 0x00, 0xFF, 0xFF]);
+
+        assert_eq!(
+            disassembly(&memory, 0x200, 8),
+            "200  00E0  CLS\n202  1200  JP 0x200\n"
+        );
+    }
+}
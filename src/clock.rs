@@ -0,0 +1,138 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Time between frames at 60Hz
+pub const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+// A source of time for pacing frames, so the pacing can run against a
+// virtual clock in tests rather than waiting on the wall clock
+pub trait Clock {
+    // Time since some fixed point, only differences between calls matter
+    fn now(&self) -> Duration;
+    fn sleep(&mut self, duration: Duration);
+}
+
+pub struct RealClock {
+    start: Instant,
+}
+
+impl RealClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for RealClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+// Time that only moves when told to, sleeping returns straight away
+#[derive(Default)]
+pub struct VirtualClock {
+    now: Duration,
+}
+
+impl VirtualClock {
+    pub fn advance(&mut self, duration: Duration) {
+        self.now += duration;
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        self.now
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+// Decides when the next 60Hz frame is due
+pub struct Pacer<C: Clock> {
+    clock: C,
+    last: Duration,
+    // Frames that were due while the host was stalled and never ran
+    dropped: u64,
+}
+
+impl<C: Clock> Pacer<C> {
+    pub fn new(clock: C) -> Self {
+        let last = clock.now();
+
+        Self {
+            clock,
+            last,
+            dropped: 0,
+        }
+    }
+
+    pub fn clock(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
+    // True once a frame's worth of time has passed since the last frame,
+    // which then counts as starting now
+    pub fn frame_due(&mut self) -> bool {
+        let now = self.clock.now();
+
+        let elapsed = now - self.last;
+
+        if elapsed >= FRAME_TIME {
+            self.dropped += (elapsed.as_nanos() / FRAME_TIME.as_nanos()) as u64 - 1;
+            self.last = now;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    // Sleep until the next frame is due
+    pub fn wait(&mut self) {
+        let elapsed = self.clock.now() - self.last;
+        self.clock.sleep(FRAME_TIME.saturating_sub(elapsed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paces_frames_at_60hz() {
+        let mut pacer = Pacer::new(VirtualClock::default());
+
+        assert!(!pacer.frame_due());
+
+        pacer.clock().advance(FRAME_TIME / 2);
+        assert!(!pacer.frame_due());
+
+        pacer.wait();
+        assert!(pacer.frame_due());
+        assert!(!pacer.frame_due());
+
+        // a stall delays the following frames rather than bunching them up
+        pacer.clock().advance(FRAME_TIME * 5);
+        assert!(pacer.frame_due());
+        assert!(!pacer.frame_due());
+        assert_eq!(pacer.dropped(), 4);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::display::Display;
+
+// Longest a picture is held, so a ROM that erases sprites without drawing
+// them again still shows it soon after
+const MAX_HELD_FRAMES: u32 = 8;
+// Sprites remembered as drawn, to recognise them being erased
+const MAX_DRAWN: usize = 16;
+
+// A sprite as `(x, y, I, rows)`, which is the same when a ROM draws it
+// again to erase it
+pub type Sprite = (u8, u8, u32, u8);
+
+// Hides the flicker of sprites being erased and drawn again to move or
+// animate them, which with one sprite drawn a frame shows them missing for
+// a frame or more. When a sprite is drawn again with the same position,
+// address and size as one drawn before, it's taken as erased, and the
+// window keeps showing the display from before the erase until as many
+// new sprites have been drawn. Only what's shown changes, the machine
+// runs exactly as it would without it.
+#[derive(Default)]
+pub struct Coalescer {
+    drawn: Vec<Sprite>,
+    held: Option<Display>,
+    frames: u32,
+    erased: u32,
+    redrawn: u32,
+}
+
+impl Coalescer {
+    // Before each sprite is drawn, with the display as it is
+    pub fn sprite(&mut self, sprite: Sprite, display: &Display) {
+        if let Some(position) = self.drawn.iter().position(|&drawn| drawn == sprite) {
+            self.drawn.remove(position);
+
+            if self.held.is_none() {
+                self.held = Some(display.clone());
+                self.frames = 0;
+            }
+
+            self.erased += 1;
+        } else {
+            if self.drawn.len() == MAX_DRAWN {
+                self.drawn.remove(0);
+            }
+
+            self.drawn.push(sprite);
+
+            if self.held.is_some() {
+                self.redrawn += 1;
+            }
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        if self.held.is_none() {
+            return;
+        }
+
+        self.frames += 1;
+
+        if self.redrawn >= self.erased || self.frames >= MAX_HELD_FRAMES {
+            self.held = None;
+            self.erased = 0;
+            self.redrawn = 0;
+        }
+    }
+
+    // What the window should show in place of the display
+    pub fn shown<'a>(&'a self, display: &'a Display) -> &'a Display {
+        self.held.as_ref().unwrap_or(display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::Resolution;
+
+    #[test]
+    fn holds_the_display_until_erased_sprites_are_redrawn() {
+        let mut display = Display::new(Resolution::LORES);
+        let mut coalescer = Coalescer::default();
+
+        coalescer.sprite((0, 0, 0x300, 1), &display);
+        display.draw(0, 0, &[0x80]);
+        coalescer.end_frame();
+
+        // erased, so the frame still shows it
+        coalescer.sprite((0, 0, 0x300, 1), &display);
+        display.draw(0, 0, &[0x80]);
+        coalescer.end_frame();
+
+        assert_eq!(coalescer.shown(&display).buffer()[0], 1);
+
+        coalescer.sprite((1, 0, 0x300, 1), &display);
+        display.draw(1, 0, &[0x80]);
+        coalescer.end_frame();
+
+        assert_eq!(coalescer.shown(&display).buffer()[..2], [0, 1]);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::device::{Config, Device};
+use crate::display::{Display, Resolution};
+use crate::png;
+
+use std::path::Path;
+
+#[derive(Clone, Copy, Default)]
+pub enum Layout {
+    // Both displays next to each other, A on the left
+    #[default]
+    SideBySide,
+    // One display with pixels lit in only one run coloured by run
+    Difference,
+}
+
+impl Layout {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "side" => Self::SideBySide,
+            "diff" => Self::Difference,
+            _ => panic!("Unknown layout '{}', expected side or diff", value),
+        }
+    }
+
+    fn size(&self) -> (usize, usize) {
+        match self {
+            Self::SideBySide => (PANEL.width * 2 + GAP, PANEL.height),
+            Self::Difference => (PANEL.width, PANEL.height),
+        }
+    }
+
+    // Indices into `PALETTE` for every position in the frame
+    fn compose(&self, a: &Display, b: &Display) -> Vec<u8> {
+        let (width, height) = self.size();
+        let mut frame = vec![0; width * height];
+
+        for (index, color) in frame.iter_mut().enumerate() {
+            let (x, y) = (index % width, index / width);
+
+            *color = match self {
+                Self::SideBySide if x < PANEL.width => u8::from(lit(a, x, y)),
+                Self::SideBySide if x < PANEL.width + GAP => SEPARATOR,
+                Self::SideBySide => u8::from(lit(b, x - PANEL.width - GAP, y)),
+                Self::Difference => match (lit(a, x, y), lit(b, x, y)) {
+                    (true, true) => BOTH,
+                    (true, false) => ONLY_A,
+                    (false, true) => ONLY_B,
+                    (false, false) => 0,
+                },
+            };
+        }
+
+        frame
+    }
+}
+
+// Every display is scaled up to hires so lores and hires runs line up
+const PANEL: Resolution = Resolution::HIRES;
+const GAP: usize = 2;
+
+const BOTH: u8 = 1;
+const ONLY_A: u8 = 2;
+const ONLY_B: u8 = 3;
+const SEPARATOR: u8 = 2;
+
+// Black, white, then red and cyan for the difference layout (the side by
+// side layout uses red as its separator)
+const PALETTE: [u8; 12] = [
+    0x00, 0x00, 0x00, //
+    0xFF, 0xFF, 0xFF, //
+    0xFF, 0x40, 0x40, //
+    0x40, 0xE0, 0xFF, //
+];
+
+fn lit(display: &Display, x: usize, y: usize) -> bool {
+    let Resolution { width, height } = display.resolution();
+    let (x, y) = (x * width / PANEL.width, y * height / PANEL.height);
+
+    display.buffer()[x + y * width] != 0
+}
+
+// Run a ROM under two configurations side by side and encode the result as
+// an animated PNG at 60fps, so the effect of a quirk can be watched and
+// shared. Identical consecutive frames are merged into one longer frame.
+pub fn compare(path: &Path, a: Config, b: Config, frames: u32, layout: Layout) -> Vec<u8> {
+    let mut a = Device::new(a);
+    let mut b = Device::new(b);
+
+    a.load(path);
+    b.load(path);
+
+    // each entry is a frame and how many 60Hz ticks it is shown for
+    let mut video: Vec<(Vec<u8>, u16)> = Vec::new();
+
+    for _ in 0..frames.max(1) {
+        a.run_frame();
+        b.run_frame();
+
+        let frame = layout.compose(a.display(), b.display());
+
+        match video.last_mut() {
+            Some((last, ticks)) if *last == frame && *ticks < u16::MAX => *ticks += 1,
+            _ => video.push((frame, 1)),
+        }
+    }
+
+    encode(layout.size(), &video)
+}
+
+fn encode((width, height): (usize, usize), video: &[(Vec<u8>, u16)]) -> Vec<u8> {
+    let mut image = png::SIGNATURE.to_vec();
+
+    png::chunk(
+        &mut image,
+        b"IHDR",
+        &png::header(width as u32, height as u32, 2, png::INDEXED),
+    );
+    png::chunk(&mut image, b"PLTE", &PALETTE);
+
+    // frame count, then loop forever
+    let mut control = (video.len() as u32).to_be_bytes().to_vec();
+    control.extend_from_slice(&0u32.to_be_bytes());
+    png::chunk(&mut image, b"acTL", &control);
+
+    let mut sequence = 0u32;
+
+    for (index, (frame, ticks)) in video.iter().enumerate() {
+        let mut frame_control = sequence.to_be_bytes().to_vec();
+        frame_control.extend_from_slice(&(width as u32).to_be_bytes());
+        frame_control.extend_from_slice(&(height as u32).to_be_bytes());
+        // offset
+        frame_control.extend_from_slice(&[0; 8]);
+        // delay of `ticks` 60ths of a second, no disposal or blending
+        frame_control.extend_from_slice(&ticks.to_be_bytes());
+        frame_control.extend_from_slice(&60u16.to_be_bytes());
+        frame_control.extend_from_slice(&[0, 0]);
+
+        png::chunk(&mut image, b"fcTL", &frame_control);
+        sequence += 1;
+
+        let data = png::zlib_stored(&png::scanlines(frame.chunks(width), 2));
+
+        // the first frame doubles as the still image for non-animated viewers
+        if index == 0 {
+            png::chunk(&mut image, b"IDAT", &data);
+        } else {
+            let mut frame_data = sequence.to_be_bytes().to_vec();
+            frame_data.extend_from_slice(&data);
+
+            png::chunk(&mut image, b"fdAT", &frame_data);
+            sequence += 1;
+        }
+    }
+
+    png::chunk(&mut image, b"IEND", &[]);
+
+    image
+}
@@ -0,0 +1,79 @@
+use log::info;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Violation {
+    // An instruction that only exists on later interpreters (SCHIP, XO-CHIP)
+    ExtendedInstruction,
+    // A 0NNN call into 1802 machine code
+    MachineRoutine,
+    // A memory write below 0x200, into the interpreter's own area
+    InterpreterWrite,
+    // VF used as an operand of an ALU instruction that also sets VF
+    FlagOperand,
+    // Subroutines nested deeper than the VIP's 12 stack entries
+    StackDepth,
+}
+
+impl Violation {
+    fn describe(&self) -> &'static str {
+        match self {
+            Self::ExtendedInstruction => "instruction not supported by the COSMAC VIP",
+            Self::MachineRoutine => "call to 1802 machine routine",
+            Self::InterpreterWrite => "write to interpreter memory (below 0x200)",
+            Self::FlagOperand => "VF used as operand of a flag-setting instruction",
+            Self::StackDepth => "subroutines nested deeper than 12 levels",
+        }
+    }
+}
+
+struct Occurrence {
+    count: usize,
+    address: u16,
+    opcode: u16,
+}
+
+// Collects every behaviour of a ROM that falls outside of what the original
+// COSMAC VIP interpreter guarantees
+pub struct Report {
+    rom: String,
+    violations: BTreeMap<Violation, Occurrence>,
+}
+
+impl Report {
+    pub fn new(rom: &str) -> Self {
+        Self {
+            rom: rom.to_string(),
+            violations: BTreeMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, violation: Violation, address: u16, opcode: u16) {
+        self.violations
+            .entry(violation)
+            .or_insert(Occurrence {
+                count: 0,
+                address,
+                opcode,
+            })
+            .count += 1;
+    }
+
+    pub fn print(&self) {
+        info!("Conformance report for '{}'", self.rom);
+
+        if self.violations.is_empty() {
+            info!("  No issues found");
+        }
+
+        for (violation, occurrence) in &self.violations {
+            info!(
+                "  {} ({} times, first at {:03x}: {:04x})",
+                violation.describe(),
+                occurrence.count,
+                occurrence.address,
+                occurrence.opcode,
+            );
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use chip8::clipboard::Selection;
+use chip8::device::Event;
+use chip8::editor::Edit;
+use chip8::font::Font;
+use chip8::quirks::Quirks;
+
+use log::warn;
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+// Reads commands from stdin while the emulator is running, so settings can
+// be changed without restarting the ROM:
+//
+//     quirk <name> [on|off]    set a quirk, or flip it if no value is given
+//     quirks                   list the quirk names
+//     font [name]              load a font, or the next one
+//     pause / resume           stop and restart execution
+//     mem <addr> [len]         show memory as hex and ASCII
+//     poke <addr> <bytes>      write hex bytes or a "quoted" string
+//     undo                     revert the last poke
+//     copy mem <addr> <len>    copy memory as hex to the clipboard
+//     copy dis <addr> [count]  copy a disassembly
+//     copy regs                copy the registers
+//     copy frame               copy the display as an image
+//     export <path>            save the machine state in Octo's shape
+//     import <path>            load a state exported here or from Octo
+//     unknown                  list unknown opcodes skipped by --lenient
+//     ops [search|opcode]      look up the platform's instructions
+//
+// With `control` set, for driving the emulator from a shell script:
+//
+//     step [count]             pause and run one or more instructions
+//     key <hex> down|up        press or release a keypad key
+//     screenshot [path]        save a screenshot as a PNG
+//     quit                     close the emulator, as does the end of stdin
+pub fn spawn(channel: Sender<Event>, control: bool) {
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            let event = match words.as_slice() {
+                ["step" | "key" | "screenshot" | "quit", ..] if !control => {
+                    warn!("'{}' needs --stdin-control", words[0]);
+                    continue;
+                }
+                ["step"] => Event::Step(1),
+                ["step", count] => match count.parse() {
+                    Ok(count) => Event::Step(count),
+                    Err(_) => {
+                        warn!("Invalid count '{}'", count);
+                        continue;
+                    }
+                },
+                ["key", key, state @ ("down" | "up")] => {
+                    match u8::from_str_radix(key, 16).ok().filter(|&key| key < 16) {
+                        Some(key) => Event::Key(key, *state == "down"),
+                        None => {
+                            warn!("Invalid key '{}'", key);
+                            continue;
+                        }
+                    }
+                }
+                ["screenshot"] => Event::Screenshot(None),
+                ["screenshot", path] => Event::Screenshot(Some(PathBuf::from(path))),
+                ["quit"] => break,
+                ["pause"] => Event::Pause(true),
+                ["resume"] => Event::Pause(false),
+                ["undo"] => Event::Memory(Edit::Undo),
+                ["unknown"] => Event::UnknownOpcodes,
+                ["ops", search @ ..] => Event::Reference(search.join(" ")),
+                ["export", path] => Event::Export(PathBuf::from(path)),
+                ["import", path] => Event::Import(PathBuf::from(path)),
+                ["copy", "regs"] => Event::Copy(Selection::Registers),
+                ["copy", "frame"] => Event::Copy(Selection::Frame),
+                ["copy", "mem", address, len] => match (address_of(address), len.parse()) {
+                    (Some(address), Ok(len)) => Event::Copy(Selection::Memory(address, len)),
+                    _ => {
+                        warn!("Invalid length '{}'", len);
+                        continue;
+                    }
+                },
+                ["copy", "dis", address, rest @ ..] => {
+                    let count = match rest {
+                        [] => Some(16),
+                        [count] => count.parse().ok(),
+                        _ => None,
+                    };
+
+                    match (address_of(address), count) {
+                        (Some(address), Some(count)) => {
+                            Event::Copy(Selection::Disassembly(address, count))
+                        }
+                        _ => {
+                            warn!("Invalid command '{}'", line);
+                            continue;
+                        }
+                    }
+                }
+                ["mem", address] => match address_of(address) {
+                    Some(address) => Event::Memory(Edit::Show(address, 64)),
+                    None => continue,
+                },
+                ["mem", address, len] => match (address_of(address), len.parse()) {
+                    (Some(address), Ok(len)) => Event::Memory(Edit::Show(address, len)),
+                    _ => {
+                        warn!("Invalid length '{}'", len);
+                        continue;
+                    }
+                },
+                ["poke", address, ..] => {
+                    // keep spaces inside quoted strings
+                    let bytes = line.trim_start()[4..].trim_start()[address.len()..].trim();
+
+                    match (address_of(address), Edit::parse_bytes(bytes)) {
+                        (Some(address), Some(bytes)) => Event::Memory(Edit::Write(address, bytes)),
+                        (Some(_), None) => {
+                            warn!(
+                                "Invalid bytes '{}', expected hex or a \"quoted\" string",
+                                bytes
+                            );
+                            continue;
+                        }
+                        _ => continue,
+                    }
+                }
+                [] => continue,
+                ["font"] => Event::Font(None),
+                ["font", name] => match Font::ALL.into_iter().find(|font| font.name() == *name) {
+                    Some(font) => Event::Font(Some(font)),
+                    None => {
+                        warn!("Unknown font '{}'", name);
+                        continue;
+                    }
+                },
+                ["quirks"] => {
+                    println!("{}", Quirks::NAMES.join(" "));
+                    continue;
+                }
+                ["quirk", name] => Event::Quirk(name.to_string(), None),
+                ["quirk", name, "on"] => Event::Quirk(name.to_string(), Some(true)),
+                ["quirk", name, "off"] => Event::Quirk(name.to_string(), Some(false)),
+                _ => {
+                    warn!("Unknown command '{}'", line);
+                    continue;
+                }
+            };
+
+            if channel.send(event).is_err() {
+                return;
+            }
+        }
+
+        if control {
+            channel.send(Event::Off).ok();
+        }
+    });
+}
+
+// A hex address, with or without a `0x` prefix
+fn address_of(text: &str) -> Option<u16> {
+    let address = u16::from_str_radix(text.trim_start_matches("0x"), 16)
+        .ok()
+        .filter(|&address| address < 0x1000);
+
+    if address.is_none() {
+        warn!("Invalid address '{}'", text);
+    }
+
+    address
+}
@@ -1,19 +1,510 @@
+use crate::banks::{Banks, BANK_SIZE, WINDOW};
+use crate::clipboard::{self, Selection};
+use crate::clock::{Clock, Pacer, RealClock};
+use crate::coalesce::Coalescer;
+use crate::conformance::{Report, Violation};
+use crate::disasm;
+use crate::display::{Display, Resolution, RgbaImage};
+use crate::editor::{Edit, Editor};
+use crate::font::{self, Font};
+use crate::hash::crc32;
+use crate::input::{InputLog, Playback};
+use crate::instructions::Table;
+use crate::ips;
+use crate::keypad::{Keypad, DEBOUNCE_FRAMES};
+use crate::known;
+use crate::latency::LatencyTest;
+use crate::megachip::Blend;
+use crate::memory::{MemoryMap, BIG_FONT_ADDRESS, PROGRAM_ADDRESS};
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::octo::Machine;
+use crate::palette::Palette;
+use crate::platform::Platform;
+use crate::profiles::Profiles;
+use crate::quirks::{Quirks, Sensitivity};
+use crate::recorder::Recorder;
+use crate::rng::Rng;
 use crate::screen::Screen;
+use crate::slots::{self, Slot};
+use crate::state::{self, Reader, Writer};
+use crate::stats::{BatchEnd, BudgetStats, FlickerStats, UnknownOpcodes};
+use crate::storage::Storage;
+use crate::stream::HashStream;
+use crate::timing;
+use crate::trace::{Filter, Tracer};
+use crate::tracedb::{Input, Keyframe, Settings, TraceDbWriter, Watch, Write};
+use crate::tutorial;
 
-use log::info;
-use std::fs::File;
-use std::io::Read;
+use log::{error, info, warn};
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::TryRecvError;
 use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 use winit::window::Window;
 
 pub enum Event {
-    On(Arc<Window>),
+    // The window and the scale it was opened at
+    On(Arc<Window>, u32),
     Key(u8, bool),
+    // When the window saw the key sent next, for `chip8 latency`
+    Probe(Instant),
+    // A key on CHIP-8X's second keypad
+    SecondKey(u8, bool),
+    Resize(u32, u32),
+    // The window gained or lost focus
+    Focus(bool),
+    // A left click at a position in the window
+    Click(f64, f64),
+    SaveState,
+    LoadState,
+    // Show or hide the strip of save slots
+    Slots(bool),
+    // Move the selection along the strip of save slots
+    SelectSlot(i8),
+    // Go back to the last automatic checkpoint
+    Retry,
+    RecordMacro,
+    PlayMacro,
+    // Save a screenshot, to the data directory unless a path is given
+    Screenshot(Option<PathBuf>),
+    // Write the machine state to a file in Octo's shape, or read one back
+    Export(PathBuf),
+    Import(PathBuf),
+    // Pause and run this many instructions
+    Step(u32),
+    CancelWait(u8),
+    // Set a quirk by name, or flip it when no value is given
+    Quirk(String, Option<bool>),
+    // Stop running instructions, or carry on again
+    Pause(bool),
+    Memory(Edit),
+    Copy(Selection),
+    // List the unknown opcodes skipped in lenient mode
+    UnknownOpcodes,
+    // List the platform's instructions that mention some text, or the one
+    // that runs a 4 digit opcode
+    Reference(String),
+    // Show how to play the ROM, if the ROM database knows
+    Controls,
+    // The window switched to the named keymap
+    Keymap(String),
+    // Load another font, or the next one
+    Font(Option<Font>),
+    // Stop this ROM and move on to the next one in the queue
+    Next,
     Off,
 }
 
+// Why `Device::run` returned
+#[derive(PartialEq, Eq)]
+pub enum Stop {
+    Next,
+    Off,
+    // The ROM executed `00FD` with `ExitAction::Close`
+    Exit,
+}
+
+#[derive(Clone, Copy, Default)]
+pub enum ExitAction {
+    // Stop executing but keep showing the last frame
+    #[default]
+    Halt,
+    // Shut down the emulator
+    Close,
+}
+
+#[derive(Clone, Copy, Default)]
+pub enum StackOverflow {
+    // Stop executing, as the original interpreters would crash
+    #[default]
+    Halt,
+    // Wrap around and overwrite the oldest return address, for homebrew
+    // that jumps out of subroutines without returning
+    Wrap,
+}
+
+impl StackOverflow {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "halt" => Self::Halt,
+            "wrap" => Self::Wrap,
+            _ => panic!("Unknown stack overflow '{}', expected halt or wrap", value),
+        }
+    }
+}
+
+// Timer values after a 60Hz tick, passed to the hook set with
+// `Device::on_timer`
+#[derive(Clone, Copy, Debug)]
+pub struct TimerTick {
+    pub frame: u64,
+    pub delay: u8,
+    pub sound: u8,
+}
+
+// A `0NNN` call into 1802 machine code, passed to the hook set with
+// `Device::on_machine_routine` along with the parts of the machine a
+// routine could change
+pub struct MachineCall<'a> {
+    pub address: u16,
+    pub registers: &'a mut [u8; 16],
+    pub memory: &'a mut [u8],
+    pub display: &'a mut Display,
+}
+
+type RoutineHook = dyn FnMut(MachineCall) -> bool + Send;
+type KeyHook = dyn FnMut(u8, bool) -> Option<u8> + Send;
+
+// What the sound timer plays while it's running
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tone {
+    // The single beep of CHIP-8 and SCHIP, its pitch left to the host
+    Beep,
+    // XO-CHIP's 128 one bit samples, played on a loop at a pitch set by
+    // `FX3A`
+    Pattern { samples: [u8; 16], pitch: u8 },
+}
+
+impl Tone {
+    // Samples of a pattern played per second, 4000Hz at the default pitch
+    pub fn rate(pitch: u8) -> f64 {
+        4000.0 * 2f64.powf((f64::from(pitch) - 64.0) / 48.0)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Config {
+    pub exit_action: ExitAction,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub strict: bool,
+    pub storage: Storage,
+    pub record: Option<PathBuf>,
+    pub flicker_stats: bool,
+    // Keep showing sprites while they're erased and drawn again
+    pub coalesce_draws: bool,
+    pub slow_memory: bool,
+    // Charge each instruction the machine cycles it takes on the COSMAC
+    // VIP, instead of running a fixed number of them per frame
+    pub vip_timing: bool,
+    // Run 2 instructions a second, explaining each one on stdout
+    pub tutorial: bool,
+    // Light the display while a key is held, and print how long each key
+    // took to show and where the time went
+    pub latency_test: bool,
+    pub patch: Option<PathBuf>,
+    pub rng: Rng,
+    pub budget_stats: bool,
+    pub banking: bool,
+    // Frames before a key change is visible to the machine
+    pub key_latency: u64,
+    pub palette: Palette,
+    // Hex digits loaded for FX29, the platform's own when unset
+    pub font: Option<Font>,
+    // Scale of screenshots, and of recorded frames when set
+    pub capture_scale: Option<usize>,
+    // Return addresses the stack holds, `DEFAULT_STACK_DEPTH` when unset
+    pub stack_depth: Option<usize>,
+    // Bytes of memory, the platform's own amount when unset
+    pub memory_size: Option<usize>,
+    // Where the ROM is loaded and run from, the platform's own start
+    // address when unset. ETI-660 programs start at 0x600.
+    pub start_address: Option<u16>,
+    pub stack_overflow: StackOverflow,
+    // Seconds between automatic checkpoints that `Retry` goes back to
+    pub checkpoint_interval: Option<u64>,
+    // Sleep between frames instead of polling, and don't redraw an
+    // unfocused window unless the display changed
+    pub low_power: bool,
+    // Skip unknown opcodes instead of stopping, summarising them at the end
+    pub lenient: bool,
+    // Apply quirks saved for the ROM by `chip8 wizard`, or recommended for
+    // it by the ROM database, when loading it
+    pub saved_quirks: bool,
+    // Switch to the platform the ROM is known to be for when loading it
+    pub detect_platform: bool,
+    // Ignore quirks and controls saved under the ROM's file name when the
+    // ROM doesn't match the hash they were saved for
+    pub verify_rom: bool,
+    // Log executed instructions matching `trace_filter` to this file
+    pub trace: Option<PathBuf>,
+    pub trace_filter: Filter,
+    // Record the session to this file, for `TraceDb` to answer questions
+    // about it by replaying it
+    pub trace_db: Option<PathBuf>,
+    // Write each frame's display hash to stdout (`-`) or a `host:port`,
+    // with a hash of the whole machine state too if `hash_state` is set
+    pub hash_stream: Option<String>,
+    pub hash_state: bool,
+    // Serve Prometheus metrics on this address
+    #[cfg(feature = "metrics")]
+    pub metrics: Option<String>,
+}
+
+// Instructions are costed in units so memory wait states can be charged
+// as a fraction of an instruction
+const INSTRUCTION_COST: u32 = 8;
+const WAIT_STATE_COST: u32 = 1;
+const FRAME_BUDGET: u32 = 12 * INSTRUCTION_COST;
+
+const SCREENSHOT_SCALE: usize = 10;
+
+pub const DEFAULT_STACK_DEPTH: usize = 16;
+pub const DEFAULT_START_ADDRESS: u16 = PROGRAM_ADDRESS as u16;
+
+// Where hi-res CHIP-8 programs start, past their patch to the interpreter
+const HIRES_ENTRY: u16 = 0x2C0;
+// Depth of the COSMAC VIP's stack, anything deeper is reported by --strict
+const VIP_STACK_DEPTH: usize = 12;
+// XO-CHIP pitch for 4000Hz playback of the audio pattern
+const DEFAULT_PITCH: u8 = 64;
+
+type Handler = fn(&mut Device, Opcode);
+
+// The operands of a custom instruction's opcode and the parts of the
+// machine it can change
+pub struct Cpu<'a> {
+    pub opcode: u16,
+    pub x: u8,
+    pub y: u8,
+    pub n: u8,
+    pub kk: u8,
+    pub nnn: u16,
+    pub registers: &'a mut [u8; 16],
+    pub i: &'a mut u32,
+    pub pc: &'a mut u16,
+    pub memory: &'a mut [u8],
+    pub display: &'a mut Display,
+}
+
+// An opcode and what runs it, the opcode written like `8XY4` where hex
+// digits have to match and anything else is an operand
+#[derive(Clone, Copy)]
+pub struct Instruction {
+    code: &'static str,
+    // For the console's `ops`, when it isn't one the disassembler knows
+    summary: &'static str,
+    run: Run,
+}
+
+#[derive(Clone, Copy)]
+enum Run {
+    Builtin(Handler),
+    Custom(fn(Cpu)),
+}
+
+impl Instruction {
+    // An instruction of an embedder's own, for ROM experiments
+    pub fn new(code: &'static str, summary: &'static str, run: fn(Cpu)) -> Self {
+        if code.len() != 4 {
+            panic!("Instruction '{}' isn't 4 characters", code);
+        }
+
+        Self {
+            code,
+            summary,
+            run: Run::Custom(run),
+        }
+    }
+
+    const fn builtin(code: &'static str, handler: Handler) -> Self {
+        Self {
+            code,
+            summary: "",
+            run: Run::Builtin(handler),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+}
+
+// A group of instructions that a platform is built from, such as SCHIP's
+// on top of CHIP-8's. Each platform runs its sets in order, the first
+// instruction matching an opcode taking it, and embedders can put sets of
+// their own ahead of them with `Device::extend`.
+pub trait InstructionSet {
+    fn name(&self) -> &'static str;
+    fn instructions(&self) -> Vec<Instruction>;
+}
+
+// CHIP-8 as on the COSMAC VIP, which every platform builds on. `0NNN`
+// calls a machine code routine.
+#[rustfmt::skip]
+const CHIP8: &[Instruction] = &[
+    Instruction::builtin("00E0", |device, _| device.op_00e0()),
+    Instruction::builtin("00EE", |device, _| device.op_00ee()),
+    Instruction::builtin("0NNN", |device, op| device.op_0nnn(op.nnn)),
+    Instruction::builtin("1NNN", |device, op| device.op_1nnn(op.nnn)),
+    Instruction::builtin("2NNN", |device, op| device.op_2nnn(op.nnn)),
+    Instruction::builtin("3XKK", |device, op| device.op_3xkk(op.x, op.kk)),
+    Instruction::builtin("4XKK", |device, op| device.op_4xkk(op.x, op.kk)),
+    Instruction::builtin("5XY0", |device, op| device.op_5xy0(op.x, op.y)),
+    Instruction::builtin("6XKK", |device, op| device.op_6xkk(op.x, op.kk)),
+    Instruction::builtin("7XKK", |device, op| device.op_7xkk(op.x, op.kk)),
+    Instruction::builtin("8XY0", |device, op| device.op_8xy0(op.x, op.y)),
+    Instruction::builtin("8XY1", |device, op| device.op_8xy1(op.x, op.y)),
+    Instruction::builtin("8XY2", |device, op| device.op_8xy2(op.x, op.y)),
+    Instruction::builtin("8XY3", |device, op| device.op_8xy3(op.x, op.y)),
+    Instruction::builtin("8XY4", |device, op| device.op_8xy4(op.x, op.y)),
+    Instruction::builtin("8XY5", |device, op| device.op_8xy5(op.x, op.y)),
+    Instruction::builtin("8XY6", |device, op| device.op_8xy6(op.x, op.y)),
+    Instruction::builtin("8XY7", |device, op| device.op_8xy7(op.x, op.y)),
+    Instruction::builtin("8XYE", |device, op| device.op_8xye(op.x, op.y)),
+    Instruction::builtin("9XY0", |device, op| device.op_9xy0(op.x, op.y)),
+    Instruction::builtin("ANNN", |device, op| device.op_annn(op.nnn)),
+    Instruction::builtin("BNNN", |device, op| device.op_bnnn(op.nnn)),
+    Instruction::builtin("CXKK", |device, op| device.op_cxkk(op.x, op.kk)),
+    Instruction::builtin("DXYN", |device, op| device.op_dxyn(op.x, op.y, op.n)),
+    Instruction::builtin("EX9E", |device, op| device.op_ex9e(op.x)),
+    Instruction::builtin("EXA1", |device, op| device.op_exa1(op.x)),
+    Instruction::builtin("FX07", |device, op| device.op_fx07(op.x)),
+    Instruction::builtin("FX0A", |device, op| device.op_fx0a(op.x)),
+    Instruction::builtin("FX15", |device, op| device.op_fx15(op.x)),
+    Instruction::builtin("FX18", |device, op| device.op_fx18(op.x)),
+    Instruction::builtin("FX1E", |device, op| device.op_fx1e(op.x)),
+    Instruction::builtin("FX29", |device, op| device.op_fx29(op.x)),
+    Instruction::builtin("FX33", |device, op| device.op_fx33(op.x)),
+    Instruction::builtin("FX55", |device, op| device.op_fx55(op.x)),
+    Instruction::builtin("FX65", |device, op| device.op_fx65(op.x)),
+];
+
+// The VIP's hi-res CHIP-8 clears its two page display with its own routine
+#[rustfmt::skip]
+const HIRES_CHIP8: &[Instruction] = &[
+    Instruction::builtin("0230", |device, _| device.op_0230()),
+];
+
+#[rustfmt::skip]
+const CHIP8X: &[Instruction] = &[
+    Instruction::builtin("02A0", |device, _| device.op_02a0()),
+    Instruction::builtin("5XY1", |device, op| device.op_5xy1(op.x, op.y)),
+    Instruction::builtin("BXYN", |device, op| device.op_bxyn(op.x, op.y, op.n)),
+    Instruction::builtin("EXF2", |device, op| device.op_exf2(op.x)),
+    Instruction::builtin("EXF5", |device, op| device.op_exf5(op.x)),
+    Instruction::builtin("FXF8", |device, op| device.op_fxf8(op.x)),
+];
+
+// SCHIP 1.1's scrolling, hires mode, large digits and RPL flags
+#[rustfmt::skip]
+const SCHIP: &[Instruction] = &[
+    Instruction::builtin("00CN", |device, op| device.op_00cn(op.n)),
+    Instruction::builtin("00FB", |device, _| device.op_00fb()),
+    Instruction::builtin("00FC", |device, _| device.op_00fc()),
+    Instruction::builtin("00FD", |device, _| device.op_00fd()),
+    Instruction::builtin("00FE", |device, _| device.op_00fe()),
+    Instruction::builtin("00FF", |device, _| device.op_00ff()),
+    Instruction::builtin("DXY0", |device, op| device.op_dxyn(op.x, op.y, op.n)),
+    Instruction::builtin("FX30", |device, op| device.op_fx30(op.x)),
+    Instruction::builtin("FX75", |device, op| device.op_fx75(op.x)),
+    Instruction::builtin("FX85", |device, op| device.op_fx85(op.x)),
+];
+
+#[rustfmt::skip]
+const XO_CHIP: &[Instruction] = &[
+    Instruction::builtin("00DN", |device, op| device.op_00dn(op.n)),
+    Instruction::builtin("5XY2", |device, op| device.op_5xy2(op.x, op.y)),
+    Instruction::builtin("5XY3", |device, op| device.op_5xy3(op.x, op.y)),
+    Instruction::builtin("F000", |device, _| device.op_f000()),
+    Instruction::builtin("FX01", |device, op| device.op_fn01(op.x)),
+    Instruction::builtin("F002", |device, _| device.op_f002()),
+    Instruction::builtin("FX3A", |device, op| device.op_fx3a(op.x)),
+];
+
+#[rustfmt::skip]
+const MEGA_CHIP: &[Instruction] = &[
+    Instruction::builtin("0010", |device, _| device.op_0010()),
+    Instruction::builtin("0011", |device, _| device.op_0011()),
+    Instruction::builtin("00BN", |device, op| device.op_00bn(op.n)),
+    Instruction::builtin("01NN", |device, op| device.op_01nn(op.kk)),
+    Instruction::builtin("02NN", |device, op| device.op_02nn(op.kk)),
+    Instruction::builtin("03NN", |device, op| device.op_03nn(op.kk)),
+    Instruction::builtin("04NN", |device, op| device.op_04nn(op.kk)),
+    Instruction::builtin("05NN", |device, op| device.op_05nn(op.kk)),
+    Instruction::builtin("060N", |device, _| device.op_060n()),
+    Instruction::builtin("0700", |device, _| device.op_060n()),
+    Instruction::builtin("080N", |device, op| device.op_080n(op.n)),
+    Instruction::builtin("09NN", |device, op| device.op_09nn(op.kk)),
+];
+
+// The bank switching extension, on any platform
+#[rustfmt::skip]
+const BANKING: &[Instruction] = &[
+    Instruction::builtin("FXB0", |device, op| device.op_fxb0(op.x)),
+];
+
+macro_rules! builtin_sets {
+    ($($set:ident($name:literal, $instructions:ident)),*) => {
+        $(pub struct $set;
+
+        impl InstructionSet for $set {
+            fn name(&self) -> &'static str {
+                $name
+            }
+
+            fn instructions(&self) -> Vec<Instruction> {
+                $instructions.to_vec()
+            }
+        })*
+    };
+}
+
+builtin_sets!(
+    Chip8Set("chip8", CHIP8),
+    HiresChip8Set("chip8-hires", HIRES_CHIP8),
+    Chip8XSet("chip8x", CHIP8X),
+    SchipSet("schip", SCHIP),
+    XoChipSet("xo-chip", XO_CHIP),
+    MegaChipSet("megachip", MEGA_CHIP),
+    BankingSet("banking", BANKING)
+);
+
+// Each platform's own extensions ahead of what it builds on, so they take
+// the opcodes they redefine
+pub fn instruction_sets(platform: Platform) -> Vec<&'static dyn InstructionSet> {
+    match platform {
+        Platform::Chip8 | Platform::Dream6800 => vec![&Chip8Set],
+        Platform::HiresChip8 => vec![&HiresChip8Set, &Chip8Set],
+        Platform::Chip8X => vec![&Chip8XSet, &Chip8Set],
+        Platform::Schip => vec![&SchipSet, &Chip8Set],
+        Platform::XoChip => vec![&XoChipSet, &SchipSet, &Chip8Set],
+        Platform::MegaChip => vec![&MegaChipSet, &SchipSet, &Chip8Set],
+    }
+}
+
+// The embedder's instructions, then banking when it's on, then the
+// platform's
+fn instruction_table(
+    platform: Platform,
+    banking: bool,
+    extensions: &[Instruction],
+) -> Table<Instruction> {
+    let banking = banking.then_some(&BankingSet as &dyn InstructionSet);
+    let rows: Vec<_> = extensions
+        .iter()
+        .copied()
+        .chain(
+            banking
+                .into_iter()
+                .chain(instruction_sets(platform))
+                .flat_map(|set| set.instructions()),
+        )
+        .map(|instruction| {
+            let (mask, pattern) = disasm::encoding(instruction.code);
+            (mask, pattern, instruction)
+        })
+        .collect();
+
+    Table::new(&rows)
+}
+
+#[derive(Clone, Copy)]
 struct Opcode {
     raw: u16,
     code: u16,
@@ -25,526 +516,3741 @@ struct Opcode {
 }
 
 pub struct Device {
-    window: Arc<Window>,
-    screen: Screen,
-    memory: [u8; 4096],
+    display: Display,
+    // 4K, or 64K for XO-CHIP, unless `Config::memory_size` says otherwise
+    memory: Vec<u8>,
+    map: MemoryMap,
+    memory_size: Option<usize>,
     registers: [u8; 16],
-    stack: [u16; 16],
+    // SCHIP's RPL user flags, saved and loaded by FX75/FX85. They're kept
+    // out of save states so scores outlast loading a state or retrying, the
+    // way they outlasted the game on the HP48.
+    rpl_flags: [u8; 16],
+    stack: Vec<u16>,
+    stack_overflow: StackOverflow,
     keys: [bool; 16],
+    // CHIP-8X's second keypad, for the other player
+    second_keys: [bool; 16],
+    // Last value sent to CHIP-8X's output port with `FXF8`
+    output_port: u8,
     pc: u16,
+    start_address: u16,
     sp: usize,
-    i: u16,
+    // 24 bits on Mega-CHIP
+    i: u32,
     dt: u8,
     st: u8,
+    sound: bool,
+    // XO-CHIP's 128 bit sample loaded by `F002`, played while the sound
+    // timer runs at a rate set by `FX3A`
+    audio_pattern: [u8; 16],
+    pitch: u8,
+    // Size of Mega-CHIP's colour sprites, set by `03NN` and `04NN`
+    sprite_width: usize,
+    sprite_height: usize,
     wait_key: u8,
     draw_flag: bool,
+    halted: bool,
+    // Set by `00FD` when the emulator should shut down
+    exited: bool,
+    exit_action: ExitAction,
+    platform: Platform,
+    quirks: Quirks,
+    strict: bool,
+    report: Option<Report>,
+    opcode: u16,
+    sensitivities: BTreeSet<Sensitivity>,
+    i_incremented: bool,
+    storage: Storage,
+    rom_name: String,
+    // How to play the loaded ROM, from the ROM database
+    controls: Option<String>,
+    rom_hash: u32,
+    frame: u64,
+    recorder: Option<Recorder>,
+    flicker: Option<FlickerStats>,
+    coalescer: Option<Coalescer>,
+    macro_recording: Option<(u64, InputLog)>,
+    macro_playback: Option<Playback>,
+    slow_memory: bool,
+    memory_accesses: u32,
+    vip_timing: bool,
+    tutorial: bool,
+    latency: Option<LatencyTest>,
+    // The last instruction skipped the one after it
+    skipped: bool,
+    patch: Option<PathBuf>,
+    rng: Rng,
+    budget: Option<BudgetStats>,
+    banking: bool,
+    table: Table<Instruction>,
+    // Instructions added by the embedder with `extend`
+    extensions: Vec<Instruction>,
+    banks: Option<Banks>,
+    keypad: Keypad,
+    key_latency: u64,
+    palette: Palette,
+    font: Option<Font>,
+    capture_scale: Option<usize>,
+    timer_hook: Option<Box<dyn FnMut(TimerTick) + Send>>,
+    routine_hook: Option<Box<RoutineHook>>,
+    key_hook: Option<Box<KeyHook>>,
+    paused: bool,
+    // Save slot that F5 and F9 use, and whether the strip to pick it is up
+    slot: usize,
+    choosing_slot: bool,
+    editor: Editor,
+    checkpoint_interval: Option<u64>,
+    checkpoint: Option<Vec<u8>>,
+    low_power: bool,
+    focused: bool,
+    instructions: u64,
+    saved_quirks: bool,
+    detect_platform: bool,
+    verify_rom: bool,
+    tracer: Option<Tracer>,
+    trace_db: Option<TraceDbWriter>,
+    // Running the inputs of a trace database rather than the host's
+    replaying: bool,
+    watch: Option<Watch>,
+    hash_stream: Option<HashStream>,
+    hash_state: bool,
+    unknown: Option<UnknownOpcodes>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<Metrics>>,
 }
 
 impl Device {
-    pub fn new(window: Arc<Window>) -> Self {
+    pub fn new(config: Config) -> Self {
+        let map = MemoryMap::new(config.platform, config.memory_size);
+
+        // banks are switched into 0x800-0xFFF
+        if config.banking && map.size < 0x1000 {
+            panic!("Banking needs at least 4K of memory");
+        }
+
+        let start_address = config
+            .start_address
+            .unwrap_or(config.platform.start_address());
+
+        if config.banking && usize::from(start_address) >= WINDOW {
+            panic!(
+                "Banking needs a start address below the bank window at {:#x}",
+                WINDOW
+            );
+        }
+
         Self {
-            window: window.clone(),
-            screen: Screen::new(window),
-            memory: [0; 4096],
+            display: config.platform.display(),
+            memory: vec![0; map.size],
+            map,
+            memory_size: config.memory_size,
             registers: [0; 16],
-            stack: [0; 16],
+            rpl_flags: [0; 16],
+            stack: vec![0; config.stack_depth.unwrap_or(DEFAULT_STACK_DEPTH)],
+            stack_overflow: config.stack_overflow,
             keys: [false; 16],
-            pc: 0x200,
+            second_keys: [false; 16],
+            output_port: 0,
+            pc: start_address,
+            start_address,
             sp: 0,
             i: 0,
             dt: 0,
             st: 0,
+            sound: false,
+            audio_pattern: [0; 16],
+            pitch: DEFAULT_PITCH,
+            sprite_width: 8,
+            sprite_height: 8,
             wait_key: 0xFF,
             draw_flag: false,
+            halted: false,
+            exited: false,
+            exit_action: config.exit_action,
+            platform: config.platform,
+            quirks: config.quirks,
+            strict: config.strict,
+            report: None,
+            opcode: 0,
+            sensitivities: BTreeSet::new(),
+            i_incremented: false,
+            storage: config.storage.clone(),
+            rom_name: String::new(),
+            controls: None,
+            rom_hash: 0,
+            frame: 0,
+            recorder: config.record.as_deref().map(|dir| {
+                Recorder::new(
+                    dir,
+                    config.capture_scale.map(|scale| (config.palette, scale)),
+                )
+            }),
+            flicker: config.flicker_stats.then(FlickerStats::new),
+            coalescer: config.coalesce_draws.then(Coalescer::default),
+            macro_recording: None,
+            macro_playback: None,
+            slow_memory: config.slow_memory,
+            memory_accesses: 0,
+            vip_timing: config.vip_timing,
+            tutorial: config.tutorial,
+            latency: config.latency_test.then(LatencyTest::default),
+            skipped: false,
+            patch: config.patch,
+            rng: config.rng,
+            budget: config.budget_stats.then(BudgetStats::new),
+            banking: config.banking,
+            table: instruction_table(config.platform, config.banking, &[]),
+            extensions: Vec::new(),
+            banks: None,
+            keypad: Keypad::new([false; 16]),
+            key_latency: config.key_latency,
+            palette: config.palette,
+            font: config.font,
+            capture_scale: config.capture_scale,
+            timer_hook: None,
+            routine_hook: None,
+            key_hook: None,
+            paused: false,
+            slot: 0,
+            choosing_slot: false,
+            editor: Editor::default(),
+            checkpoint_interval: config.checkpoint_interval,
+            checkpoint: None,
+            low_power: config.low_power,
+            focused: true,
+            instructions: 0,
+            saved_quirks: config.saved_quirks,
+            detect_platform: config.detect_platform,
+            verify_rom: config.verify_rom,
+            tracer: config
+                .trace
+                .as_deref()
+                .map(|path| Tracer::new(path, config.trace_filter.clone())),
+            trace_db: config.trace_db.as_deref().map(TraceDbWriter::new),
+            replaying: false,
+            watch: None,
+            unknown: config.lenient.then(UnknownOpcodes::default),
+            hash_stream: config
+                .hash_stream
+                .as_deref()
+                .map(|target| HashStream::open(target, config.hash_state)),
+            hash_state: config.hash_state,
+            #[cfg(feature = "metrics")]
+            metrics: config.metrics.as_deref().map(Metrics::serve),
+        }
+    }
+
+    // Inspect or change memory, printing the affected bytes afterwards
+    pub fn edit_memory(&mut self, edit: Edit) {
+        self.invalidate_trace();
+
+        let (address, len) = match edit {
+            Edit::Show(address, len) => (address, len),
+            Edit::Write(address, bytes) => {
+                self.editor.write(&mut self.memory, address, &bytes);
+                (address, bytes.len())
+            }
+            Edit::Undo => match self.editor.undo(&mut self.memory) {
+                Some(range) => range,
+                None => {
+                    warn!("Nothing to undo");
+                    return;
+                }
+            },
+        };
+
+        print!("{}", self.editor.show(&self.memory, address, len));
+    }
+
+    // The display in the `--palette` colours with every pixel blown up to a
+    // `scale` by `scale` square
+    pub fn capture_frame(&self, scale: usize) -> RgbaImage {
+        self.display.rgba(&self.palette, scale)
+    }
+
+    // Put part of the machine state on the host clipboard
+    pub fn copy(&self, selection: Selection) {
+        match selection {
+            Selection::Memory(address, len) => {
+                let start = usize::from(address).min(self.memory.len());
+                let end = (start + len).min(self.memory.len());
+
+                clipboard::copy_text("memory", clipboard::hex(&self.memory[start..end], address));
+            }
+            Selection::Disassembly(address, count) => clipboard::copy_text(
+                "disassembly",
+                clipboard::disassembly(&self.memory, address, count),
+            ),
+            Selection::Registers => clipboard::copy_text("registers", self.register_dump()),
+            Selection::Frame => clipboard::copy_image(
+                self.capture_frame(self.capture_scale.unwrap_or(SCREENSHOT_SCALE)),
+            ),
+        }
+    }
+
+    fn register_dump(&self) -> String {
+        let registers: Vec<String> = self
+            .registers
+            .iter()
+            .enumerate()
+            .map(|(index, value)| format!("V{:X}={:02X}", index, value))
+            .collect();
+
+        format!(
+            "{}\nPC={:03X} I={:03X} SP={} DT={:02X} ST={:02X}\n",
+            registers.join(" "),
+            self.pc,
+            self.i,
+            self.sp,
+            self.dt,
+            self.st
+        )
+    }
+
+    // Call `hook` on every 60Hz timer tick, after DT and ST have counted
+    // down, so embedders can keep audio or lights in step with the timers
+    pub fn on_timer(&mut self, hook: impl FnMut(TimerTick) + Send + 'static) {
+        self.timer_hook = Some(Box::new(hook));
+    }
+
+    // Call `hook` for every `0NNN` machine code routine the ROM calls, so
+    // embedders can emulate the routines a ROM needs, such as the VIP's
+    // display off routine, or log them. The hook returns whether it
+    // handled the call; unhandled calls to 0000 are skipped and any others
+    // are unknown opcodes, as they are without a hook.
+    pub fn on_machine_routine(&mut self, hook: impl FnMut(MachineCall) -> bool + Send + 'static) {
+        self.routine_hook = Some(Box::new(hook));
+    }
+
+    // Run a set of instructions of the embedder's own ahead of the
+    // platform's, to try out new ones in ROM experiments. Sets added later
+    // take opcodes from those added before them.
+    pub fn extend(&mut self, set: &dyn InstructionSet) {
+        self.extensions.splice(0..0, set.instructions());
+        self.table = instruction_table(self.platform, self.banking, &self.extensions);
+
+        info!("Added the {} instruction set", set.name());
+    }
+
+    // Called with each keypad key the window presses or releases, returning
+    // the key to send instead or `None` to drop it, for remapping or
+    // filtering input
+    pub fn on_key(&mut self, hook: impl FnMut(u8, bool) -> Option<u8> + Send + 'static) {
+        self.key_hook = Some(Box::new(hook));
+    }
+
+    pub fn load(&mut self, path: &Path) {
+        info!("Loading ROM '{}'", path.display());
+
+        let mut rom = fs::read(path).unwrap();
+        let profiles = Profiles::load(&self.storage);
+        let mut file = path.file_name().map(|name| name.to_string_lossy());
+
+        if let Some(expected) = file
+            .as_deref()
+            .and_then(|file| profiles.mismatch(&rom, file))
+        {
+            warn!(
+                "ROM doesn't match the SHA-1 its settings were saved for, expected {} but it's {}. \
+                 The file may be corrupt or a different version.",
+                expected,
+                Profiles::key(&rom)
+            );
+
+            if self.verify_rom {
+                info!("Not using the settings saved for '{}'", path.display());
+                file = None;
+            }
+        }
+
+        self.controls = profiles.controls(&rom, file.as_deref()).map(str::to_owned);
+
+        let known = known::lookup(&rom);
+
+        if let Some(known) = &known {
+            info!("Recognised '{}' from the ROM database", known.title);
+        }
+
+        if self.detect_platform {
+            if let Some(platform) =
+                Platform::detect(&rom).filter(|&platform| platform != self.platform)
+            {
+                info!("Running it as {}", platform.name());
+                self.set_platform(platform);
+            }
+        }
+
+        if self.saved_quirks {
+            for (name, enabled) in known.iter().flat_map(|known| &known.quirks) {
+                self.quirks.set(name, *enabled);
+            }
+
+            if let Some(quirks) = profiles.get(&rom, file.as_deref()) {
+                info!("Using the quirks saved for this ROM");
+
+                for (name, enabled) in quirks {
+                    self.quirks.set(name, *enabled);
+                }
+            }
+        }
+
+        // Patches are applied in memory only, the ROM file is left untouched
+        if let Some(patch) = &self.patch {
+            info!("Applying patch '{}'", patch.display());
+
+            ips::apply(&mut rom, &fs::read(patch).unwrap())
+                .unwrap_or_else(|err| panic!("Invalid patch '{}': {}", patch.display(), err));
+        }
+
+        self.load_rom(&rom);
+
+        self.rom_name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        if self.strict {
+            self.report = Some(Report::new(&path.display().to_string()));
+        }
+    }
+
+    // Start afresh on another platform, before a ROM is loaded. A start
+    // address other than the old platform's own is kept.
+    fn set_platform(&mut self, platform: Platform) {
+        if self.start_address == self.platform.start_address() {
+            self.start_address = platform.start_address();
+            self.pc = self.start_address;
+        }
+
+        self.platform = platform;
+        self.table = instruction_table(platform, self.banking, &self.extensions);
+        self.display = platform.display();
+        self.map = MemoryMap::new(platform, self.memory_size);
+        self.memory = vec![0; self.map.size];
+    }
+
+    // Panics if the ROM doesn't fit in memory, as running the part that
+    // does would only fail later in ways that are much harder to explain
+    pub fn load_rom(&mut self, rom: &[u8]) {
+        self.invalidate_trace();
+
+        if self.banking {
+            let start = usize::from(self.start_address);
+            let (fixed, banks) = Banks::split(rom, start);
+
+            self.memory[start..start + fixed.len()].copy_from_slice(fixed);
+            banks.map(&mut self.memory);
+
+            info!("Loaded {} bytes in {} banks", rom.len(), banks.len());
+
+            self.banks = Some(banks);
+        } else {
+            let start = usize::from(self.start_address);
+
+            if let Some(problem) = oversize(rom.len(), start, self.map.work, self.platform) {
+                panic!("{}", problem);
+            }
+
+            self.memory[start..start + rom.len()].copy_from_slice(rom);
+
+            info!("Loaded {} bytes", rom.len());
+        }
+
+        self.rom_hash = crc32(rom);
+
+        if self.platform == Platform::HiresChip8 {
+            if rom.starts_with(&[0x12, 0x60]) {
+                self.pc = HIRES_ENTRY;
+            } else {
+                warn!("ROM doesn't start with 1260, it may not be a hi-res CHIP-8 ROM");
+            }
+        }
+
+        self.load_font();
+        self.memory[BIG_FONT_ADDRESS..BIG_FONT_ADDRESS + font::BIG.len()]
+            .copy_from_slice(&font::BIG);
+    }
+
+    fn load_font(&mut self) {
+        let digits = self.font.unwrap_or(self.platform.font()).digits();
+
+        self.memory[..digits.len()].copy_from_slice(digits);
+    }
+
+    // Switch fonts while running, to the next one when none is given,
+    // which shows once the ROM draws its digits again
+    pub fn set_font(&mut self, font: Option<Font>) -> Font {
+        self.invalidate_trace();
+        let font = font.unwrap_or_else(|| self.font.unwrap_or(self.platform.font()).next());
+
+        self.font = Some(font);
+        self.load_font();
+
+        font
+    }
+
+    pub fn display(&self) -> &Display {
+        &self.display
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    // The sound played while the sound timer runs, for audio backends
+    pub fn tone(&self) -> Tone {
+        if self.xo_chip() {
+            Tone::Pattern {
+                samples: self.audio_pattern,
+                pitch: self.pitch,
+            }
+        } else {
+            Tone::Beep
+        }
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    // Last value a CHIP-8X ROM sent to its output port, for embedders
+    // wiring up a sound board or other hardware
+    pub fn output_port(&self) -> u8 {
+        self.output_port
+    }
+
+    // Instructions executed since the ROM was loaded
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    pub fn run(&mut self, screen: &mut Screen, channel: &Receiver<Event>) -> Stop {
+        self.run_with_clock(screen, channel, RealClock::new())
+    }
+
+    // Run at 60Hz according to `clock`
+    pub fn run_with_clock(
+        &mut self,
+        screen: &mut Screen,
+        channel: &Receiver<Event>,
+        clock: impl Clock,
+    ) -> Stop {
+        let mut pacer = Pacer::new(clock);
+
+        self.show_controls(screen);
+
+        let stop = 'outer: loop {
+            if pacer.frame_due() {
+                let start = Instant::now();
+
+                if !self.paused && !self.choosing_slot {
+                    if self.tutorial {
+                        self.tutor_frame(screen);
+                    } else {
+                        self.run_frame();
+                    }
+                }
+
+                if self.exited {
+                    break 'outer Stop::Exit;
+                }
+
+                let end = Instant::now();
+
+                screen.set_sound(self.sound);
+                screen.set_quirk_warning(!self.sensitivities.is_empty());
+                screen.expire_notice();
+
+                if let Some(flicker) = &self.flicker {
+                    screen.set_highlight(flicker.changed());
+                }
+
+                // nobody is watching pixels fade out in a window in the
+                // background, so only draw it when something changed
+                let draw = !self.low_power || self.focused || self.display.dirty() != 0;
+
+                if draw {
+                    match &self.coalescer {
+                        Some(coalescer) => screen.refresh(coalescer.shown(&self.display)),
+                        None => screen.refresh(&self.display),
+                    }
+                    self.display.mark_clean();
+
+                    if let Some(latency) = &mut self.latency {
+                        let (render, present) = screen.timings();
+
+                        if let Some(sample) = latency.frame(start, end, render, present) {
+                            println!("{}", sample);
+                        }
+                    }
+                }
+
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_frame(draw);
+                    metrics.set_instructions(self.instructions);
+                    metrics.set_faults(self.unknown.as_ref().map_or(0, UnknownOpcodes::count));
+                    metrics.set_dropped(pacer.dropped());
+                }
+            }
+
+            'events: loop {
+                match channel.try_recv() {
+                    Ok(event) => match event {
+                        Event::Key(key, pressed) => self.host_key(key, pressed),
+                        Event::Probe(seen) => {
+                            if let Some(latency) = &mut self.latency {
+                                latency.key(seen);
+                            }
+                        }
+                        Event::SecondKey(key, pressed) => self.set_second_key(key & 0xF, pressed),
+                        Event::Resize(width, height) => screen.resize(width, height),
+                        Event::Focus(focused) => {
+                            self.focused = focused;
+
+                            if !focused {
+                                if let Some((start, log)) = &mut self.macro_recording {
+                                    for key in 0..16 {
+                                        if self.keys[usize::from(key)] {
+                                            log.push(self.frame - *start, key, false);
+                                        }
+                                    }
+                                }
+
+                                self.release_keys();
+                            }
+                        }
+                        Event::Click(x, y) => {
+                            if let Some((x, y)) = screen.pixel_at(x, y) {
+                                print_coords(x, y);
+                            }
+                        }
+                        Event::SaveState => self.save_state(),
+                        Event::LoadState => self.load_state(),
+                        Event::Slots(open) => self.choose_slot(screen, open),
+                        Event::SelectSlot(step) => self.select_slot(screen, step),
+                        Event::Retry => self.retry(),
+                        Event::RecordMacro => self.record_macro(),
+                        Event::PlayMacro => self.play_macro(),
+                        Event::Screenshot(path) => self.screenshot(path),
+                        Event::Export(path) => self.export_state(&path),
+                        Event::Import(path) => self.import_state(&path),
+                        Event::Step(count) => self.step(count),
+                        Event::CancelWait(value) => self.cancel_wait(value),
+                        Event::Quirk(name, enabled) => self.set_quirk(&name, enabled),
+                        Event::Pause(paused) => self.paused = paused,
+                        Event::Memory(edit) => self.edit_memory(edit),
+                        Event::Copy(selection) => self.copy(selection),
+                        Event::UnknownOpcodes => self.print_unknown_opcodes(),
+                        Event::Reference(search) => self.print_reference(&search),
+                        Event::Controls => self.show_controls(screen),
+                        Event::Keymap(name) => screen.notify(format!("Keymap: {}", name)),
+                        Event::Font(font) => {
+                            let font = self.set_font(font);
+                            info!("Switched to the {} font", font.name());
+                            screen.notify(format!("Font: {}", font.name()));
+                        }
+                        Event::Next => break 'outer Stop::Next,
+                        Event::Off => break 'outer Stop::Off,
+                        Event::On(..) => panic!("Should never receive `On`"),
+                    },
+                    Err(TryRecvError::Empty) => break 'events,
+                    Err(TryRecvError::Disconnected) => break 'outer Stop::Off,
+                }
+            }
+
+            // keys only reach the machine at the next frame anyway, so
+            // sleeping until then doesn't add any latency
+            if self.low_power {
+                pacer.wait();
+            }
+        };
+
+        self.print_report();
+
+        if let Some(summary) = self.latency.as_ref().and_then(LatencyTest::summary) {
+            println!("{}", summary);
+        }
+
+        stop
+    }
+
+    pub fn print_report(&self) {
+        if let Some(report) = &self.report {
+            report.print();
+        }
+
+        if let Some(flicker) = &self.flicker {
+            flicker.print();
+        }
+
+        if let Some(budget) = &self.budget {
+            budget.print();
+        }
+
+        if let Some(unknown) = self.unknown.as_ref().filter(|unknown| !unknown.is_empty()) {
+            unknown.print();
+        }
+    }
+
+    // Every unknown opcode skipped so far, for the console
+    pub fn print_unknown_opcodes(&self) {
+        match &self.unknown {
+            Some(unknown) => unknown.print(),
+            None => warn!("Unknown opcodes are only collected with --lenient"),
+        }
+    }
+
+    // The platform's instructions as the disassembler knows them, with the
+    // quirks that change each one, for the console
+    pub fn print_reference(&self, search: &str) {
+        let entries = self.reference(search);
+
+        if entries.is_empty() {
+            warn!(
+                "No {} instructions match '{}'",
+                self.platform.name(),
+                search
+            );
+        }
+
+        for entry in entries {
+            println!("{}", entry);
+        }
+    }
+
+    fn reference(&self, search: &str) -> Vec<String> {
+        let search = search.to_lowercase();
+        let describe = |instruction: Instruction| match disasm::form(instruction.code) {
+            Some(form) => form.describe(),
+            None => format!(
+                "{}  {:<18}{} [custom]",
+                instruction.code, "", instruction.summary
+            ),
+        };
+
+        if search.len() == 4 {
+            if let Ok(opcode) = u16::from_str_radix(&search, 16) {
+                return self.table.get(opcode).map(describe).into_iter().collect();
+            }
+        }
+
+        self.table
+            .reachable()
+            .into_iter()
+            .map(describe)
+            .filter(|entry| entry.to_lowercase().contains(&search))
+            .collect()
+    }
+
+    fn show_controls(&self, screen: &mut Screen) {
+        if let Some(controls) = &self.controls {
+            info!("Controls: {}", controls);
+            screen.notify(controls.clone());
+        }
+    }
+
+    fn stream_hashes(&mut self) {
+        let display = crc32(self.display.buffer());
+        let state = self.hash_state.then(|| crc32(&self.snapshot()));
+
+        if let Some(stream) = &mut self.hash_stream {
+            if !stream.record(self.frame, display, state) {
+                self.hash_stream = None;
+            }
+        }
+    }
+
+    // Pause, then run up to `count` instructions one at a time, stopping
+    // early at a key wait or halt like a frame would
+    pub fn step(&mut self, count: u32) {
+        self.paused = true;
+        self.invalidate_trace();
+
+        for _ in 0..count {
+            if self.wait_key != 0xFF || self.halted {
+                break;
+            }
+
+            self.tick();
+            self.instructions += 1;
+        }
+    }
+
+    // Run an instruction every `tutorial::FRAMES_PER_STEP` frames and
+    // explain it, with the timers still counting down at 60Hz
+    fn tutor_frame(&mut self, screen: &mut Screen) {
+        let due = self.frame.is_multiple_of(tutorial::FRAMES_PER_STEP);
+
+        if due && self.wait_key == 0xFF && !self.halted {
+            let pc = usize::from(self.pc);
+            let bytes = self.memory[pc..(pc + 4).min(self.memory.len())].to_vec();
+            let before = self.tutorial_registers();
+
+            self.tick();
+            self.instructions += 1;
+
+            let explanation = tutorial::explain(&bytes, &before, &self.tutorial_registers());
+
+            println!("{}\n", explanation);
+
+            if let Some(line) = explanation.lines().next() {
+                screen.notify(line.to_owned());
+            }
+        }
+
+        self.handle_delay();
+        self.handle_sound();
+        self.frame += 1;
+    }
+
+    fn tutorial_registers(&self) -> tutorial::Registers {
+        tutorial::Registers {
+            v: self.registers,
+            i: self.i,
+            pc: self.pc,
+            dt: self.dt,
+            st: self.st,
+        }
+    }
+
+    // Emulate a single 60Hz frame: a batch of instructions followed by
+    // the timer updates
+    pub fn run_frame(&mut self) {
+        if self.trace_db.as_ref().is_some_and(|db| db.due(self.frame)) {
+            let state = self.snapshot();
+            let settings = self.trace_settings();
+
+            if let Some(db) = &mut self.trace_db {
+                db.keyframe(self.frame, &settings, &state);
+            }
+        }
+
+        // a replay gets its keys from the trace instead
+        if !self.replaying {
+            self.handle_playback();
+            self.settle_keys();
+        }
+
+        let mut budget = if self.vip_timing {
+            timing::FRAME_CYCLES
+        } else {
+            FRAME_BUDGET
+        };
+        let mut instructions = 0;
+        let mut end = BatchEnd::Budget;
+
+        // about a 720Mhz clock speed
+        while budget > 0 {
+            // simulate blocking execution until
+            // key is pressed
+            if self.wait_key != 0xFF || self.halted {
+                end = if self.halted {
+                    BatchEnd::Halted
+                } else {
+                    BatchEnd::KeyWait
+                };
+                break;
+            }
+
+            self.tick();
+            budget = budget.saturating_sub(self.tick_cost());
+            instructions += 1;
+
+            // simulate waiting for screen refresh
+            // after drawing
+            if self.draw_flag && self.quirks.display_wait {
+                end = BatchEnd::DrawWait;
+                break;
+            }
+        }
+
+        self.instructions += u64::from(instructions);
+
+        if let Some(stats) = &mut self.budget {
+            stats.end_frame(instructions, end);
+        }
+
+        self.handle_delay();
+        self.handle_sound();
+        self.rng.interrupt();
+
+        if let Some(hook) = &mut self.timer_hook {
+            hook(TimerTick {
+                frame: self.frame,
+                delay: self.dt,
+                sound: self.st,
+            });
+        }
+
+        let tone = self.sound.then(|| self.tone());
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(self.frame, &self.display, tone);
+        }
+
+        if let Some(flicker) = &mut self.flicker {
+            flicker.end_frame(&self.display);
+        }
+
+        if let Some(coalescer) = &mut self.coalescer {
+            coalescer.end_frame();
+        }
+
+        if self.hash_stream.is_some() {
+            self.stream_hashes();
+        }
+
+        if let Some(db) = &mut self.trace_db {
+            db.end_frame();
+        }
+
+        self.frame += 1;
+
+        if let Some(interval) = self.checkpoint_interval {
+            // a halted ROM has nothing left worth retrying from
+            if self.frame.is_multiple_of(interval * 60) && !self.halted {
+                self.checkpoint = Some(self.snapshot());
+            }
+        }
+    }
+
+    fn macro_name(&self) -> String {
+        format!("{}.macro", self.rom_name)
+    }
+
+    // Start recording a macro, or stop and save the one being recorded
+    pub fn record_macro(&mut self) {
+        match self.macro_recording.take() {
+            Some((_, log)) => {
+                let name = self.macro_name();
+                let location = self.storage.locate(&name);
+
+                match self.storage.write(&name, log.to_text()) {
+                    Ok(()) => info!("Saved macro to '{}'", location),
+                    Err(err) => error!("Failed to save macro to '{}': {}", location, err),
+                }
+            }
+            None => {
+                info!("Recording macro");
+                self.macro_recording = Some((self.frame, InputLog::new(self.keys)));
+            }
+        }
+    }
+
+    pub fn play_macro(&mut self) {
+        let name = self.macro_name();
+
+        let log = match self
+            .storage
+            .read_to_string(&name)
+            .map_err(|err| err.to_string())
+        {
+            Ok(text) => InputLog::parse(&text),
+            Err(err) => Err(err),
+        };
+
+        match log {
+            Ok(log) => {
+                let playback = Playback::new(log, self.frame);
+
+                self.keys = playback.initial_keys();
+                self.keypad = Keypad::new(self.keys);
+                self.macro_playback = Some(playback);
+            }
+            Err(err) => warn!("Can't play macro '{}': {}", self.storage.locate(&name), err),
+        }
+    }
+
+    fn handle_playback(&mut self) {
+        let Some(playback) = &mut self.macro_playback else {
+            return;
+        };
+
+        let due = playback.due(self.frame).to_vec();
+
+        if playback.is_finished() {
+            self.macro_playback = None;
+        }
+
+        for event in due {
+            self.handle_key(event.key, event.pressed);
+        }
+    }
+
+    // Save the display as a PNG in the data directory, at a large scale by
+    // default so it's usable as a promotional image
+    pub fn screenshot(&self, path: Option<PathBuf>) {
+        let scale = self.capture_scale.unwrap_or(SCREENSHOT_SCALE);
+        let png = self.display.capture(&self.palette, scale);
+
+        let (result, location) = match path {
+            Some(path) => (fs::write(&path, png), path.display().to_string()),
+            None => {
+                let name = format!("{}_{:06}.png", self.rom_name, self.frame);
+
+                (self.storage.write(&name, png), self.storage.locate(&name))
+            }
+        };
+
+        match result {
+            Ok(()) => info!("Saved screenshot to '{}'", location),
+            Err(err) => error!("Failed to save screenshot to '{}': {}", location, err),
+        }
+    }
+
+    // The first slot keeps the name states had before there were slots
+    fn state_name(&self, slot: usize) -> String {
+        match slot {
+            0 => format!("{}.state", self.rom_name),
+            _ => format!("{}.{}.state", self.rom_name, slot),
+        }
+    }
+
+    // Hold the game and show every save slot while the select key is down
+    fn choose_slot(&mut self, screen: &mut Screen, open: bool) {
+        self.choosing_slot = open;
+
+        if open {
+            self.show_slots(screen);
+        } else {
+            screen.set_overlay(None);
+        }
+    }
+
+    fn select_slot(&mut self, screen: &mut Screen, step: i8) {
+        if !self.choosing_slot {
+            return;
+        }
+
+        self.slot =
+            (self.slot as isize + isize::from(step)).rem_euclid(slots::COUNT as isize) as usize;
+        self.show_slots(screen);
+    }
+
+    fn show_slots(&self, screen: &mut Screen) {
+        let now = SystemTime::now();
+        let slots: Vec<Slot> = (0..slots::COUNT)
+            .map(|slot| {
+                let name = self.state_name(slot);
+
+                Slot {
+                    saved: self.storage.modified(&name),
+                    thumbnail: self
+                        .storage
+                        .read(&name)
+                        .ok()
+                        .and_then(|data| state::decode(&data).ok())
+                        .and_then(|(_, payload)| self.saved_display(&payload)),
+                }
+            })
+            .collect();
+
+        screen.notify(slots[self.slot].describe(self.slot, now));
+        screen.set_overlay(Some(slots::render(&slots, self.slot, &self.palette, now)));
+    }
+
+    pub fn save_state(&self) {
+        let header = state::Header {
+            version: state::VERSION,
+            rom_hash: self.rom_hash,
+            quirks: self.quirks.bits(),
+            platform: self.platform as u8,
+            memory_size: self.memory.len() as u32,
+        };
+
+        let name = self.state_name(self.slot);
+        let location = self.storage.locate(&name);
+
+        match self
+            .storage
+            .write(&name, state::encode(&header, &self.snapshot()))
+        {
+            Ok(()) => info!("Saved state to '{}'", location),
+            Err(err) => error!("Failed to save state to '{}': {}", location, err),
+        }
+    }
+
+    // Restore the most recent automatic checkpoint, kept in memory only
+    pub fn retry(&mut self) {
+        match self.checkpoint.take() {
+            Some(checkpoint) => {
+                self.restore(&checkpoint);
+                self.checkpoint = Some(checkpoint);
+
+                info!("Retrying from the last checkpoint");
+            }
+            None => warn!("No checkpoint to retry from yet"),
+        }
+    }
+
+    pub fn load_state(&mut self) {
+        let name = self.state_name(self.slot);
+        let location = self.storage.locate(&name);
+
+        let data = match self.storage.read(&name) {
+            Ok(data) => data,
+            Err(err) => {
+                warn!("No save state at '{}': {}", location, err);
+                return;
+            }
+        };
+
+        let (header, payload) = match state::decode(&data) {
+            Ok(decoded) => decoded,
+            Err(err) => {
+                error!("Refusing to load state '{}': {}", location, err);
+                return;
+            }
+        };
+
+        if let Err(err) = self.check_state(&header) {
+            error!("Refusing to load state '{}': {}", location, err);
+            return;
+        }
+
+        if header.rom_hash != self.rom_hash {
+            warn!("State was saved from a different ROM, it may not behave as expected");
+        }
+
+        if header.quirks != self.quirks.bits() {
+            warn!("State was saved with a different quirks profile");
+        }
+
+        self.restore(&payload);
+
+        info!("Loaded state from '{}'", location);
+    }
+
+    // A state from another platform or memory size doesn't fit the machine,
+    // so restoring it would read past the payload or leave memory half
+    // overwritten
+    fn check_state(&self, header: &state::Header) -> Result<(), String> {
+        if header.platform != self.platform as u8 {
+            let saved = Platform::ALL
+                .into_iter()
+                .find(|&platform| platform as u8 == header.platform)
+                .map_or("an unknown platform", |platform| platform.name());
+
+            return Err(format!(
+                "saved on {}, but running {}",
+                saved,
+                self.platform.name()
+            ));
+        }
+
+        if header.memory_size as usize != self.memory.len() {
+            return Err(format!(
+                "saved with {} bytes of memory, but running with {}",
+                header.memory_size,
+                self.memory.len()
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn export_state(&self, path: &Path) {
+        match fs::write(path, self.octo_state().to_json().to_string()) {
+            Ok(()) => info!("Exported state to '{}'", path.display()),
+            Err(err) => error!("Failed to export state to '{}': {}", path.display(), err),
+        }
+    }
+
+    pub fn import_state(&mut self, path: &Path) {
+        let machine = fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| Machine::parse(&text));
+
+        match machine {
+            Ok(machine) => {
+                self.set_octo_state(&machine);
+                info!("Imported state from '{}'", path.display());
+            }
+            Err(err) => error!("Failed to import state from '{}': {}", path.display(), err),
+        }
+    }
+
+    // The registers, memory and display as Octo's debugger holds them
+    pub fn octo_state(&self) -> Machine {
+        let buffer = self.display.buffer();
+        let plane = |bit: u8| buffer.iter().map(|pixel| (pixel >> bit) & 1).collect();
+
+        Machine {
+            memory: self.memory.clone(),
+            registers: self.registers,
+            stack: self.stack[..self.sp].to_vec(),
+            i: self.i,
+            pc: self.pc,
+            dt: self.dt,
+            st: self.st,
+            hires: self.display.resolution() == Resolution::HIRES,
+            flags: self.rpl_flags,
+            plane: self.display.planes(),
+            pattern: self.audio_pattern,
+            pitch: self.pitch,
+            pixels: [plane(0), plane(1)],
+        }
+    }
+
+    // Octo always has 64K of memory, so only memory that doesn't fit and
+    // isn't blank is worth a warning
+    pub fn set_octo_state(&mut self, machine: &Machine) {
+        self.invalidate_trace();
+
+        let size = machine.memory.len().min(self.memory.len());
+
+        if machine.memory[size..].iter().any(|&byte| byte != 0) {
+            warn!(
+                "State's memory past {:#x} doesn't fit and was dropped",
+                size
+            );
+        }
+
+        self.memory[..size].copy_from_slice(&machine.memory[..size]);
+        self.registers = machine.registers;
+
+        if machine.stack.len() > self.stack.len() {
+            warn!(
+                "State has a stack depth of {}, but it is set to {}",
+                machine.stack.len(),
+                self.stack.len()
+            );
+        }
+
+        self.sp = machine.stack.len().min(self.stack.len());
+        self.stack.fill(0);
+        self.stack[..self.sp].copy_from_slice(&machine.stack[..self.sp]);
+        self.i = machine.i;
+        self.pc = machine.pc;
+        self.dt = machine.dt;
+        self.st = machine.st;
+        self.rpl_flags = machine.flags;
+        self.audio_pattern = machine.pattern;
+        self.pitch = machine.pitch;
+        self.halted = false;
+
+        let resolution = if machine.hires {
+            Resolution::HIRES
+        } else {
+            Resolution::LORES
+        };
+        let size = resolution.width * resolution.height;
+
+        // a blank display when the planes don't fit the resolution
+        let [first, second] = &machine.pixels;
+        let pixels: Vec<u8> = if first.len() == size && second.len() == size {
+            first
+                .iter()
+                .zip(second)
+                .map(|(low, high)| low | high << 1)
+                .collect()
+        } else {
+            vec![0; size]
+        };
+
+        self.display.restore(resolution, &pixels);
+        self.display.select_planes(machine.plane);
+    }
+
+    // A machine to replay a trace database from one of its keyframes, with
+    // the settings it was recorded with
+    pub(crate) fn from_keyframe(keyframe: &Keyframe) -> Self {
+        let settings = keyframe.settings;
+        let mut device = Self::new(Config {
+            platform: settings.platform,
+            quirks: settings.quirks,
+            memory_size: Some(settings.memory_size),
+            stack_depth: Some(settings.stack_depth),
+            vip_timing: settings.vip_timing,
+            slow_memory: settings.slow_memory,
+            banking: settings.banking,
+            lenient: settings.lenient,
+            storage: Storage::memory(),
+            rng: Rng::Replayed(VecDeque::new()),
+            ..Config::default()
+        });
+
+        device.restore(&keyframe.state);
+        device.frame = keyframe.frame;
+        device.replaying = true;
+        device
+    }
+
+    // Run a frame with the inputs recorded for it
+    pub(crate) fn replay_frame(&mut self, inputs: &[Input]) {
+        for &input in inputs {
+            match input {
+                Input::Key(key, pressed) => self.set_key(key, pressed),
+                Input::SecondKey(key, pressed) => self.set_second_key(key, pressed),
+                Input::ReleaseKeys => self.release_keys(),
+                Input::CancelWait(value) => self.cancel_wait(value),
+                Input::Random(byte) => self.rng.replay(byte),
+            }
+        }
+
+        self.run_frame();
+    }
+
+    // Note every write to `address` from now on
+    pub(crate) fn watch(&mut self, address: usize) {
+        self.watch = Some(Watch {
+            address,
+            pending: None,
+            writes: Vec::new(),
+        });
+    }
+
+    pub(crate) fn writes(&self) -> &[Write] {
+        self.watch.as_ref().map_or(&[], |watch| &watch.writes)
+    }
+
+    fn trace_settings(&self) -> Settings {
+        Settings {
+            platform: self.platform,
+            quirks: self.quirks,
+            memory_size: self.memory.len(),
+            stack_depth: self.stack.len(),
+            vip_timing: self.vip_timing,
+            slow_memory: self.slow_memory,
+            banking: self.banking,
+            lenient: self.unknown.is_some(),
+        }
+    }
+
+    fn record_input(&mut self, input: Input) {
+        if let Some(db) = &mut self.trace_db {
+            db.input(input);
+        }
+    }
+
+    // The host changed the machine, so the trace needs a new keyframe to
+    // replay from
+    fn invalidate_trace(&mut self) {
+        if let Some(db) = &mut self.trace_db {
+            db.invalidate();
+        }
+    }
+
+    fn snapshot(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+
+        writer.bytes(&self.memory);
+        writer.bytes(&self.registers);
+
+        writer.u8(self.stack.len() as u8);
+
+        for &address in &self.stack {
+            writer.u16(address);
+        }
+
+        writer.u8(self.sp as u8);
+        writer.u16(self.pc);
+        writer.u16(self.i as u16);
+        writer.u8(self.dt);
+        writer.u8(self.st);
+        writer.u8(self.wait_key);
+        writer.u8(self.halted.into());
+
+        let resolution = self.display.resolution();
+
+        writer.u16(resolution.width as u16);
+        writer.u16(resolution.height as u16);
+        writer.bytes(self.display.buffer());
+
+        for &key in &self.keys {
+            writer.u8(key.into());
+        }
+
+        match &self.banks {
+            Some(banks) => {
+                writer.u16(banks.len() as u16);
+                writer.u16(banks.current() as u16);
+
+                for bank in banks.contents(&self.memory) {
+                    writer.bytes(bank);
+                }
+            }
+            None => {
+                writer.u16(0);
+                writer.u16(0);
+            }
+        }
+
+        writer.u8(self.display.planes());
+        writer.bytes(&self.audio_pattern);
+        writer.u8(self.pitch);
+        writer.u8((self.i >> 16) as u8);
+
+        writer.0
+    }
+
+    // The display in a state payload, laid out as `snapshot` writes it,
+    // without restoring anything
+    fn saved_display(&self, payload: &[u8]) -> Option<(Resolution, Vec<u8>)> {
+        let depth_at = self.memory.len() + self.registers.len();
+        let depth = usize::from(*payload.get(depth_at)?);
+        // past the stack, SP, PC, I, the timers, the key wait and halted
+        let resolution_at = depth_at + 1 + depth * 2 + 1 + 2 + 2 + 4;
+        let size = payload.get(resolution_at..resolution_at + 4)?;
+
+        let resolution = Resolution {
+            width: usize::from(u16::from_le_bytes([size[0], size[1]])),
+            height: usize::from(u16::from_le_bytes([size[2], size[3]])),
+        };
+        let start = resolution_at + 4;
+        let buffer = payload.get(start..start + resolution.width * resolution.height)?;
+
+        Some((resolution, buffer.to_vec()))
+    }
+
+    fn restore(&mut self, payload: &[u8]) {
+        self.invalidate_trace();
+
+        let mut reader = Reader::new(payload);
+
+        let memory = reader.bytes(self.memory.len());
+        self.memory.copy_from_slice(memory);
+
+        let registers = reader.bytes(self.registers.len());
+        self.registers.copy_from_slice(registers);
+
+        let depth = usize::from(reader.u8());
+        let stack: Vec<u16> = (0..depth).map(|_| reader.u16()).collect();
+
+        self.sp = usize::from(reader.u8());
+
+        if depth != self.stack.len() {
+            warn!(
+                "State has a stack depth of {}, but it is set to {}",
+                depth,
+                self.stack.len()
+            );
+        }
+
+        let kept = depth.min(self.stack.len());
+
+        self.stack.fill(0);
+        self.stack[..kept].copy_from_slice(&stack[..kept]);
+        self.sp = self.sp.min(self.stack.len());
+        self.pc = reader.u16();
+        self.i = u32::from(reader.u16());
+        self.dt = reader.u8();
+        self.st = reader.u8();
+        self.wait_key = reader.u8();
+        self.halted = reader.u8() != 0;
+
+        let resolution = Resolution {
+            width: usize::from(reader.u16()),
+            height: usize::from(reader.u16()),
+        };
+
+        self.display.restore(
+            resolution,
+            reader.bytes(resolution.width * resolution.height),
+        );
+
+        for key in &mut self.keys {
+            *key = reader.u8() != 0;
+        }
+
+        self.keypad = Keypad::new(self.keys);
+
+        let count = usize::from(reader.u16());
+        let current = usize::from(reader.u16());
+        let banks = (0..count)
+            .map(|_| reader.bytes(BANK_SIZE).to_vec())
+            .collect();
+
+        match &mut self.banks {
+            Some(bank_set) if count > 0 => bank_set.restore(current, banks),
+            Some(_) => warn!("State has no memory banks, keeping the current ones"),
+            None if count > 0 => warn!("State has memory banks, but banking is off"),
+            None => {}
+        }
+
+        self.display.select_planes(reader.u8());
+        self.audio_pattern.copy_from_slice(reader.bytes(16));
+        self.pitch = reader.u8();
+        self.i |= u32::from(reader.u8()) << 16;
+    }
+
+    fn handle_delay(&mut self) {
+        if self.dt > 0 {
+            self.dt -= 1;
+        }
+    }
+
+    fn handle_sound(&mut self) {
+        self.sound = self.st > 0;
+
+        if self.st > 0 {
+            self.st -= 1;
+        }
+    }
+
+    // A key from the window, passed through the embedder's hook and recorded
+    // into any macro before it's handled
+    fn host_key(&mut self, key: u8, pressed: bool) {
+        let key = match &mut self.key_hook {
+            Some(hook) => match hook(key, pressed) {
+                Some(key) => key & 0xF,
+                None => return,
+            },
+            None => key,
+        };
+
+        if let Some((start, log)) = &mut self.macro_recording {
+            log.push(self.frame - *start, key, pressed);
+        }
+
+        self.handle_key(key, pressed);
+
+        if self.latency.is_some() {
+            self.flash(pressed);
+        }
+    }
+
+    // Light the whole display, or clear it, for `chip8 latency`
+    fn flash(&mut self, lit: bool) {
+        let resolution = self.display.resolution();
+
+        if lit {
+            self.display
+                .restore(resolution, &vec![1; resolution.width * resolution.height]);
+        } else {
+            self.display.clear();
+        }
+    }
+
+    // Input from the host, which reaches the machine straight away unless
+    // it's delayed by the simulated latency or the debounce quirk holds it
+    // back until the key settles
+    pub fn handle_key(&mut self, key: u8, pressed: bool) {
+        if self.key_latency > 0 {
+            self.keypad
+                .delay(self.frame + self.key_latency, key, pressed);
+        } else {
+            self.press_key(key, pressed);
+        }
+    }
+
+    fn press_key(&mut self, key: u8, pressed: bool) {
+        self.keypad.set(key, pressed, self.frame);
+
+        if !self.quirks.debounce {
+            self.set_key(key, pressed);
+        }
+    }
+
+    fn settle_keys(&mut self) {
+        for (key, pressed) in self.keypad.due(self.frame) {
+            self.press_key(key, pressed);
+        }
+
+        let frames = if self.quirks.debounce {
+            DEBOUNCE_FRAMES
+        } else {
+            0
+        };
+
+        for (key, pressed) in self.keypad.settled(&self.keys, self.frame, frames) {
+            self.set_key(key, pressed);
+        }
+    }
+
+    fn set_key(&mut self, key: u8, pressed: bool) {
+        self.record_input(Input::Key(key, pressed));
+        self.keys[usize::from(key)] = pressed;
+
+        if self.wait_key != 0xFF && !pressed {
+            self.registers[usize::from(self.wait_key)] = key;
+            self.wait_key = 0xFF;
+        }
+    }
+
+    // Lift every key at once, for when the window loses focus and will
+    // never see their releases. This isn't a key press, so a pending FX0A
+    // keeps waiting rather than taking whichever key happened to be held.
+    pub fn release_keys(&mut self) {
+        self.record_input(Input::ReleaseKeys);
+        self.keys = [false; 16];
+        self.second_keys = [false; 16];
+        self.keypad = Keypad::new(self.keys);
+    }
+
+    // Release a pending FX0A wait as if `value` had been pressed, for when
+    // a ROM waits on a key that isn't mapped
+    pub fn cancel_wait(&mut self, value: u8) {
+        self.record_input(Input::CancelWait(value));
+
+        if self.wait_key != 0xFF {
+            info!("Cancelling key wait with {:x}", value);
+
+            self.registers[usize::from(self.wait_key)] = value;
+            self.wait_key = 0xFF;
+        }
+    }
+
+    fn set_second_key(&mut self, key: u8, pressed: bool) {
+        self.record_input(Input::SecondKey(key, pressed));
+        self.second_keys[usize::from(key)] = pressed;
+    }
+
+    // Change a quirk while running, taking effect from the next instruction
+    pub fn set_quirk(&mut self, name: &str, enabled: Option<bool>) {
+        self.invalidate_trace();
+
+        match self.quirks.flag(name) {
+            Some(flag) => {
+                *flag = enabled.unwrap_or(!*flag);
+                info!("Quirk '{}' {}", name, if *flag { "on" } else { "off" });
+            }
+            None => warn!("Unknown quirk '{}'", name),
+        }
+    }
+
+    fn fetch(&mut self) -> Opcode {
+        let raw = self.word(usize::from(self.pc));
+        let bottom = raw as u8;
+
+        self.pc += 2;
+
+        Opcode {
+            raw,
+            code: raw & 0xF000,
+            nnn: raw & 0x0FFF,
+            x: ((raw & 0x0F00) >> 8) as u8,
+            y: ((raw & 0x00F0) >> 4) as u8,
+            n: (raw & 0x000F) as u8,
+            kk: bottom,
+        }
+    }
+
+    // With slow memory, every byte an instruction reads or writes (beyond
+    // fetching it) stalls the VIP's CPU, so fewer instructions fit in a frame.
+    // VIP timing counts machine cycles instead, which covers that already.
+    fn tick_cost(&self) -> u32 {
+        if self.vip_timing {
+            let vx = self.register(((self.opcode >> 8) & 0xF) as u8);
+
+            timing::cycles(self.opcode, vx, self.skipped)
+        } else if self.slow_memory {
+            INSTRUCTION_COST + self.memory_accesses * WAIT_STATE_COST
+        } else {
+            INSTRUCTION_COST
+        }
+    }
+
+    fn tick(&mut self) {
+        self.draw_flag = false;
+        self.memory_accesses = 0;
+        self.skipped = false;
+
+        if let Some(tracer) = &mut self.tracer {
+            let pc = usize::from(self.pc) % self.memory.len();
+            tracer.record(self.frame, self.pc, &self.memory[pc..]);
+        }
+
+        let opcode = self.fetch();
+        self.opcode = opcode.raw;
+
+        if opcode.code == 0x8000 && opcode.n != 0 && (opcode.x == 0xF || opcode.y == 0xF) {
+            self.violation(Violation::FlagOperand);
+        }
+
+        match self.table.get(opcode.raw) {
+            Some(instruction) => self.execute(instruction, opcode),
+            None => self.unknown_opcode(),
+        }
+
+        if let Some(watch) = &mut self.watch {
+            if let Some(pc) = watch.pending.take() {
+                watch.writes.push(Write {
+                    frame: self.frame,
+                    pc,
+                    value: self.memory[watch.address],
+                });
+            }
+        }
+    }
+
+    fn execute(&mut self, instruction: Instruction, opcode: Opcode) {
+        match instruction.run {
+            Run::Builtin(run) => run(self, opcode),
+            Run::Custom(run) => run(Cpu {
+                opcode: opcode.raw,
+                x: opcode.x,
+                y: opcode.y,
+                n: opcode.n,
+                kk: opcode.kk,
+                nnn: opcode.nnn,
+                registers: &mut self.registers,
+                i: &mut self.i,
+                pc: &mut self.pc,
+                memory: &mut self.memory,
+                display: &mut self.display,
+            }),
+        }
+    }
+
+    // Return from a subroutine
+    fn op_00ee(&mut self) {
+        self.memory_accesses += 2;
+
+        if self.sp == 0 {
+            warn!("Stack underflow at {:03x}", self.pc - 2);
+
+            match self.stack_overflow {
+                StackOverflow::Halt => {
+                    self.halted = true;
+                    return;
+                }
+                StackOverflow::Wrap => self.sp = self.stack.len(),
+            }
+        }
+
+        self.sp -= 1;
+        self.pc = self.stack[self.sp];
+    }
+
+    // Call a machine code routine, which only the embedder's hook can run
+    fn op_0nnn(&mut self, nnn: u16) {
+        self.violation(Violation::MachineRoutine);
+
+        let handled = self.routine_hook.as_mut().is_some_and(|hook| {
+            hook(MachineCall {
+                address: nnn,
+                registers: &mut self.registers,
+                memory: &mut self.memory,
+                display: &mut self.display,
+            })
+        });
+
+        if !handled && nnn != 0 {
+            self.unknown_opcode();
+        }
+    }
+
+    // Clear the display, with the hi-res CHIP-8 interpreter's own routine
+    fn op_0230(&mut self) {
+        self.op_00e0();
+    }
+
+    // Clear the display
+    fn op_00e0(&mut self) {
+        self.display.clear();
+
+        self.draw_flag = true;
+    }
+
+    // Scroll the display down by n pixels
+    fn op_00cn(&mut self, n: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.display.scroll_down(self.scroll_amount(n));
+
+        self.draw_flag = true;
+    }
+
+    // Scroll the display up by n pixels
+    fn op_00dn(&mut self, n: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.display.scroll_up(self.scroll_amount(n));
+
+        self.draw_flag = true;
+    }
+
+    // Scroll the display right by 4 pixels
+    fn op_00fb(&mut self) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.display.scroll_right(self.scroll_amount(4));
+
+        self.draw_flag = true;
+    }
+
+    // Scroll the display left by 4 pixels
+    fn op_00fc(&mut self) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.display.scroll_left(self.scroll_amount(4));
+
+        self.draw_flag = true;
+    }
+
+    // Exit the interpreter
+    fn op_00fd(&mut self) {
+        self.violation(Violation::ExtendedInstruction);
+
+        info!("Program exited");
+
+        self.halted = true;
+        self.exited = matches!(self.exit_action, ExitAction::Close);
+    }
+
+    // Switch to lores (64x32) mode
+    fn op_00fe(&mut self) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.display
+            .set_resolution(Resolution::LORES, self.quirks.preserve_on_resize);
+
+        self.draw_flag = true;
+    }
+
+    // Switch to hires (128x64) mode
+    fn op_00ff(&mut self) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.display
+            .set_resolution(Resolution::HIRES, self.quirks.preserve_on_resize);
+
+        self.draw_flag = true;
+    }
+
+    // Step to the next background colour (CHIP-8X)
+    fn op_02a0(&mut self) {
+        self.violation(Violation::ExtendedInstruction);
+
+        if let Some(zones) = self.display.zones_mut() {
+            zones.step_background();
+        }
+
+        self.draw_flag = true;
+    }
+
+    // Leave Mega-CHIP mode, back to the lores display
+    fn op_0010(&mut self) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.display.set_mega(false);
+
+        self.draw_flag = true;
+    }
+
+    // Enter Mega-CHIP mode, with the 256x192 colour display
+    fn op_0011(&mut self) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.display.set_mega(true);
+
+        self.draw_flag = true;
+    }
+
+    // Set I = the 24 bit address made of nn and the 16 bits that follow
+    // (Mega-CHIP)
+    fn op_01nn(&mut self, nn: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.i = u32::from(nn) << 16 | u32::from(self.word(usize::from(self.pc)));
+        self.i_incremented = false;
+        self.pc += 2;
+    }
+
+    // Load nn ARGB colours from I into the palette, from index 1 on
+    // (Mega-CHIP)
+    fn op_02nn(&mut self, nn: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        let colors = &self.memory[self.span(self.i as usize, usize::from(nn) * 4)];
+        self.memory_accesses += colors.len() as u32;
+
+        if let Some(palette) = self.display.colors_mut() {
+            palette.load_palette(colors);
+        }
+    }
+
+    // Set the width of colour sprites, 0 being 256 (Mega-CHIP)
+    fn op_03nn(&mut self, nn: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.sprite_width = if nn == 0 { 256 } else { usize::from(nn) };
+    }
+
+    // Set the height of colour sprites, 0 being 256 (Mega-CHIP)
+    fn op_04nn(&mut self, nn: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.sprite_height = if nn == 0 { 256 } else { usize::from(nn) };
+    }
+
+    // Set the brightness of the whole screen (Mega-CHIP)
+    fn op_05nn(&mut self, nn: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        if let Some(colors) = self.display.colors_mut() {
+            colors.set_alpha(nn);
+        }
+
+        self.draw_flag = true;
+    }
+
+    // Play or stop the digitised sound at I, which isn't supported so the
+    // ROM runs silently (Mega-CHIP)
+    fn op_060n(&mut self) {
+        self.violation(Violation::ExtendedInstruction);
+    }
+
+    // Set how sprites blend with the screen (Mega-CHIP)
+    fn op_080n(&mut self, n: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        if let Some(colors) = self.display.colors_mut() {
+            colors.set_blend(Blend::from_mode(n));
+        }
+    }
+
+    // Set the colour that sprites collide with (Mega-CHIP)
+    fn op_09nn(&mut self, nn: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        if let Some(colors) = self.display.colors_mut() {
+            colors.set_collision(nn);
+        }
+    }
+
+    // Scroll the display up by n pixels (Mega-CHIP)
+    fn op_00bn(&mut self, n: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.display.scroll_up(n);
+
+        self.draw_flag = true;
+    }
+
+    // Jump to location at nnn
+    fn op_1nnn(&mut self, nnn: u16) {
+        self.pc = nnn;
+    }
+
+    // Call subroutine at nnn
+    fn op_2nnn(&mut self, nnn: u16) {
+        self.memory_accesses += 2;
+
+        if self.sp == self.stack.len() {
+            warn!("Stack overflow at {:03x}", self.pc - 2);
+
+            match self.stack_overflow {
+                StackOverflow::Halt => {
+                    self.halted = true;
+                    return;
+                }
+                StackOverflow::Wrap => self.sp = 0,
+            }
+        }
+
+        self.stack[self.sp] = self.pc;
+        self.sp += 1;
+        self.pc = nnn;
+
+        if self.sp > VIP_STACK_DEPTH {
+            self.violation(Violation::StackDepth);
+        }
+    }
+
+    // Skip next instruction if Vx = kk
+    fn op_3xkk(&mut self, x: u8, kk: u8) {
+        if self.register(x) == kk {
+            self.skip();
+        }
+    }
+
+    // Skip next instruction if Vx != kk
+    fn op_4xkk(&mut self, x: u8, kk: u8) {
+        if self.register(x) != kk {
+            self.skip();
+        }
+    }
+
+    // Skip next instruction if Vx = Vy
+    fn op_5xy0(&mut self, x: u8, y: u8) {
+        if self.register(x) == self.register(y) {
+            self.skip();
+        }
+    }
+
+    // Add each octal digit of Vy to Vx's, dropping the carries (CHIP-8X)
+    fn op_5xy1(&mut self, x: u8, y: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        let sum = (self.register(x) & 0x77) + (self.register(y) & 0x77);
+        self.registers[usize::from(x)] = sum & 0x77;
+    }
+
+    // Store registers Vx through Vy in memory starting at location I,
+    // leaving I unchanged (XO-CHIP)
+    fn op_5xy2(&mut self, x: u8, y: u8) {
+        let registers = register_range(x, y);
+
+        self.violation(Violation::ExtendedInstruction);
+        self.check_write(registers.len());
+
+        let span = self.span(self.i as usize, registers.len());
+
+        for (address, &register) in span.zip(&registers) {
+            self.memory[address] = self.registers[register];
+        }
+
+        self.memory_accesses += registers.len() as u32;
+    }
+
+    // Read registers Vx through Vy from memory starting at location I,
+    // leaving I unchanged (XO-CHIP)
+    fn op_5xy3(&mut self, x: u8, y: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        let registers = register_range(x, y);
+        let span = self.span(self.i as usize, registers.len());
+
+        for (address, &register) in span.zip(&registers) {
+            self.registers[register] = self.memory[address];
+        }
+
+        self.memory_accesses += registers.len() as u32;
+    }
+
+    // Set Vx = kk
+    fn op_6xkk(&mut self, x: u8, kk: u8) {
+        self.registers[usize::from(x)] = kk;
+    }
+
+    // Set Vx = Vx + kk
+    fn op_7xkk(&mut self, x: u8, kk: u8) {
+        self.registers[usize::from(x)] = self.register(x).overflowing_add(kk).0;
+    }
+
+    // Set Vx = Vy
+    fn op_8xy0(&mut self, x: u8, y: u8) {
+        self.registers[usize::from(x)] = self.register(y);
+    }
+
+    // Set Vx = Vx OR Vy
+    fn op_8xy1(&mut self, x: u8, y: u8) {
+        if self.register(0xF) != 0 {
+            self.sensitive(Sensitivity::VfReset);
+        }
+
+        self.registers[usize::from(x)] |= self.register(y);
+
+        if self.quirks.vf_reset {
+            self.set_flag(false);
+        }
+    }
+
+    // Set Vx = Vx AND Vy
+    fn op_8xy2(&mut self, x: u8, y: u8) {
+        if self.register(0xF) != 0 {
+            self.sensitive(Sensitivity::VfReset);
+        }
+
+        self.registers[usize::from(x)] &= self.register(y);
+
+        if self.quirks.vf_reset {
+            self.set_flag(false);
+        }
+    }
+
+    // Set Vx = Vx XOR Vy
+    fn op_8xy3(&mut self, x: u8, y: u8) {
+        if self.register(0xF) != 0 {
+            self.sensitive(Sensitivity::VfReset);
+        }
+
+        self.registers[usize::from(x)] ^= self.register(y);
+
+        if self.quirks.vf_reset {
+            self.set_flag(false);
+        }
+    }
+
+    // Set Vx = Vx + Vy, set VF = carry
+    fn op_8xy4(&mut self, x: u8, y: u8) {
+        let (result, carry) = self.register(x).overflowing_add(self.register(y));
+
+        self.registers[usize::from(x)] = result;
+        self.set_flag(carry);
+    }
+
+    // Set Vx = Vx - Vy, set VF = NOT borrow
+    fn op_8xy5(&mut self, x: u8, y: u8) {
+        let (result, carry) = self.register(x).overflowing_sub(self.register(y));
+
+        self.registers[usize::from(x)] = result;
+        self.set_flag(!carry);
+    }
+
+    // Set Vx = Vy SHR 1, or Vx SHR 1 with the shifting quirk
+    fn op_8xy6(&mut self, x: u8, y: u8) {
+        if x != y {
+            self.sensitive(Sensitivity::Shifting);
+        }
+
+        let value = self.register(if self.quirks.shifting { x } else { y });
+
+        self.registers[usize::from(x)] = value >> 1;
+        self.set_flag(value & 0b0000_0001);
+    }
+
+    // Set Vx = Vy - Vx, set VF = NOT borrow
+    fn op_8xy7(&mut self, x: u8, y: u8) {
+        let (result, carry) = self.register(y).overflowing_sub(self.register(x));
+
+        self.registers[usize::from(x)] = result;
+        self.set_flag(!carry);
+    }
+
+    // Set Vx = Vy SHL 1, or Vx SHL 1 with the shifting quirk
+    fn op_8xye(&mut self, x: u8, y: u8) {
+        if x != y {
+            self.sensitive(Sensitivity::Shifting);
+        }
+
+        let value = self.register(if self.quirks.shifting { x } else { y });
+
+        self.registers[usize::from(x)] = value << 1;
+        self.set_flag(value >> 7);
+    }
+
+    // Skip next instruction if Vx != Vy
+    fn op_9xy0(&mut self, x: u8, y: u8) {
+        if self.register(x) != self.register(y) {
+            self.skip();
+        }
+    }
+
+    // Set I = nnn
+    fn op_annn(&mut self, nnn: u16) {
+        self.i = u32::from(nnn);
+        self.i_incremented = false;
+    }
+
+    // Jump to location nnn + V0, or nnn + Vx with the jumping quirk
+    fn op_bnnn(&mut self, nnn: u16) {
+        let x = (nnn >> 8) as u8;
+
+        if self.register(x) != self.register(0) {
+            self.sensitive(Sensitivity::Jumping);
+        }
+
+        let offset = self.register(if self.quirks.jumping { x } else { 0 });
+        self.pc = nnn + u16::from(offset);
+    }
+
+    // Colour the zones at Vx and Vx+1 in Vy's colour (CHIP-8X). With n of
+    // 0 each holds a starting zone in its low digit and how many more to
+    // colour in its high one, counting 4 rows to a zone vertically.
+    // Otherwise Vx and Vx+1 are a pixel position, and n rows of the
+    // column there are coloured.
+    fn op_bxyn(&mut self, x: u8, y: u8, n: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        let horizontal = usize::from(self.register(x));
+        let vertical = usize::from(self.register((x + 1) & 0xF));
+        let color = self.register(y);
+
+        let (columns, rows) = if n == 0 {
+            let (left, right) = (horizontal & 0xF, horizontal >> 4);
+            let (top, bottom) = (vertical & 0xF, vertical >> 4);
+
+            (left..left + right + 1, top * 4..(top + bottom + 1) * 4)
+        } else {
+            let column = horizontal / 8;
+
+            (column..column + 1, vertical..vertical + usize::from(n))
+        };
+
+        if let Some(zones) = self.display.zones_mut() {
+            zones.fill(columns, rows, color);
+        }
+
+        self.draw_flag = true;
+    }
+
+    // Set Vx = random byte AND kk
+    fn op_cxkk(&mut self, x: u8, kk: u8) {
+        let byte = self.rng.next_byte();
+        self.record_input(Input::Random(byte));
+
+        self.registers[usize::from(x)] = kk & byte;
+    }
+
+    // Display n-byte sprite starting at memory location I at (Vx, Vy), or a
+    // 16x16 sprite of 32 bytes when n is 0 in hires (or always on XO-CHIP).
+    // XO-CHIP draws a sprite for each selected plane, one after the other.
+    fn op_dxyn(&mut self, x: u8, y: u8, n: u8) {
+        self.check_increment();
+
+        if self.display.colors().is_some() {
+            self.draw_colors(x, y, n);
+            return;
+        }
+
+        let x_pos = self.register(x);
+        let y_pos = self.register(y);
+
+        let resolution = self.display.resolution();
+
+        // DXY0 draws a 16x16 sprite in hires (SCHIP)
+        let wide = n == 0 && (resolution == Resolution::HIRES || self.xo_chip());
+        let (width, height) = if wide { (16, 16) } else { (8, usize::from(n)) };
+
+        if usize::from(x_pos) % resolution.width + width > resolution.width
+            || usize::from(y_pos) % resolution.height + height > resolution.height
+        {
+            self.sensitive(Sensitivity::Clipping);
+        }
+
+        let planes = self.display.planes().count_ones() as usize;
+        let bytes = width / 8 * height * planes;
+        self.memory_accesses += bytes as u32;
+
+        if let Some(coalescer) = &mut self.coalescer {
+            coalescer.sprite((x_pos, y_pos, self.i, n), &self.display);
+        }
+
+        let sprite = &self.memory[self.span(self.i as usize, bytes)];
+
+        self.display.set_wrapping(!self.quirks.clipping);
+        let collision = if wide {
+            self.display.draw_wide(x_pos, y_pos, sprite)
+        } else {
+            self.display.draw(x_pos, y_pos, sprite)
+        };
+
+        if self.quirks.row_collisions && resolution == Resolution::HIRES {
+            self.set_flag(collision.rows + collision.clipped);
+        } else {
+            self.set_flag(collision.any());
+        }
+
+        if let Some(flicker) = &mut self.flicker {
+            flicker.record_sprite();
+        }
+
+        self.draw_flag = true;
+    }
+
+    // Draw a Mega-CHIP sprite of palette indices at I. The fonts are still
+    // 1 bit sprites, and are drawn n rows high in the last colour.
+    fn draw_colors(&mut self, x: u8, y: u8, n: u8) {
+        let start = self.i as usize;
+
+        let (width, sprite) = if self.map.interpreter().contains(&start) {
+            let rows = &self.memory[self.span(start, usize::from(n))];
+            let pixels = rows
+                .iter()
+                .flat_map(|&row| {
+                    (0..8).map(move |bit| if row & (0x80 >> bit) != 0 { 0xFF } else { 0 })
+                })
+                .collect();
+
+            (8, pixels)
+        } else {
+            let bytes = self.sprite_width * self.sprite_height;
+
+            (
+                self.sprite_width,
+                self.memory[self.span(start, bytes)].to_vec(),
+            )
+        };
+
+        self.memory_accesses += sprite.len() as u32;
+
+        let height = sprite.len() / width;
+        let collision =
+            self.display
+                .draw_colors(self.register(x), self.register(y), width, height, &sprite);
+        self.set_flag(collision);
+
+        self.draw_flag = true;
+    }
+
+    // Skip the next instruction if key with the value of Vx is pressed
+    fn op_ex9e(&mut self, x: u8) {
+        if self.keys[usize::from(self.register(x))] {
+            self.skip();
+        }
+    }
+
+    // Skip the next instruction if key with the value of Vx is not pressed
+    fn op_exa1(&mut self, x: u8) {
+        if !self.keys[usize::from(self.register(x))] {
+            self.skip();
+        }
+    }
+
+    // Skip the next instruction if key Vx on the second keypad is pressed
+    // (CHIP-8X)
+    fn op_exf2(&mut self, x: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        if self.second_keys[usize::from(self.register(x) & 0xF)] {
+            self.skip();
+        }
+    }
+
+    // Skip the next instruction if key Vx on the second keypad is not
+    // pressed (CHIP-8X)
+    fn op_exf5(&mut self, x: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        if !self.second_keys[usize::from(self.register(x) & 0xF)] {
+            self.skip();
+        }
+    }
+
+    // Set Vx = delay timer value
+    fn op_fx07(&mut self, x: u8) {
+        self.registers[usize::from(x)] = self.dt;
+    }
+
+    // Wait for a key press, store the value of the key in Vx
+    fn op_fx0a(&mut self, x: u8) {
+        self.wait_key = x;
+    }
+
+    // Set delay timer = Vx
+    fn op_fx15(&mut self, x: u8) {
+        self.dt = self.register(x);
+    }
+
+    // Set sound timer = Vx
+    fn op_fx18(&mut self, x: u8) {
+        self.st = self.register(x);
+    }
+
+    // Set I = I + Vx
+    fn op_fx1e(&mut self, x: u8) {
+        self.check_increment();
+
+        self.i += u32::from(self.register(x));
+    }
+
+    // Set I = location of sprite for digit Vx
+    fn op_fx29(&mut self, x: u8) {
+        self.i = u32::from(self.register(x)) * 5;
+        self.i_incremented = false;
+    }
+
+    // Set I = the 16 bit address that follows (XO-CHIP)
+    fn op_f000(&mut self) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.i = u32::from(self.word(usize::from(self.pc)));
+        self.i_incremented = false;
+        self.pc += 2;
+    }
+
+    // Select the display planes that drawing, clearing and scrolling
+    // apply to (XO-CHIP)
+    fn op_fn01(&mut self, n: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.display.select_planes(n);
+    }
+
+    // Load the 16 byte audio pattern at I (XO-CHIP)
+    fn op_f002(&mut self) {
+        self.violation(Violation::ExtendedInstruction);
+
+        let pattern = self.span(self.i as usize, 16);
+        self.audio_pattern.copy_from_slice(&self.memory[pattern]);
+        self.memory_accesses += 16;
+    }
+
+    // Set the audio pattern's playback rate to 4000*2^((Vx-64)/48)Hz
+    // (XO-CHIP)
+    fn op_fx3a(&mut self, x: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.pitch = self.register(x);
+    }
+
+    // Set I = location of the large sprite for digit Vx
+    fn op_fx30(&mut self, x: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.i = (BIG_FONT_ADDRESS + usize::from(self.register(x) & 0xF) * 10) as u32;
+        self.i_incremented = false;
+    }
+
+    // Store BCD representation of Vx in memory locations I, I+1, and I+2
+    fn op_fx33(&mut self, x: u8) {
+        self.check_increment();
+        self.check_write(3);
+
+        self.memory_accesses += 3;
+
+        let vx = self.register(x);
+
+        let digits = self.span(self.i as usize, 3);
+
+        self.memory[digits].copy_from_slice(&[vx / 100, vx % 100 / 10, vx % 10]);
+    }
+
+    // Store registers V0 through Vx in memory starting at location I
+    fn op_fx55(&mut self, x: u8) {
+        self.check_increment();
+        self.check_write(usize::from(x) + 1);
+
+        self.memory_accesses += u32::from(x) + 1;
+
+        let span = self.span(self.i as usize, usize::from(x) + 1);
+        self.memory[span].copy_from_slice(&self.registers[0..=usize::from(x)]);
+
+        self.increment_i(x);
+    }
+
+    // Read registers V0 through Vx from memory starting at location I
+    fn op_fx65(&mut self, x: u8) {
+        self.check_increment();
+
+        self.memory_accesses += u32::from(x) + 1;
+
+        let span = self.span(self.i as usize, usize::from(x) + 1);
+        self.registers[0..=usize::from(x)].copy_from_slice(&self.memory[span]);
+
+        self.increment_i(x);
+    }
+
+    // Store registers V0 through Vx in the RPL user flags
+    fn op_fx75(&mut self, x: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        let x = usize::from(x);
+        self.rpl_flags[..=x].copy_from_slice(&self.registers[..=x]);
+    }
+
+    // Read registers V0 through Vx from the RPL user flags
+    fn op_fx85(&mut self, x: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        let x = usize::from(x);
+        self.registers[..=x].copy_from_slice(&self.rpl_flags[..=x]);
+    }
+
+    // Switch memory bank VX into the window at 0x800 (banking extension)
+    fn op_fxb0(&mut self, x: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        let bank = usize::from(self.register(x));
+
+        if let Some(banks) = &mut self.banks {
+            if !banks.switch(&mut self.memory, bank) {
+                warn!(
+                    "ROM switched to bank {}, but it only has {}",
+                    bank,
+                    banks.len()
+                );
+            }
+        }
+    }
+
+    // Send Vx to the output port, where the VP-595 sound board takes it as
+    // its tone (CHIP-8X)
+    fn op_fxf8(&mut self, x: u8) {
+        self.violation(Violation::ExtendedInstruction);
+
+        self.output_port = self.register(x);
+    }
+
+    fn scroll_amount(&self, n: u8) -> u8 {
+        if self.quirks.half_pixel_scroll && self.display.resolution() == Resolution::LORES {
+            n / 2
+        } else {
+            n
+        }
+    }
+
+    // Stop on an opcode no interpreter defines, or skip over it in lenient
+    // mode
+    fn unknown_opcode(&mut self) {
+        let address = self.pc - 2;
+
+        match &mut self.unknown {
+            Some(unknown) => unknown.record(self.opcode, address),
+            None => panic!("{}", self.unsupported()),
+        }
+    }
+
+    // Why the opcode can't run, naming the platforms that have an
+    // instruction for it, where here it's unknown or a machine code
+    // routine nothing handled
+    fn unsupported(&self) -> String {
+        let here = self.table.instruction(self.opcode);
+        let elsewhere: Vec<&str> = Platform::ALL
+            .iter()
+            .filter(|&&platform| {
+                let instruction = instruction_table(platform, self.banking, &self.extensions)
+                    .instruction(self.opcode);
+
+                instruction.is_some() && instruction != here
+            })
+            .map(Platform::name)
+            .collect();
+
+        if elsewhere.is_empty() {
+            format!("unknown opcode {:04x}", self.opcode)
+        } else {
+            format!(
+                "opcode {:04x} isn't supported on {}, it runs with --platform {}",
+                self.opcode,
+                self.platform.name(),
+                elsewhere.join(", ")
+            )
+        }
+    }
+
+    fn violation(&mut self, violation: Violation) {
+        if let Some(report) = &mut self.report {
+            report.record(violation, self.pc - 2, self.opcode);
+        }
+    }
+
+    // The `len` bytes of memory from `address`, which instructions read and
+    // write through. Running past the end of memory stops the ROM, like an
+    // unknown opcode.
+    fn span(&self, address: usize, len: usize) -> Range<usize> {
+        if address + len > self.memory.len() {
+            panic!(
+                "address out of range: {} bytes at {:#x} run past the end of {:#x} bytes of memory",
+                len,
+                address,
+                self.memory.len()
+            );
+        }
+
+        address..address + len
+    }
+
+    // The big endian 16 bit value at `address`
+    fn word(&self, address: usize) -> u16 {
+        let span = self.span(address, 2);
+
+        u16::from_be_bytes([self.memory[span.start], self.memory[span.start + 1]])
+    }
+
+    // Record writes of `len` bytes at I that land in the interpreter area,
+    // or on the address being watched
+    fn check_write(&mut self, len: usize) {
+        if self.map.interpreter().contains(&(self.i as usize)) {
+            self.violation(Violation::InterpreterWrite);
+        }
+
+        if let Some(watch) = &mut self.watch {
+            if (self.i as usize..self.i as usize + len).contains(&watch.address) {
+                watch.pending = Some(self.pc - 2);
+            }
+        }
+    }
+
+    fn sensitive(&mut self, sensitivity: Sensitivity) {
+        if self.sensitivities.insert(sensitivity) {
+            warn!(
+                "ROM depends on the {} quirk, try toggling `{}` if it misbehaves",
+                sensitivity.describe(),
+                sensitivity.quirk()
+            );
+        }
+    }
+
+    // FX55/FX65 leave I after the last register they touched with the
+    // memory increment quirk, or on it when it's incremented by X
+    fn increment_i(&mut self, x: u8) {
+        if self.quirks.memory_increment {
+            let registers = if self.quirks.memory_increment_by_x {
+                x
+            } else {
+                x + 1
+            };
+
+            self.i += u32::from(registers);
+            self.i_incremented = true;
+        }
+    }
+
+    // Using I after FX55/FX65 moved it gives different results per interpreter
+    fn check_increment(&mut self) {
+        if self.i_incremented {
+            self.sensitive(Sensitivity::MemoryIncrement);
+        }
+    }
+
+    fn xo_chip(&self) -> bool {
+        self.platform == Platform::XoChip
+    }
+
+    fn mega_chip(&self) -> bool {
+        self.platform == Platform::MegaChip
+    }
+
+    // Skip the next instruction, which on XO-CHIP may be the four byte
+    // `F000 NNNN`, and on Mega-CHIP `01NN NNNN`
+    fn skip(&mut self) {
+        let next = &self.memory[self.span(usize::from(self.pc), 2)];
+        self.skipped = true;
+
+        if (self.xo_chip() && next == [0xF0, 0x00]) || (self.mega_chip() && next[0] == 0x01) {
+            self.pc += 4;
+        } else {
+            self.pc += 2;
+        }
+    }
+
+    fn register(&self, index: u8) -> u8 {
+        self.registers[usize::from(index)]
+    }
+
+    fn set_flag<T: Into<u8>>(&mut self, value: T) {
+        self.registers[0xF] = value.into();
+    }
+}
+
+// Register indexes from x to y inclusive, counting down when y is lower
+fn register_range(x: u8, y: u8) -> Vec<usize> {
+    let (x, y) = (usize::from(x), usize::from(y));
+
+    if x <= y {
+        (x..=y).collect()
+    } else {
+        (y..=x).rev().collect()
+    }
+}
+
+// Why a ROM of `size` bytes can't be loaded from `start` up to `end`, if
+// it can't
+fn oversize(size: usize, start: usize, end: usize, platform: Platform) -> Option<String> {
+    let available = end.saturating_sub(start);
+
+    if size <= available {
+        return None;
+    }
+
+    let mut problem = format!(
+        "ROM is {} bytes, {} more than the {} bytes of memory from {:#05x} to {:#05x}",
+        size,
+        size - available,
+        available,
+        start,
+        end - 1
+    );
+
+    let xo_chip = Platform::XoChip.memory_size().saturating_sub(start);
+
+    if platform != Platform::XoChip && platform != Platform::MegaChip && size <= xo_chip {
+        problem +=
+            ". It fits in XO-CHIP's 64K, run it with --platform xo-chip if it's an XO-CHIP ROM";
+    } else {
+        problem += ", it's too large for this platform";
+    }
+
+    Some(problem)
+}
+
+// Where a click landed, with the instructions to put a sprite there
+fn print_coords(x: usize, y: usize) {
+    println!("({}, {})", x, y);
+    println!("  LD V0, {:#04X}  LD V1, {:#04X}", x, y);
+    println!("  v0 := {}  v1 := {}", x, y);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::RomBuilder;
+    use std::sync::{Arc, Mutex};
+
+    // Waits for a key into V0, then copies it to V1
+    fn boot() -> Device {
+        let rom = RomBuilder::new().wait_key(0).ld_vv(1, 0).halt().build();
+
+        let mut device = Device::new(Config::default());
+        device.load_rom(&rom);
+        device
+    }
+
+    fn run(rom: &mut RomBuilder) -> Device {
+        let mut device = Device::new(Config::default());
+        device.load_rom(&rom.halt().build());
+        device.run_frame();
+        device
+    }
+
+    #[test]
+    fn adds_with_carry_flag() {
+        let device = run(RomBuilder::new().ld_v(0, 0xFF).ld_v(1, 0x02).add_vv(0, 1));
+
+        assert_eq!(device.registers[0], 0x01);
+        assert_eq!(device.registers[0xF], 1);
+    }
+
+    #[test]
+    fn stores_bcd() {
+        let device = run(RomBuilder::new().ld_v(0, 237).ld_i(0x300).ld_b(0));
+
+        assert_eq!(device.memory[0x300..0x303], [2, 3, 7]);
+    }
+
+    #[test]
+    fn follows_the_configured_quirks() {
+        let mut device = Device::new(Config {
+            quirks: Quirks {
+                vf_reset: false,
+                memory_increment: false,
+                display_wait: false,
+                shifting: true,
+                jumping: true,
+                ..Quirks::default()
+            },
+            ..Config::default()
+        });
+        device.load_rom(
+            &RomBuilder::new()
+                .ld_v(1, 0b11)
+                .ld_v(2, 0x10)
+                .shr(1, 2)
+                .ld_v(0, 0xFF)
+                .ld_i(0x300)
+                .store(0)
+                .ld_v(0, 0)
+                .drw(0, 0, 1)
+                .drw(0, 1, 1)
+                .ld_v(0xF, 1)
+                .or(0, 0)
+                .jump_v0(0x21A - 0x10)
+                .halt()
+                .build(),
+        );
+        device.run_frame();
+
+        assert_eq!(device.registers[1], 0b01);
+        assert_eq!(device.registers[0xF], 1);
+        assert_eq!(device.i, 0x300);
+        assert_eq!(
+            [device.display.buffer()[0], device.display.buffer()[64]],
+            [1, 1]
+        );
+        assert_eq!(device.pc, 0x21A);
+    }
+
+    #[test]
+    fn moves_i_by_x_with_the_memory_increment_quirk() {
+        let mut device = Device::new(Config {
+            quirks: Quirks {
+                memory_increment_by_x: true,
+                ..Quirks::default()
+            },
+            ..Config::default()
+        });
+        device.load_rom(
+            &RomBuilder::new()
+                .ld_i(0x300)
+                .store(2)
+                .load(1)
+                .halt()
+                .build(),
+        );
+        device.run_frame();
+
+        assert_eq!(device.i, 0x303);
+    }
+
+    #[test]
+    fn keeps_rpl_flags_across_restores() {
+        let mut device = Device::new(Config::default());
+        device.load_rom(
+            &RomBuilder::new()
+                .ld_v(0, 1)
+                .ld_v(1, 2)
+                .opcode(0xF175)
+                .halt()
+                .build(),
+        );
+        let start = device.snapshot();
+        device.run_frame();
+
+        device.restore(&start);
+        device.registers[..2].fill(0);
+        device.op_fx85(1);
+
+        assert_eq!(device.registers[..2], [1, 2]);
+    }
+
+    #[test]
+    fn moves_state_through_octo() {
+        let mut device = Device::new(Config::default());
+        device.load_rom(&RomBuilder::new().ld_v(3, 7).call(0x204).halt().build());
+        device.run_frame();
+        device.op_00ff();
+        device.display.draw(0, 0, &[0x80]);
+
+        let exported = device.octo_state().to_json().to_string();
+        let mut other = Device::new(Config::default());
+        other.set_octo_state(&Machine::parse(&exported).unwrap());
+
+        assert_eq!(other.snapshot(), device.snapshot());
+    }
+
+    #[test]
+    fn exits_only_when_asked_to_close() {
+        let rom = RomBuilder::new().opcode(0x00FD).build();
+
+        let mut halting = Device::new(Config::default());
+        halting.load_rom(&rom);
+        halting.run_frame();
+
+        assert!(halting.is_halted() && !halting.has_exited());
+
+        let mut closing = Device::new(Config {
+            exit_action: ExitAction::Close,
+            ..Config::default()
+        });
+        closing.load_rom(&rom);
+        closing.run_frame();
+
+        assert!(closing.has_exited());
+    }
+
+    #[test]
+    fn points_at_large_digits() {
+        let device = run(RomBuilder::new().ld_v(0, 7).ld_hf(0));
+
+        assert_eq!(device.i, 0x50 + 70);
+        assert_eq!(device.memory[0x50 + 70..0x50 + 72], [0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn toggles_quirks_while_running() {
+        let mut device = run(&mut RomBuilder::new());
+
+        device.set_quirk("preserve_on_resize", None);
+        assert!(device.quirks.preserve_on_resize);
+
+        device.set_quirk("preserve_on_resize", Some(false));
+        device.set_quirk("no_such_quirk", Some(true));
+        assert!(!device.quirks.preserve_on_resize);
+    }
+
+    #[test]
+    fn shifts_vx_once_the_shifting_quirk_is_on() {
+        let mut device = Device::new(Config::default());
+        device.registers[..2].copy_from_slice(&[0x81, 0x02]);
+
+        device.op_8xy6(0, 1);
+        assert_eq!([device.registers[0], device.registers[0xF]], [0x01, 0]);
+
+        device.set_quirk("shifting", None);
+
+        device.registers[0] = 0x81;
+        device.op_8xy6(0, 1);
+        assert_eq!([device.registers[0], device.registers[0xF]], [0x40, 1]);
+
+        device.registers[0] = 0x81;
+        device.op_8xye(0, 1);
+        assert_eq!([device.registers[0], device.registers[0xF]], [0x02, 1]);
+    }
+
+    #[test]
+    fn switches_banks() {
+        let mut rom = RomBuilder::new();
+        rom.ld_v(0, 1).opcode(0xF0B0).jump(0x800);
+        rom.data(&vec![0; 0x800 - rom.here() as usize]);
+        // bank 0
+        rom.data(&[0; 0x800]);
+        // bank 1
+        // the builder counts past 0xFFF here, so halt at the window address
+        rom.ld_v(1, 0x42).jump(0x802);
+
+        let mut device = Device::new(Config {
+            banking: true,
+            ..Config::default()
+        });
+        device.load_rom(&rom.build());
+        device.run_frame();
+
+        assert_eq!(device.registers[1], 0x42);
+
+        let mut restored = Device::new(Config {
+            banking: true,
+            ..Config::default()
+        });
+        restored.load_rom(&rom.build());
+        restored.restore(&device.snapshot());
+
+        assert_eq!(restored.banks.as_ref().unwrap().current(), 1);
+    }
+
+    #[test]
+    fn calls_timer_hook_every_tick() {
+        let ticks = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&ticks);
+
+        let mut device = Device::new(Config::default());
+        device.load_rom(&RomBuilder::new().ld_v(0, 3).ld_dt(0).halt().build());
+        device.on_timer(move |tick| log.lock().unwrap().push((tick.frame, tick.delay)));
+
+        for _ in 0..4 {
+            device.run_frame();
         }
+
+        assert_eq!(*ticks.lock().unwrap(), [(0, 2), (1, 1), (2, 0), (3, 0)]);
     }
 
-    pub fn load(&mut self, path: &str) {
-        info!("Loading ROM '{}'", path);
+    #[test]
+    fn names_the_platforms_an_opcode_runs_on() {
+        let mut device = Device::new(Config {
+            platform: Platform::Chip8,
+            ..Config::default()
+        });
+        device.opcode = 0x00FF;
 
-        let mut file = File::open(path).unwrap();
-        let program = &mut self.memory[0x200..0xFFF];
-        let bytes = file.read(program).unwrap();
+        assert_eq!(
+            device.unsupported(),
+            "opcode 00ff isn't supported on chip8, it runs with --platform schip, xo-chip, megachip"
+        );
 
-        info!("Loaded {} bytes", bytes);
+        device.opcode = 0x8008;
 
-        self.memory[..Self::FONT.len()].copy_from_slice(&Self::FONT);
+        assert_eq!(device.unsupported(), "unknown opcode 8008");
     }
 
-    pub fn run(&mut self, channel: Receiver<Event>) {
-        let mut timer = std::time::Instant::now();
+    #[test]
+    fn lists_the_instructions_a_platform_runs() {
+        for &platform in &Platform::ALL {
+            for instruction in instruction_table(platform, true, &[]).reachable() {
+                let code = instruction.code;
+                assert!(disasm::form(code).is_some(), "{} has no form", code);
+            }
+        }
 
-        'outer: loop {
-            let elapsed = timer.elapsed().as_secs_f64();
+        let mut device = Device::new(Config {
+            platform: Platform::Chip8X,
+            ..Config::default()
+        });
 
-            if elapsed >= 1.0 / 60.0 {
-                timer = std::time::Instant::now();
+        assert!(device.reference("00ff")[0].starts_with("0NNN  SYS NNN"));
+        assert!(device.reference("jp v0").is_empty());
+        assert_eq!(device.reference("colour").len(), 2);
 
-                let mut cycles = 0;
+        device.set_platform(Platform::Schip);
 
-                // about a 720Mhz clock speed
-                while cycles < 12 {
-                    // simulate blocking execution until
-                    // key is pressed
-                    if self.wait_key != 0xFF {
-                        break;
-                    }
+        assert!(device.reference("00FF")[0].starts_with("00FF  HIGH"));
+        assert!(device.reference("shr")[0].contains("shifting:"));
+    }
 
-                    self.tick();
-                    cycles += 1;
+    #[test]
+    fn switches_fonts() {
+        let mut device = Device::new(Config {
+            font: Some(Font::Eti660),
+            ..Config::default()
+        });
+        device.load_rom(&RomBuilder::new().halt().build());
 
-                    // simulate waiting for screen refresh
-                    // after drawing
-                    if self.draw_flag {
-                        break;
-                    }
-                }
+        assert_eq!(device.memory[..80], font::ETI_660);
+        assert_eq!(device.set_font(None), Font::FishNChips);
+        assert_eq!(device.memory[..80], font::FISH_N_CHIPS);
+
+        device.set_font(Some(Font::Vip));
+        device.set_platform(Platform::Dream6800);
+        device.load_rom(&RomBuilder::new().halt().build());
+
+        assert_eq!(device.memory[..80], font::VIP);
+    }
 
-                self.handle_delay();
-                self.handle_sound();
-                self.screen.refresh();
+    #[test]
+    fn runs_instructions_added_by_the_embedder() {
+        struct Experiment;
+
+        impl InstructionSet for Experiment {
+            fn name(&self) -> &'static str {
+                "experiment"
             }
 
-            'events: loop {
-                match channel.try_recv() {
-                    Ok(event) => match event {
-                        Event::Key(key, pressed) => self.handle_key(key, pressed),
-                        Event::Off => break 'outer,
-                        Event::On(_) => panic!("Should never receive `On`"),
-                    },
-                    Err(TryRecvError::Empty) => break 'events,
-                    Err(TryRecvError::Disconnected) => break 'outer,
-                }
+            fn instructions(&self) -> Vec<Instruction> {
+                vec![Instruction::new("5XY4", "Add VY to VX", |cpu| {
+                    cpu.registers[usize::from(cpu.x)] += cpu.registers[usize::from(cpu.y)];
+                })]
             }
         }
+
+        let mut device = Device::new(Config {
+            platform: Platform::Chip8,
+            ..Config::default()
+        });
+        device.extend(&Experiment);
+        device.load_rom(
+            &RomBuilder::new()
+                .ld_v(0, 2)
+                .ld_v(1, 3)
+                .opcode(0x5014)
+                .halt()
+                .build(),
+        );
+        device.run_frame();
+
+        assert_eq!(device.registers[0], 5);
+        assert!(device.reference("5014")[0].contains("Add VY to VX [custom]"));
+        assert_eq!(device.reference("jp").len(), 2);
     }
 
-    fn handle_delay(&mut self) {
-        if self.dt > 0 {
-            self.dt -= 1;
-        }
+    #[test]
+    fn passes_machine_routines_to_the_hook() {
+        let mut device = Device::new(Config::default());
+        device.load_rom(&RomBuilder::new().opcode(0x0ABC).halt().build());
+        device.on_machine_routine(|call| {
+            call.registers[0] = call.memory[usize::from(call.address)];
+            call.address == 0xABC
+        });
+        device.memory[0xABC] = 0x42;
+        device.run_frame();
+
+        assert_eq!(device.registers[0], 0x42);
+        assert_eq!(device.pc, 0x202);
     }
 
-    fn handle_sound(&mut self) {
-        if self.st > 0 {
-            self.window.set_title("🔊");
-            self.st -= 1;
-        } else {
-            self.window.set_title("CHIP8");
-        }
+    #[test]
+    fn passes_keys_through_the_hook() {
+        let mut device = Device::new(Config::default());
+        device.on_key(|key, _| match key {
+            0x5 => Some(0x8),
+            0x6 => None,
+            key => Some(key),
+        });
+
+        device.host_key(0x5, true);
+        device.host_key(0x6, true);
+        device.host_key(0x7, true);
+
+        assert!(device.keys[0x8] && device.keys[0x7]);
+        assert!(!device.keys[0x5] && !device.keys[0x6]);
     }
 
-    fn handle_key(&mut self, key: u8, pressed: bool) {
-        self.keys[usize::from(key)] = pressed;
+    #[test]
+    fn retries_from_last_checkpoint() {
+        let mut device = Device::new(Config {
+            checkpoint_interval: Some(1),
+            ..Config::default()
+        });
+        device.load_rom(&RomBuilder::new().add_v(0, 1).jump(0x200).build());
 
-        if self.wait_key != 0xFF && !pressed {
-            self.registers[usize::from(self.wait_key)] = key;
-            self.wait_key = 0xFF;
+        for _ in 0..60 {
+            device.run_frame();
+        }
+
+        let checkpoint = device.registers[0];
+
+        for _ in 0..10 {
+            device.run_frame();
         }
+
+        assert_ne!(device.registers[0], checkpoint);
+
+        device.retry();
+
+        assert_eq!(device.registers[0], checkpoint);
     }
 
-    fn fetch(&mut self) -> Opcode {
-        let top = (self.memory[self.pc as usize] as u16) << 8;
-        let bottom = self.memory[self.pc as usize + 1];
-        let raw = top | bottom as u16;
+    #[test]
+    fn skips_unknown_opcodes_when_lenient() {
+        let mut device = Device::new(Config {
+            lenient: true,
+            ..Config::default()
+        });
+        device.load_rom(&RomBuilder::new().opcode(0xFFFF).ld_v(0, 7).halt().build());
+        device.run_frame();
 
-        self.pc += 2;
+        assert_eq!(device.registers[0], 7);
+        assert!(!device.unknown.as_ref().unwrap().is_empty());
+    }
 
-        Opcode {
-            raw,
-            code: raw & 0xF000,
-            nnn: raw & 0x0FFF,
-            x: ((raw & 0x0F00) >> 8) as u8,
-            y: ((raw & 0x00F0) >> 4) as u8,
-            n: (raw & 0x000F) as u8,
-            kk: bottom,
+    #[test]
+    fn switches_between_lores_and_hires() {
+        // each switch redraws the display, ending the frame
+        let mut device = run(RomBuilder::new().opcode(0x00FF).opcode(0x00FE));
+        assert_eq!(device.display.resolution(), Resolution::HIRES);
+
+        device.run_frame();
+
+        assert_eq!(device.display.resolution(), Resolution::LORES);
+    }
+
+    #[test]
+    fn scrolls_with_schip_opcodes() {
+        // the top row of the 0 glyph lights the first four pixels
+        let mut device = run(RomBuilder::new()
+            .opcode(0x00FF)
+            .ld_f(0)
+            .drw(0, 0, 5)
+            .opcode(0x00C2)
+            .opcode(0x00FB));
+
+        for _ in 0..4 {
+            device.run_frame();
         }
+
+        let width = Resolution::HIRES.width;
+        let buffer = device.display.buffer();
+
+        assert_eq!(buffer[..8], [0; 8]);
+        assert_eq!(buffer[2 * width..2 * width + 8], [0, 0, 0, 0, 1, 1, 1, 1]);
     }
 
-    fn tick(&mut self) {
-        self.draw_flag = false;
+    #[test]
+    fn halves_scrolls_in_lores_only() {
+        let scrolled = |resolution: u16| {
+            let mut device = Device::new(Config {
+                quirks: Quirks {
+                    half_pixel_scroll: true,
+                    ..Quirks::default()
+                },
+                ..Config::default()
+            });
+            device.load_rom(
+                &RomBuilder::new()
+                    .opcode(resolution)
+                    .ld_f(0)
+                    .drw(0, 0, 5)
+                    .opcode(0x00FB)
+                    .halt()
+                    .build(),
+            );
 
-        let opcode = self.fetch();
+            for _ in 0..4 {
+                device.run_frame();
+            }
 
-        match opcode.code {
-            0x0000 => match opcode.kk {
-                0xEE => self.op_00ee(),
-                0xE0 => self.op_00e0(),
-                0x00 => {}
-                _ => panic!("unknown opcode {:04x}", opcode.raw),
-            },
-            0x1000 => self.op_1nnn(opcode.nnn),
-            0x2000 => self.op_2nnn(opcode.nnn),
-            0x3000 => self.op_3xkk(opcode.x, opcode.kk),
-            0x4000 => self.op_4xkk(opcode.x, opcode.kk),
-            0x5000 => self.op_5xy0(opcode.x, opcode.y),
-            0x6000 => self.op_6xkk(opcode.x, opcode.kk),
-            0x7000 => self.op_7xkk(opcode.x, opcode.kk),
-            0x8000 => match opcode.n {
-                0x0 => self.op_8xy0(opcode.x, opcode.y),
-                0x1 => self.op_8xy1(opcode.x, opcode.y),
-                0x2 => self.op_8xy2(opcode.x, opcode.y),
-                0x3 => self.op_8xy3(opcode.x, opcode.y),
-                0x4 => self.op_8xy4(opcode.x, opcode.y),
-                0x5 => self.op_8xy5(opcode.x, opcode.y),
-                0x6 => self.op_8xy6(opcode.x, opcode.y),
-                0x7 => self.op_8xy7(opcode.x, opcode.y),
-                0xE => self.op_8xye(opcode.x, opcode.y),
-                _ => panic!("unknown opcode {:04x}", opcode.raw),
-            },
-            0x9000 => self.op_9xy0(opcode.x, opcode.y),
-            0xA000 => self.op_annn(opcode.nnn),
-            0xB000 => self.op_bnnn(opcode.nnn),
-            0xC000 => self.op_cxkk(opcode.x, opcode.kk),
-            0xD000 => self.op_dxyn(opcode.x, opcode.y, opcode.n),
-            0xE000 => match opcode.kk {
-                0x9e => self.op_ex9e(opcode.x),
-                0xa1 => self.op_exa1(opcode.x),
-                _ => panic!("unknown opcode {:04x}", opcode.raw),
-            },
-            0xF000 => match opcode.kk {
-                0x07 => self.op_fx07(opcode.x),
-                0x0A => self.op_fx0a(opcode.x),
-                0x15 => self.op_fx15(opcode.x),
-                0x18 => self.op_fx18(opcode.x),
-                0x1e => self.op_fx1e(opcode.x),
-                0x29 => self.op_fx29(opcode.x),
-                0x33 => self.op_fx33(opcode.x),
-                0x55 => self.op_fx55(opcode.x),
-                0x65 => self.op_fx65(opcode.x),
-                _ => panic!("unknown opcode {:04x}", opcode.raw),
-            },
-            _ => panic!("unknown opcode {:04x}", opcode.raw),
-        }
+            device.display.buffer()[..8].to_vec()
+        };
+
+        // lores scrolls by 2 of its pixels, hires by the full 4
+        assert_eq!(scrolled(0x00FE), [0, 0, 1, 1, 1, 1, 0, 0]);
+        assert_eq!(scrolled(0x00FF), [0, 0, 0, 0, 1, 1, 1, 1]);
     }
 
-    // Return from a subroutine
-    fn op_00ee(&mut self) {
-        self.sp -= 1;
-        self.pc = self.stack[self.sp];
+    #[test]
+    fn starts_hires_chip8_past_the_patch() {
+        let mut rom = RomBuilder::new();
+        rom.jump(0x260);
+        rom.data(&[0; 0xC0 - 2]);
+        rom.opcode(0x0230).ld_v(0, 1).halt();
+
+        let mut device = Device::new(Config {
+            platform: Platform::HiresChip8,
+            ..Config::default()
+        });
+        device.load_rom(&rom.build());
+
+        // clearing the display waits for the next frame
+        device.run_frame();
+        device.run_frame();
+
+        assert_eq!(device.display.resolution(), Resolution::TWO_PAGE);
+        assert_eq!(device.registers[0], 1);
     }
 
-    // Clear the display
-    fn op_00e0(&mut self) {
-        self.screen.clear();
+    #[test]
+    fn sizes_memory_as_configured() {
+        let mut device = Device::new(Config {
+            memory_size: Some(0x800),
+            ..Config::default()
+        });
+        device.load_rom(&RomBuilder::new().halt().build());
 
-        self.draw_flag = true;
+        assert_eq!(device.memory.len(), 0x800);
+        assert_eq!(device.memory[BIG_FONT_ADDRESS], font::BIG[0]);
+        assert!(
+            oversize(0x600, PROGRAM_ADDRESS, device.map.work, Platform::Chip8)
+                .unwrap()
+                .contains("from 0x200 to 0x7fe")
+        );
+
+        device.set_platform(Platform::XoChip);
+
+        assert_eq!(device.memory.len(), 0x800);
+        assert_eq!(device.map.work, 0x800);
     }
 
-    // Jump to location at nnn
-    fn op_1nnn(&mut self, nnn: u16) {
-        self.pc = nnn;
+    // Run a few frames of `rom` in 1K of memory, returning why it stopped
+    fn fault(platform: Platform, rom: &RomBuilder) -> String {
+        let mut device = Device::new(Config {
+            platform,
+            memory_size: Some(0x400),
+            ..Config::default()
+        });
+        device.load_rom(&rom.build());
+
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            for _ in 0..4 {
+                device.run_frame();
+            }
+        }))
+        .unwrap_err();
+
+        *payload.downcast::<String>().unwrap()
     }
 
-    // Call subroutine at nnn
-    fn op_2nnn(&mut self, nnn: u16) {
-        self.stack[self.sp] = self.pc;
-        self.sp += 1;
-        self.pc = nnn;
+    // A ROM ending with `opcode` in the last two bytes of 1K of memory
+    fn at_the_end(opcode: u16) -> RomBuilder {
+        let mut rom = RomBuilder::new();
+        rom.jump(0x3FE);
+        rom.data(&vec![0; usize::from(0x3FE - rom.here())]);
+        rom.opcode(opcode);
+        rom
     }
 
-    // Skip next instruction if Vx = kk
-    fn op_3xkk(&mut self, x: u8, kk: u8) {
-        if self.register(x) == kk {
-            self.pc += 2;
-        }
+    #[test]
+    fn stops_fetching_past_the_end_of_memory() {
+        let rom = RomBuilder::new().jump(0x3FF).clone();
+
+        assert!(fault(Platform::Chip8, &rom).starts_with("address out of range"));
     }
 
-    // Skip next instruction if Vx != kk
-    fn op_4xkk(&mut self, x: u8, kk: u8) {
-        if self.register(x) != kk {
-            self.pc += 2;
+    #[test]
+    fn stops_writing_past_the_end_of_memory() {
+        for (platform, rom) in [
+            (
+                Platform::Chip8,
+                RomBuilder::new().ld_i(0x3FF).store(1).clone(),
+            ),
+            (
+                Platform::Chip8,
+                RomBuilder::new().ld_i(0x3FE).ld_b(0).clone(),
+            ),
+            (
+                Platform::XoChip,
+                RomBuilder::new().ld_i(0x3FF).store_range(0, 1).clone(),
+            ),
+        ] {
+            assert!(fault(platform, &rom).starts_with("address out of range"));
         }
     }
 
-    // Skip next instruction if Vx = Vy
-    fn op_5xy0(&mut self, x: u8, y: u8) {
-        if self.register(x) == self.register(y) {
-            self.pc += 2;
+    #[test]
+    fn stops_reading_past_the_end_of_memory() {
+        for (platform, rom) in [
+            (
+                Platform::Chip8,
+                RomBuilder::new().ld_i(0x3FF).load(1).clone(),
+            ),
+            (
+                Platform::XoChip,
+                RomBuilder::new().ld_i(0x3FF).load_range(0, 1).clone(),
+            ),
+            (
+                Platform::XoChip,
+                RomBuilder::new().ld_i(0x3F8).audio().clone(),
+            ),
+            (
+                Platform::MegaChip,
+                RomBuilder::new().ld_i(0x3FC).opcode(0x0202).clone(),
+            ),
+        ] {
+            assert!(fault(platform, &rom).starts_with("address out of range"));
         }
     }
 
-    // Set Vx = kk
-    fn op_6xkk(&mut self, x: u8, kk: u8) {
-        self.registers[usize::from(x)] = kk;
+    #[test]
+    fn stops_drawing_sprites_past_the_end_of_memory() {
+        for (platform, rom) in [
+            (
+                Platform::Chip8,
+                RomBuilder::new().ld_i(0x3FF).drw(0, 0, 2).clone(),
+            ),
+            (
+                Platform::MegaChip,
+                RomBuilder::new()
+                    .opcode(0x0011)
+                    .ld_i(0x3FF)
+                    .opcode(0x0301)
+                    .opcode(0x0402)
+                    .drw(0, 0, 1)
+                    .clone(),
+            ),
+        ] {
+            assert!(fault(platform, &rom).starts_with("address out of range"));
+        }
     }
 
-    // Set Vx = Vx + kk
-    fn op_7xkk(&mut self, x: u8, kk: u8) {
-        self.registers[usize::from(x)] = self.register(x).overflowing_add(kk).0;
+    #[test]
+    fn stops_reading_operands_past_the_end_of_memory() {
+        for (platform, opcode) in [
+            // F000 NNNN and 01NN NNNN read the address that follows
+            (Platform::XoChip, 0xF000),
+            (Platform::MegaChip, 0x0100),
+            // skips look at the instruction they step over
+            (Platform::XoChip, 0x3000),
+        ] {
+            assert!(fault(platform, &at_the_end(opcode)).starts_with("address out of range"));
+        }
     }
 
-    // Set Vx = Vy
-    fn op_8xy0(&mut self, x: u8, y: u8) {
-        self.registers[usize::from(x)] = self.register(y);
+    #[test]
+    #[should_panic(expected = "Banking needs at least 4K of memory")]
+    fn refuses_banking_in_less_than_4k() {
+        Device::new(Config {
+            banking: true,
+            memory_size: Some(0x800),
+            ..Config::default()
+        });
     }
 
-    // Set Vx = Vx OR Vy
-    fn op_8xy1(&mut self, x: u8, y: u8) {
-        self.registers[usize::from(x)] |= self.register(y);
-        self.set_flag(false); // Quirk
+    #[test]
+    fn runs_xo_chip_extensions() {
+        let mut rom = RomBuilder::new();
+        rom.ld_v(1, 1).ld_v(2, 2).ld_v(3, 3);
+        // skipping a long instruction steps over all four bytes
+        rom.se_v(0, 0).opcode(0xF000).opcode(0x1200);
+        rom.opcode(0xF000).opcode(0x1234);
+        rom.store_range(3, 1).load_range(1, 3).halt();
+
+        let mut device = Device::new(Config {
+            platform: Platform::XoChip,
+            ..Config::default()
+        });
+        device.load_rom(&rom.build());
+        device.run_frame();
+
+        assert_eq!(device.memory.len(), 0x10000);
+        assert_eq!(device.i, 0x1234);
+        assert_eq!(device.memory[0x1234..0x1237], [3, 2, 1]);
+        assert_eq!(device.registers[1..4], [3, 2, 1]);
     }
 
-    // Set Vx = Vx AND Vy
-    fn op_8xy2(&mut self, x: u8, y: u8) {
-        self.registers[usize::from(x)] &= self.register(y);
-        self.set_flag(false); // Quirk
+    #[test]
+    fn sets_audio_patterns_and_pitch() {
+        let mut rom = RomBuilder::new();
+        rom.ld_i(0x20A).audio().ld_v(5, 112).pitch(5).halt();
+        rom.data(&[0xF0; 16]);
+
+        let mut device = Device::new(Config {
+            platform: Platform::XoChip,
+            ..Config::default()
+        });
+        device.load_rom(&rom.build());
+        device.run_frame();
+
+        assert_eq!(
+            device.tone(),
+            Tone::Pattern {
+                samples: [0xF0; 16],
+                pitch: 112
+            }
+        );
+        assert_eq!(Tone::rate(112), 8000.0);
     }
 
-    // Set Vx = Vx XOR Vy
-    fn op_8xy3(&mut self, x: u8, y: u8) {
-        self.registers[usize::from(x)] ^= self.register(y);
-        self.set_flag(false); // Quirk
+    #[test]
+    fn explains_roms_too_large_for_memory() {
+        assert_eq!(oversize(0xDFF, 0x200, 0xFFF, Platform::Chip8), None);
+        assert_eq!(
+            oversize(0xE10, 0x200, 0xFFF, Platform::Chip8).unwrap(),
+            "ROM is 3600 bytes, 17 more than the 3583 bytes of memory from 0x200 to 0xffe. \
+             It fits in XO-CHIP's 64K, run it with --platform xo-chip if it's an XO-CHIP ROM"
+        );
+        assert!(oversize(0x10000, 0x200, 0x10000, Platform::XoChip)
+            .unwrap()
+            .ends_with("too large for this platform"));
     }
 
-    // Set Vx = Vx + Vy, set VF = carry
-    fn op_8xy4(&mut self, x: u8, y: u8) {
-        let (result, carry) = self.register(x).overflowing_add(self.register(y));
+    #[test]
+    fn charges_vip_machine_cycles() {
+        let mut rom = RomBuilder::new();
+        rom.add_v(0, 1).jump(0x200);
 
-        self.registers[usize::from(x)] = result;
-        self.set_flag(carry);
+        let mut device = Device::new(Config {
+            vip_timing: true,
+            ..Config::default()
+        });
+        device.load_rom(&rom.build());
+        device.run_frame();
+
+        // 50 and 52 cycles a time out of 2598
+        assert_eq!(device.instructions(), 51);
+        assert_eq!(device.registers[0], 26);
     }
 
-    // Set Vx = Vx - Vy, set VF = NOT borrow
-    fn op_8xy5(&mut self, x: u8, y: u8) {
-        let (result, carry) = self.register(x).overflowing_sub(self.register(y));
+    #[test]
+    fn colours_zones_and_reads_the_second_keypad() {
+        let mut rom = RomBuilder::new();
+        rom.ld_v(0, 0x10)
+            .ld_v(1, 0x00)
+            .ld_v(2, 4)
+            .opcode(0xB020)
+            .ld_v(3, 5)
+            .opcode(0xE3F2)
+            .ld_v(4, 1)
+            .ld_v(5, 0)
+            .ld_f(5)
+            .drw(5, 5, 1)
+            .halt();
 
-        self.registers[usize::from(x)] = result;
-        self.set_flag(!carry);
-    }
+        assert_eq!(
+            Device::new(Config {
+                platform: Platform::Chip8X,
+                ..Config::default()
+            })
+            .pc(),
+            0x300
+        );
 
-    // Set Vx = Vx SHR 1
-    fn op_8xy6(&mut self, x: u8, y: u8) {
-        let lsb = self.register(y) & 0b0000_0001;
+        let mut device = Device::new(Config {
+            platform: Platform::Chip8X,
+            start_address: Some(0x200),
+            ..Config::default()
+        });
+        device.load_rom(&rom.build());
+        device.second_keys[0x5] = true;
 
-        self.registers[usize::from(x)] = self.register(y) >> 1;
-        self.set_flag(lsb);
-    }
+        for _ in 0..3 {
+            device.run_frame();
+        }
 
-    // Set Vx = Vy - Vx, set VF = NOT borrow
-    fn op_8xy7(&mut self, x: u8, y: u8) {
-        let (result, carry) = self.register(y).overflowing_sub(self.register(x));
+        let pixels = device.display().rgba(&Palette::default(), 1).pixels;
 
-        self.registers[usize::from(x)] = result;
-        self.set_flag(!carry);
+        assert_eq!(device.registers[4], 0);
+        assert_eq!(pixels[..4], [0x00, 0xFF, 0x00, 0xFF]);
+        assert_eq!(pixels[5 * 4..6 * 4], [0x00, 0x00, 0x80, 0xFF]);
     }
 
-    // Set Vx = Vx SHR 1
-    fn op_8xye(&mut self, x: u8, y: u8) {
-        let msb = self.register(y) >> 7;
+    #[test]
+    fn draws_mega_chip_colour_sprites() {
+        let mut rom = RomBuilder::new();
+        rom.opcode(0x0011)
+            .opcode(0x0100)
+            .opcode(0x021A)
+            .opcode(0x0201)
+            .opcode(0x0302)
+            .opcode(0x0401)
+            .opcode(0x0901)
+            .ld_i(0x21E)
+            .ld_v(0, 10)
+            .drw(0, 0, 1)
+            .drw(0, 0, 1)
+            .cls()
+            .halt();
+        rom.data(&[0xFF, 0x11, 0x22, 0x33]).data(&[0x00, 0x01]);
 
-        self.registers[usize::from(x)] = self.register(y) << 1;
-        self.set_flag(msb);
-    }
+        let mut device = Device::new(Config {
+            platform: Platform::MegaChip,
+            ..Config::default()
+        });
+        device.load_rom(&rom.build());
 
-    // Skip next instruction if Vx != Vy
-    fn op_9xy0(&mut self, x: u8, y: u8) {
-        if self.register(x) != self.register(y) {
-            self.pc += 2;
+        // drawing and switching modes each wait for the next frame
+        for _ in 0..5 {
+            device.run_frame();
         }
+
+        let display = device.display();
+        let pixel = |x: usize, y: usize| {
+            let start = (x + y * 256) * 4;
+            display.rgba(&Palette::default(), 1).pixels[start..start + 4].to_vec()
+        };
+
+        assert_eq!(display.resolution(), Resolution::MEGA);
+        assert_eq!(pixel(11, 10), [0x11, 0x22, 0x33, 0xFF]);
+        assert_eq!(pixel(10, 10), [0, 0, 0, 0xFF]);
+        // the second sprite landed on the first, which is the collision colour
+        assert_eq!(device.registers[0xF], 1);
     }
 
-    // Set I = nnn
-    fn op_annn(&mut self, nnn: u16) {
-        self.i = nnn;
+    #[test]
+    fn saves_register_ranges_without_moving_i() {
+        let rom = RomBuilder::new()
+            .ld_v(4, 0x44)
+            .ld_v(5, 0x55)
+            .ld_i(0x300)
+            .store_range(4, 5)
+            .store_range(5, 5)
+            .ld_v(4, 0)
+            .load_range(4, 4)
+            .halt()
+            .build();
+
+        let mut device = Device::new(Config {
+            platform: Platform::XoChip,
+            ..Config::default()
+        });
+        device.load_rom(&rom);
+        device.run_frame();
+
+        assert_eq!(device.i, 0x300);
+        assert_eq!(device.memory[0x300..0x302], [0x55, 0x55]);
+        assert_eq!(device.registers[4], 0x55);
     }
 
-    // Jump to location nnn + V0
-    fn op_bnnn(&mut self, nnn: u16) {
-        self.pc = nnn + u16::from(self.register(0));
+    #[test]
+    fn loads_xo_chip_roms_past_4k() {
+        let mut rom = RomBuilder::new();
+        rom.opcode(0xF000).opcode(0x1000).load(1).halt();
+        rom.data(&vec![0; 0x1000 - rom.here() as usize]);
+        rom.data(&[0xAB, 0xCD]);
+
+        let mut device = Device::new(Config {
+            platform: Platform::XoChip,
+            ..Config::default()
+        });
+        device.load_rom(&rom.build());
+        device.run_frame();
+
+        assert_eq!(device.registers[..2], [0xAB, 0xCD]);
     }
 
-    // Set Vx = random byte AND kk
-    fn op_cxkk(&mut self, x: u8, kk: u8) {
-        self.registers[usize::from(x)] = kk & rand::random::<u8>();
+    #[test]
+    fn steps_single_instructions_while_paused() {
+        let rom = RomBuilder::new()
+            .ld_v(0, 1)
+            .ld_v(1, 2)
+            .wait_key(2)
+            .ld_v(3, 4)
+            .build();
+
+        let mut device = Device::new(Config::default());
+        device.load_rom(&rom);
+        device.step(1);
+
+        assert!(device.paused);
+        assert_eq!(device.registers[..2], [1, 0]);
+
+        device.step(5);
+
+        assert_eq!(device.registers[..2], [1, 2]);
+        assert_eq!(device.pc, 0x206);
+        assert_eq!(device.instructions(), 3);
     }
 
-    // Display n-byte sprite starting at memory location I at (Vx, Vy)
-    fn op_dxyn(&mut self, x: u8, y: u8, n: u8) {
-        let x_pos = self.register(x);
-        let y_pos = self.register(y);
+    #[test]
+    fn loads_and_starts_at_the_start_address() {
+        let mut device = Device::new(Config {
+            start_address: Some(0x600),
+            ..Config::default()
+        });
+        device.load_rom(&[0x60, 0x2A, 0x16, 0x02]);
+        device.tick();
 
-        let sprite = &self.memory[usize::from(self.i)..usize::from(self.i + n as u16)];
+        assert_eq!(device.memory[0x200], 0);
+        assert_eq!(device.memory[0x600..0x604], [0x60, 0x2A, 0x16, 0x02]);
+        assert_eq!(device.registers[0], 0x2A);
+        assert_eq!(device.pc, 0x602);
+    }
 
-        let collision = self.screen.draw(x_pos, y_pos, sprite);
-        self.set_flag(collision);
+    #[test]
+    fn banks_from_the_start_address() {
+        let mut rom = vec![0; 0x200 + BANK_SIZE];
+        rom[..4].copy_from_slice(&[0x60, 0x2A, 0x16, 0x02]);
+        rom[0x200] = 0xBB;
 
-        self.draw_flag = true;
+        let mut device = Device::new(Config {
+            start_address: Some(0x600),
+            banking: true,
+            ..Config::default()
+        });
+        device.load_rom(&rom);
+        device.tick();
+
+        assert_eq!(device.memory[0x200], 0);
+        assert_eq!(device.memory[0x600..0x604], [0x60, 0x2A, 0x16, 0x02]);
+        assert_eq!(device.memory[WINDOW], 0xBB);
+        assert_eq!(device.registers[0], 0x2A);
+        assert_eq!(device.pc, 0x602);
     }
 
-    // Skip the next instruction if key with the value of Vx is pressed
-    fn op_ex9e(&mut self, x: u8) {
-        if self.keys[usize::from(self.register(x))] {
-            self.pc += 2
+    #[test]
+    fn limits_stack_depth() {
+        let rom = RomBuilder::new().call(0x200).build();
+
+        let mut device = Device::new(Config {
+            stack_depth: Some(12),
+            ..Config::default()
+        });
+        device.load_rom(&rom);
+
+        for _ in 0..4 {
+            device.run_frame();
         }
-    }
 
-    // Skip the next instruction if key with the value of Vx is not pressed
-    fn op_exa1(&mut self, x: u8) {
-        if !self.keys[usize::from(self.register(x))] {
-            self.pc += 2
+        assert!(device.halted);
+        assert_eq!(device.sp, 12);
+
+        let mut wrapping = Device::new(Config {
+            stack_depth: Some(12),
+            stack_overflow: StackOverflow::Wrap,
+            ..Config::default()
+        });
+        wrapping.load_rom(&rom);
+
+        for _ in 0..4 {
+            wrapping.run_frame();
         }
-    }
 
-    // Set Vx = delay timer value
-    fn op_fx07(&mut self, x: u8) {
-        self.registers[usize::from(x)] = self.dt;
+        assert!(!wrapping.halted);
     }
 
-    // Wait for a key press, store the value of the key in Vx
-    fn op_fx0a(&mut self, x: u8) {
-        self.wait_key = x;
-    }
+    #[test]
+    fn debounce_misses_short_presses() {
+        let mut device = boot();
+        device.quirks.debounce = true;
+        device.run_frame();
 
-    // Set delay timer = Vx
-    fn op_fx15(&mut self, x: u8) {
-        self.dt = self.register(x);
-    }
+        device.handle_key(0x4, true);
+        device.handle_key(0x4, false);
+        device.run_frame();
+        device.run_frame();
+        device.run_frame();
 
-    // Set sound timer = Vx
-    fn op_fx18(&mut self, x: u8) {
-        self.st = self.register(x);
-    }
+        assert_eq!(device.wait_key, 0);
 
-    // Set I = I + Vx
-    fn op_fx1e(&mut self, x: u8) {
-        self.i += u16::from(self.register(x));
-    }
+        device.handle_key(0x6, true);
+        device.run_frame();
+        assert!(!device.keys[0x6]);
 
-    // Set I = location of sprite for digit Vx
-    fn op_fx29(&mut self, x: u8) {
-        self.i = u16::from(self.register(x)) * 5;
+        device.run_frame();
+        device.run_frame();
+        assert!(device.keys[0x6]);
     }
 
-    // Store BCD representation of Vx in memory locations I, I+1, and I+2
-    fn op_fx33(&mut self, x: u8) {
-        let vx = self.register(x);
+    #[test]
+    fn delays_keys_by_latency() {
+        let mut device = Device::new(Config {
+            key_latency: 3,
+            ..Config::default()
+        });
+        device.load_rom(&RomBuilder::new().wait_key(0).halt().build());
+        device.run_frame();
+
+        device.handle_key(0x9, true);
+        device.handle_key(0x9, false);
+        device.run_frame();
+        device.run_frame();
+        assert_eq!(device.wait_key, 0);
 
-        self.memory[usize::from(self.i)] = vx / 100;
-        self.memory[usize::from(self.i + 1)] = vx % 100 / 10;
-        self.memory[usize::from(self.i + 2)] = vx % 10;
+        device.run_frame();
+        device.run_frame();
+        assert_eq!(device.wait_key, 0xFF);
+        assert_eq!(device.registers[0], 0x9);
     }
 
-    // Store registers V0 through Vx in memory starting at location I
-    fn op_fx55(&mut self, x: u8) {
-        self.memory[usize::from(self.i)..=usize::from(self.i + u16::from(x))]
-            .copy_from_slice(&self.registers[0..=usize::from(x)]);
+    #[test]
+    fn releasing_keys_keeps_waiting() {
+        let mut device = Device::new(Config::default());
+        device.load_rom(&RomBuilder::new().wait_key(0).halt().build());
+        device.handle_key(0x9, true);
+        device.run_frame();
 
-        self.i += u16::from(x) + 1;
+        device.release_keys();
+        device.run_frame();
+
+        assert_eq!(device.keys, [false; 16]);
+        assert_eq!(device.wait_key, 0);
     }
 
-    // Read registers V0 through Vx from memory starting at location I
-    fn op_fx65(&mut self, x: u8) {
-        self.registers[0..=usize::from(x)].copy_from_slice(
-            &self.memory[usize::from(self.i)..=usize::from(self.i + u16::from(x))],
+    #[test]
+    fn reads_the_display_back_out_of_a_snapshot() {
+        let mut device = run(RomBuilder::new().ld_v(0, 5).ld_f(0).drw(0, 0, 5).halt());
+        device.stack.resize(3, 0);
+
+        assert_eq!(
+            device.saved_display(&device.snapshot()),
+            Some((Resolution::LORES, device.display().buffer().to_vec()))
         );
+        assert_eq!(device.saved_display(&[0; 16]), None);
+    }
+
+    #[test]
+    fn saves_states_to_the_storage_backend() {
+        let storage = Storage::memory();
+        let mut device = Device::new(Config {
+            storage: storage.clone(),
+            ..Config::default()
+        });
+        device.load_rom(&RomBuilder::new().ld_v(0, 5).halt().build());
+        device.run_frame();
+        device.save_state();
+
+        assert!(storage.modified(".state").is_some());
+
+        device.registers[0] = 0;
+        device.load_state();
 
-        self.i += u16::from(x) + 1;
+        assert_eq!(device.registers[0], 5);
     }
 
-    fn register(&self, index: u8) -> u8 {
-        self.registers[usize::from(index)]
+    #[test]
+    fn refuses_states_from_other_platforms_and_memory_sizes() {
+        let storage = Storage::memory();
+        let mut device = Device::new(Config {
+            storage: storage.clone(),
+            ..Config::default()
+        });
+        device.load_rom(&RomBuilder::new().ld_v(0, 5).halt().build());
+        device.run_frame();
+        device.save_state();
+
+        for config in [
+            Config {
+                platform: Platform::XoChip,
+                ..Config::default()
+            },
+            Config {
+                memory_size: Some(0x2000),
+                ..Config::default()
+            },
+        ] {
+            let mut other = Device::new(Config {
+                storage: storage.clone(),
+                ..config
+            });
+            other.load_rom(&RomBuilder::new().ld_v(0, 7).halt().build());
+            other.run_frame();
+            let memory = other.memory.clone();
+            other.load_state();
+
+            assert_eq!(other.registers[0], 7);
+            assert_eq!(other.memory, memory);
+        }
     }
 
-    fn set_flag<T: Into<u8>>(&mut self, value: T) {
-        self.registers[0xF] = value.into();
+    #[test]
+    fn resumes_key_wait_from_snapshot() {
+        let mut device = boot();
+        device.run_frame();
+        device.handle_key(0x3, true);
+
+        let mut restored = boot();
+        restored.restore(&device.snapshot());
+
+        assert!(restored.keys[0x3]);
+        assert_eq!(restored.wait_key, 0);
+
+        restored.handle_key(0x3, false);
+        restored.run_frame();
+
+        assert_eq!(restored.registers[0], 0x3);
+        assert_eq!(restored.registers[1], 0x3);
     }
 
-    const FONT: [u8; 80] = [
-        0b11110000,
-        0b10010000,
-        0b10010000,
-        0b10010000,
-        0b11110000,
-
-        0b00100000,
-        0b01100000,
-        0b00100000,
-        0b00100000,
-        0b01110000,
-
-        0b11110000,
-        0b00010000,
-        0b11110000,
-        0b10000000,
-        0b11110000,
-
-        0b11110000,
-        0b00010000,
-        0b11110000,
-        0b00010000,
-        0b11110000,
-
-        0b10010000,
-        0b10010000,
-        0b11110000,
-        0b00010000,
-        0b00010000,
-
-        0b11110000,
-        0b10000000,
-        0b11110000,
-        0b00010000,
-        0b11110000,
-
-        0b11110000,
-        0b10000000,
-        0b11110000,
-        0b10010000,
-        0b11110000,
-
-        0b11110000,
-        0b00010000,
-        0b00100000,
-        0b01000000,
-        0b01000000,
-
-        0b11110000,
-        0b10010000,
-        0b11110000,
-        0b10010000,
-        0b11110000,
-
-        0b11110000,
-        0b10010000,
-        0b11110000,
-        0b00010000,
-        0b11110000,
-
-        0b11110000,
-        0b10010000,
-        0b11110000,
-        0b10010000,
-        0b10010000,
-
-        0b11100000,
-        0b10010000,
-        0b11100000,
-        0b10010000,
-        0b11100000,
-
-        0b11110000,
-        0b10000000,
-        0b10000000,
-        0b10000000,
-        0b11110000,
-
-        0b11100000,
-        0b10010000,
-        0b10010000,
-        0b10010000,
-        0b11100000,
-
-        0b11110000,
-        0b10000000,
-        0b11110000,
-        0b10000000,
-        0b11110000,
-
-        0b11110000,
-        0b10000000,
-        0b11110000,
-        0b10000000,
-        0b10000000,
-    ];
+    #[test]
+    fn resumes_held_key_after_wait_completed() {
+        let mut device = boot();
+        device.run_frame();
+        device.handle_key(0x7, true);
+        device.handle_key(0x7, false);
+        device.handle_key(0x2, true);
+        device.run_frame();
+
+        let mut restored = boot();
+        restored.restore(&device.snapshot());
+
+        assert_eq!(restored.wait_key, 0xFF);
+        assert_eq!(restored.registers[1], 0x7);
+        assert!(restored.keys[0x2]);
+        assert!(!restored.keys[0x7]);
+    }
 }
@@ -0,0 +1,310 @@
+// The extension that introduced an instruction, in order of how much of
+// the instruction set an interpreter needs to support. The ones after
+// `XoChip` redefine opcodes of the others, so the disassembler leaves them
+// out and they're only listed in the reference.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Variant {
+    Chip8,
+    Schip,
+    XoChip,
+    HiresChip8,
+    Chip8X,
+    MegaChip,
+    Banking,
+}
+
+impl Variant {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Chip8 => "chip8",
+            Self::Schip => "schip",
+            Self::XoChip => "xo-chip",
+            Self::HiresChip8 => "chip8-hires",
+            Self::Chip8X => "chip8x",
+            Self::MegaChip => "megachip",
+            Self::Banking => "banking",
+        }
+    }
+}
+
+// One instruction as the disassembler decodes it and the console's `ops`
+// reference lists it
+pub struct Form {
+    // The opcode with its operands as letters, such as `8XY4`
+    pub code: &'static str,
+    pub mask: u16,
+    pub pattern: u16,
+    // The mnemonic, with `{x}`, `{y}`, `{n}`, `{kk}` and `{nnn}` standing
+    // for the operands and `{nnnn}` for the word after the opcode
+    pub syntax: &'static str,
+    pub variant: Variant,
+    pub summary: &'static str,
+    // The quirks and interpreter differences that change what it does
+    pub notes: &'static str,
+}
+
+impl Form {
+    const fn new(
+        code: &'static str,
+        syntax: &'static str,
+        variant: Variant,
+        summary: &'static str,
+        notes: &'static str,
+    ) -> Self {
+        let (mask, pattern) = encoding(code);
+
+        Self {
+            code,
+            mask,
+            pattern,
+            syntax,
+            variant,
+            summary,
+            notes,
+        }
+    }
+
+    pub fn matches(&self, opcode: u16) -> bool {
+        opcode & self.mask == self.pattern
+    }
+
+    // The entry in the reference, such as:
+    //
+    //     8XY6  SHR VX, VY        Shift VX right a bit, setting VF to the bit shifted out
+    //                             shifting: the VIP shifts VY into VX, SCHIP shifts VX
+    pub fn describe(&self) -> String {
+        let syntax = render(self.syntax, str::to_uppercase);
+        let mut text = format!(
+            "{}  {:<18}{} [{}]",
+            self.code,
+            syntax,
+            self.summary,
+            self.variant.name()
+        );
+
+        if !self.notes.is_empty() {
+            text.push_str(&format!("\n{:24}{}", "", self.notes));
+        }
+
+        text
+    }
+}
+
+// The mask and pattern of an opcode written like `8XY4`, where hex digits
+// have to match and anything else is an operand
+pub const fn encoding(code: &str) -> (u16, u16) {
+    let bytes = code.as_bytes();
+    let mut mask = 0;
+    let mut pattern = 0;
+    let mut position = 0;
+
+    while position < 4 {
+        let digit = match bytes[position] {
+            digit @ b'0'..=b'9' => Some(digit - b'0'),
+            digit @ b'A'..=b'F' => Some(digit - b'A' + 10),
+            _ => None,
+        };
+
+        mask <<= 4;
+        pattern <<= 4;
+
+        if let Some(digit) = digit {
+            mask |= 0xF;
+            pattern |= digit as u16;
+        }
+
+        position += 1;
+    }
+
+    (mask, pattern)
+}
+
+use Variant::*;
+
+// Every instruction of every platform, more specific ones ahead of the
+// general ones they overlap
+#[rustfmt::skip]
+pub const FORMS: &[Form] = &[
+    Form::new("00E0", "CLS", Chip8, "Clear the display", ""),
+    Form::new("00EE", "RET", Chip8, "Return from a subroutine", ""),
+    Form::new("00FB", "SCR", Schip, "Scroll the display right 4 pixels",
+        "half_pixel_scroll: only 2 pixels in lores"),
+    Form::new("00FC", "SCL", Schip, "Scroll the display left 4 pixels",
+        "half_pixel_scroll: only 2 pixels in lores"),
+    Form::new("00FD", "EXIT", Schip, "Exit the interpreter", "--on-exit picks halting or closing"),
+    Form::new("00FE", "LOW", Schip, "Switch to the 64x32 lores display",
+        "preserve_on_resize: keep the display instead of clearing it"),
+    Form::new("00FF", "HIGH", Schip, "Switch to the 128x64 hires display",
+        "preserve_on_resize: keep the display instead of clearing it"),
+    Form::new("00CN", "SCD {n}", Schip, "Scroll the display down N rows",
+        "half_pixel_scroll: only N/2 rows in lores"),
+    Form::new("00DN", "SCU {n}", XoChip, "Scroll the display up N rows", ""),
+    Form::new("0230", "HCLS", HiresChip8, "Clear the 64x64 display", ""),
+    Form::new("02A0", "BGC", Chip8X, "Step to the next background colour", ""),
+    Form::new("0010", "MEGAOFF", MegaChip, "Leave Mega-CHIP mode", ""),
+    Form::new("0011", "MEGAON", MegaChip, "Switch to the 256x192 colour display", ""),
+    Form::new("00BN", "SCRU {n}", MegaChip, "Scroll the display up N rows", ""),
+    Form::new("01NN", "LDHI I, {kk}", MegaChip, "Point I at NN and the word after it, 24 bits in all", ""),
+    Form::new("02NN", "LDPAL {kk}", MegaChip, "Load NN palette colours from I", ""),
+    Form::new("03NN", "SPRW {kk}", MegaChip, "Set the width of colour sprites", ""),
+    Form::new("04NN", "SPRH {kk}", MegaChip, "Set the height of colour sprites", ""),
+    Form::new("05NN", "ALPHA {kk}", MegaChip, "Set the display's opacity", ""),
+    Form::new("060N", "DIGISND {n}", MegaChip, "Play the digitised sound at I",
+        "runs silently, digitised sound isn't supported"),
+    Form::new("0700", "STOPSND", MegaChip, "Stop the digitised sound", ""),
+    Form::new("080N", "BMODE {n}", MegaChip, "Set how sprites blend with the display", ""),
+    Form::new("09NN", "CCOL {kk}", MegaChip, "Set the colour that collides", ""),
+    Form::new("0NNN", "SYS {nnn}", Chip8, "Call a machine code routine",
+        "only runs when an embedder's hook handles it, 0000 is skipped"),
+    Form::new("1NNN", "JP {nnn}", Chip8, "Jump to NNN", ""),
+    Form::new("2NNN", "CALL {nnn}", Chip8, "Call the subroutine at NNN", "--stack-overflow picks what overflowing does"),
+    Form::new("3XKK", "SE V{x}, {kk}", Chip8, "Skip the next instruction if VX is KK", ""),
+    Form::new("4XKK", "SNE V{x}, {kk}", Chip8, "Skip the next instruction unless VX is KK", ""),
+    Form::new("5XY0", "SE V{x}, V{y}", Chip8, "Skip the next instruction if VX equals VY", ""),
+    Form::new("5XY1", "ADDO V{x}, V{y}", Chip8X, "Add each octal digit of VY to VX's, dropping the carries", ""),
+    Form::new("5XY2", "SAVE V{x} - V{y}", XoChip, "Store VX to VY in memory at I, leaving I alone", ""),
+    Form::new("5XY3", "LOAD V{x} - V{y}", XoChip, "Load VX to VY from memory at I, leaving I alone", ""),
+    Form::new("6XKK", "LD V{x}, {kk}", Chip8, "Set VX to KK", ""),
+    Form::new("7XKK", "ADD V{x}, {kk}", Chip8, "Add KK to VX, without setting VF", ""),
+    Form::new("8XY0", "LD V{x}, V{y}", Chip8, "Copy VY into VX", ""),
+    Form::new("8XY1", "OR V{x}, V{y}", Chip8, "Set VX to VX OR VY", "vf_reset: the VIP clears VF, SCHIP leaves it"),
+    Form::new("8XY2", "AND V{x}, V{y}", Chip8, "Set VX to VX AND VY", "vf_reset: the VIP clears VF, SCHIP leaves it"),
+    Form::new("8XY3", "XOR V{x}, V{y}", Chip8, "Set VX to VX XOR VY", "vf_reset: the VIP clears VF, SCHIP leaves it"),
+    Form::new("8XY4", "ADD V{x}, V{y}", Chip8, "Add VY to VX, setting VF to the carry", ""),
+    Form::new("8XY5", "SUB V{x}, V{y}", Chip8, "Subtract VY from VX, setting VF to 0 on a borrow", ""),
+    Form::new("8XY6", "SHR V{x}, V{y}", Chip8, "Shift right a bit, setting VF to the bit shifted out",
+        "shifting: the VIP shifts VY into VX, SCHIP shifts VX"),
+    Form::new("8XY7", "SUBN V{x}, V{y}", Chip8, "Set VX to VY minus VX, setting VF to 0 on a borrow", ""),
+    Form::new("8XYE", "SHL V{x}, V{y}", Chip8, "Shift left a bit, setting VF to the bit shifted out",
+        "shifting: the VIP shifts VY into VX, SCHIP shifts VX"),
+    Form::new("9XY0", "SNE V{x}, V{y}", Chip8, "Skip the next instruction unless VX equals VY", ""),
+    Form::new("ANNN", "LD I, {nnn}", Chip8, "Point I at NNN", ""),
+    Form::new("BXYN", "COL V{x}, V{y}, {n}", Chip8X, "Colour zones or rows of the display with VY's colour", ""),
+    Form::new("BNNN", "JP V0, {nnn}", Chip8, "Jump to NNN plus V0", "jumping: SCHIP adds VX, X being NNN's first digit"),
+    Form::new("CXKK", "RND V{x}, {kk}", Chip8, "Set VX to a random number AND KK", ""),
+    Form::new("DXY0", "DRW V{x}, V{y}, 0", Schip, "Draw the 16x16 sprite at I at VX, VY",
+        "row_collisions: VF counts the rows that collided or were clipped"),
+    Form::new("DXYN", "DRW V{x}, V{y}, {n}", Chip8, "Draw the N row sprite at I at VX, VY, VF set on a collision",
+        "clipping: sprites stop at the edges rather than wrapping; display_wait: one sprite a frame; \
+         row_collisions: VF counts rows in hires"),
+    Form::new("EX9E", "SKP V{x}", Chip8, "Skip the next instruction if key VX is down", "debounce: presses register once settled"),
+    Form::new("EXA1", "SKNP V{x}", Chip8, "Skip the next instruction unless key VX is down", "debounce: presses register once settled"),
+    Form::new("EXF2", "SKP2 V{x}", Chip8X, "Skip the next instruction if key VX on the second keypad is down", ""),
+    Form::new("EXF5", "SKNP2 V{x}", Chip8X, "Skip the next instruction unless key VX on the second keypad is down", ""),
+    Form::new("F000", "LD I, {nnnn}", XoChip, "Point I at the word after the opcode", ""),
+    Form::new("F002", "AUDIO", XoChip, "Load the 16 byte audio pattern at I", ""),
+    Form::new("FX01", "PLANE {x}", XoChip, "Select the bit planes drawn to", ""),
+    Form::new("FX07", "LD V{x}, DT", Chip8, "Copy the delay timer into VX", ""),
+    Form::new("FX0A", "LD V{x}, K", Chip8, "Wait for a key press and put it in VX", "the VIP waits for the key to be released"),
+    Form::new("FX15", "LD DT, V{x}", Chip8, "Set the delay timer to VX", ""),
+    Form::new("FX18", "LD ST, V{x}", Chip8, "Set the sound timer to VX", ""),
+    Form::new("FX1E", "ADD I, V{x}", Chip8, "Add VX to I", ""),
+    Form::new("FX29", "LD F, V{x}", Chip8, "Point I at the small font digit in VX", ""),
+    Form::new("FX30", "LD HF, V{x}", Schip, "Point I at the large font digit in VX", ""),
+    Form::new("FX33", "LD B, V{x}", Chip8, "Store VX as three decimal digits at I", ""),
+    Form::new("FX3A", "PITCH V{x}", XoChip, "Set the audio pattern's pitch to VX", ""),
+    Form::new("FX55", "LD [I], V{x}", Chip8, "Store V0 to VX in memory at I",
+        "memory_increment: the VIP leaves I past the last register, SCHIP 1.0 on it (memory_increment_by_x), SCHIP 1.1 leaves it alone"),
+    Form::new("FX65", "LD V{x}, [I]", Chip8, "Load V0 to VX from memory at I",
+        "memory_increment: the VIP leaves I past the last register, SCHIP 1.0 on it (memory_increment_by_x), SCHIP 1.1 leaves it alone"),
+    Form::new("FX75", "LD R, V{x}", Schip, "Save V0 to VX in the RPL flags", ""),
+    Form::new("FX85", "LD V{x}, R", Schip, "Load V0 to VX from the RPL flags", ""),
+    Form::new("FXB0", "BANK V{x}", Banking, "Switch memory bank VX into the window at 0x800", ""),
+    Form::new("FXF8", "OUT V{x}", Chip8X, "Send VX to the output port, the sound board's tone", ""),
+];
+
+// The form written as `code`
+pub fn form(code: &str) -> Option<&'static Form> {
+    FORMS.iter().find(|form| form.code == code)
+}
+
+// `syntax` with each `{operand}` replaced
+fn render(syntax: &str, operand: impl Fn(&str) -> String) -> String {
+    let mut text = String::new();
+    let mut rest = syntax;
+
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}').unwrap();
+
+        text.push_str(&rest[..start]);
+        text.push_str(&operand(&rest[start + 1..end]));
+        rest = &rest[end + 1..];
+    }
+
+    text.push_str(rest);
+    text
+}
+
+pub struct Instruction {
+    pub opcode: u16,
+    pub mnemonic: String,
+    pub variant: Variant,
+    // In bytes, only `F000 NNNN` is longer than 2
+    pub size: u16,
+}
+
+// Decode the instruction at the start of `bytes` into Cowgod-style assembly,
+// returning `None` if it isn't a known instruction
+pub fn disassemble(bytes: &[u8]) -> Option<Instruction> {
+    let opcode = u16::from_be_bytes([*bytes.first()?, *bytes.get(1)?]);
+    let form = FORMS
+        .iter()
+        .filter(|form| form.variant <= XoChip)
+        .find(|form| form.matches(opcode))?;
+
+    let long = form.syntax.contains("{nnnn}");
+    let next = if long {
+        u16::from_be_bytes([*bytes.get(2)?, *bytes.get(3)?])
+    } else {
+        0
+    };
+
+    let mnemonic = render(form.syntax, |operand| match operand {
+        "x" => format!("{:X}", (opcode >> 8) & 0xF),
+        "y" => format!("{:X}", (opcode >> 4) & 0xF),
+        "n" => format!("{}", opcode & 0xF),
+        "kk" => format!("0x{:02X}", opcode & 0xFF),
+        "nnn" => format!("0x{:03X}", opcode & 0x0FFF),
+        "nnnn" => format!("0x{:04X}", next),
+        _ => unreachable!("unknown operand {}", operand),
+    });
+
+    Some(Instruction {
+        opcode,
+        mnemonic,
+        variant: form.variant,
+        size: if long { 4 } else { 2 },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mnemonic(bytes: &[u8]) -> Option<String> {
+        disassemble(bytes).map(|instruction| instruction.mnemonic)
+    }
+
+    #[test]
+    fn decodes_base_instructions() {
+        assert_eq!(mnemonic(&[0x12, 0x34]).unwrap(), "JP 0x234");
+        assert_eq!(mnemonic(&[0x8A, 0xBE]).unwrap(), "SHL VA, VB");
+        assert_eq!(mnemonic(&[0xF3, 0x65]).unwrap(), "LD V3, [I]");
+    }
+
+    #[test]
+    fn decodes_long_instructions() {
+        let instruction = disassemble(&[0xF0, 0x00, 0x12, 0x34]).unwrap();
+
+        assert_eq!(instruction.mnemonic, "LD I, 0x1234");
+        assert_eq!(instruction.size, 4);
+        assert_eq!(instruction.variant, Variant::XoChip);
+    }
+
+    #[test]
+    fn rejects_unknown_instructions() {
+        assert!(mnemonic(&[0x80, 0x08]).is_none());
+        assert!(mnemonic(&[0xE0, 0x00]).is_none());
+        assert!(mnemonic(&[0xF0]).is_none());
+    }
+}
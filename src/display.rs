@@ -0,0 +1,785 @@
+use crate::chip8x::Zones;
+use crate::framebuffer::{Hires, Lores, Mega, TwoPage};
+use crate::megachip::Colors;
+use crate::palette::Palette;
+use crate::png;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Resolution {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Resolution {
+    pub const LORES: Self = Self {
+        width: 64,
+        height: 32,
+    };
+
+    pub const HIRES: Self = Self {
+        width: 128,
+        height: 64,
+    };
+
+    // The VIP's hi-res CHIP-8 variant, which shows two pages of memory
+    pub const TWO_PAGE: Self = Self {
+        width: 64,
+        height: 64,
+    };
+
+    // Mega-CHIP's true colour mode
+    pub const MEGA: Self = Self {
+        width: 256,
+        height: 192,
+    };
+
+    fn len(&self) -> usize {
+        self.width * self.height
+    }
+}
+
+// The framebuffer for each supported resolution
+#[derive(Clone)]
+enum Buffer {
+    Lores(Box<Lores>),
+    Hires(Box<Hires>),
+    TwoPage(Box<TwoPage>),
+    Mega(Box<Mega>),
+}
+
+impl Buffer {
+    fn new(resolution: Resolution, pixels: &[u8]) -> Self {
+        match resolution {
+            Resolution::LORES => Self::Lores(Box::new(Lores::from_slice(pixels))),
+            Resolution::HIRES => Self::Hires(Box::new(Hires::from_slice(pixels))),
+            Resolution::TWO_PAGE => Self::TwoPage(Box::new(TwoPage::from_slice(pixels))),
+            Resolution::MEGA => Self::Mega(Box::new(Mega::from_slice(pixels))),
+            _ => panic!(
+                "Unsupported resolution {}x{}",
+                resolution.width, resolution.height
+            ),
+        }
+    }
+}
+
+// Forward a call to whichever framebuffer is active
+macro_rules! with_buffer {
+    ($buffer:expr, $framebuffer:ident => $body:expr) => {
+        match $buffer {
+            Buffer::Lores($framebuffer) => $body,
+            Buffer::Hires($framebuffer) => $body,
+            Buffer::TwoPage($framebuffer) => $body,
+            Buffer::Mega($framebuffer) => $body,
+        }
+    };
+}
+
+// What drawing a sprite ran into
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Collision {
+    // Rows that turned off a lit pixel
+    pub rows: u8,
+    // Rows cut off by the bottom of the display
+    pub clipped: u8,
+}
+
+impl Collision {
+    pub fn any(&self) -> bool {
+        self.rows > 0
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            rows: self.rows.max(other.rows),
+            clipped: self.clipped.max(other.clipped),
+        }
+    }
+}
+
+// A frame as RGBA bytes, row by row, for embedders that draw, save or
+// stream the display themselves
+pub struct RgbaImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+// The logical framebuffer of the device. Each pixel is a bitmask of the
+// planes that are lit at that position, so renderers only need to map
+// those values to colours and scale them to whatever surface they have.
+#[derive(Clone)]
+pub struct Display {
+    resolution: Resolution,
+    buffer: Buffer,
+    toggled: u64,
+    dirty: u8,
+    // Planes that drawing, clearing and scrolling apply to, only ever more
+    // than the first with XO-CHIP's `FN01`
+    planes: u8,
+    // Set in Mega-CHIP mode, where each pixel is a palette index rather
+    // than a bitmask of planes
+    colors: Option<Box<Colors>>,
+    // Set on CHIP-8X, which colours pixels by the zone they're in
+    zones: Option<Box<Zones>>,
+    // Sprites drawn across an edge carry on from the opposite edge instead
+    // of being clipped
+    wrapping: bool,
+}
+
+// Bitmask with every drawable plane set
+const ALL_PLANES: u8 = 0b11;
+
+impl Display {
+    pub fn new(resolution: Resolution) -> Self {
+        Self {
+            resolution,
+            buffer: Buffer::new(resolution, &vec![0; resolution.len()]),
+            toggled: 0,
+            dirty: ALL_PLANES,
+            planes: 1,
+            colors: None,
+            zones: None,
+            wrapping: false,
+        }
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    pub fn buffer(&self) -> &[u8] {
+        with_buffer!(&self.buffer, framebuffer => framebuffer.as_slice())
+    }
+
+    fn buffer_mut(&mut self) -> &mut [u8] {
+        with_buffer!(&mut self.buffer, framebuffer => framebuffer.as_mut_slice())
+    }
+
+    // Running count of pixels flipped by sprite drawing
+    pub fn toggled(&self) -> u64 {
+        self.toggled
+    }
+
+    // Planes that changed since the last `mark_clean`, so renderers can
+    // skip work when nothing was drawn
+    pub fn dirty(&self) -> u8 {
+        self.dirty
+    }
+
+    pub fn mark_clean(&mut self) {
+        self.dirty = 0;
+    }
+
+    pub fn planes(&self) -> u8 {
+        self.planes
+    }
+
+    pub fn select_planes(&mut self, planes: u8) {
+        self.planes = planes & ALL_PLANES;
+    }
+
+    // Replace the whole framebuffer, used when restoring a save state.
+    // Mega-CHIP colours aren't saved, so they come back once the ROM next
+    // draws a frame.
+    pub fn restore(&mut self, resolution: Resolution, buffer: &[u8]) {
+        self.resolution = resolution;
+        self.buffer = Buffer::new(resolution, buffer);
+        self.colors =
+            (resolution == Resolution::MEGA).then(|| Box::new(Colors::new(resolution.len())));
+        self.dirty = ALL_PLANES;
+    }
+
+    // Enter or leave Mega-CHIP mode, with `0011` and `0010`
+    pub fn set_mega(&mut self, on: bool) {
+        if on {
+            self.set_resolution(Resolution::MEGA, false);
+            self.colors = Some(Box::new(Colors::new(Resolution::MEGA.len())));
+        } else {
+            self.set_resolution(Resolution::LORES, false);
+        }
+    }
+
+    // Wrap sprites around the edges rather than clipping them
+    pub fn set_wrapping(&mut self, wrapping: bool) {
+        self.wrapping = wrapping;
+    }
+
+    // Colour the display with CHIP-8X's zones from now on
+    pub fn enable_zones(&mut self) {
+        self.zones = Some(Box::default());
+        self.dirty = ALL_PLANES;
+    }
+
+    pub fn zones_mut(&mut self) -> Option<&mut Zones> {
+        self.dirty = ALL_PLANES;
+        self.zones.as_deref_mut()
+    }
+
+    // Every pixel's colour, row by row, on the machines that pick colours
+    // themselves rather than leaving them to the palette
+    pub fn true_colors(&self) -> Option<Vec<[u8; 3]>> {
+        if let Some(colors) = &self.colors {
+            return Some(colors.shown().collect());
+        }
+
+        let zones = self.zones.as_ref()?;
+        let width = self.resolution.width;
+
+        Some(
+            self.buffer()
+                .iter()
+                .enumerate()
+                .map(|(index, &pixel)| {
+                    if pixel != 0 {
+                        zones.foreground(index % width, index / width)
+                    } else {
+                        zones.background()
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    pub fn colors(&self) -> Option<&Colors> {
+        self.colors.as_deref()
+    }
+
+    pub fn colors_mut(&mut self) -> Option<&mut Colors> {
+        self.colors.as_deref_mut()
+    }
+
+    // Render as text, one line per row with `#` for lit pixels
+    pub fn ascii(&self) -> String {
+        with_buffer!(&self.buffer, framebuffer => framebuffer.ascii())
+    }
+
+    // Encode as a binary PBM image, lit pixels are black
+    pub fn pbm(&self) -> Vec<u8> {
+        with_buffer!(&self.buffer, framebuffer => framebuffer.pbm())
+    }
+
+    // Encode as a greyscale PNG, lit pixels are white
+    pub fn png(&self) -> Vec<u8> {
+        with_buffer!(&self.buffer, framebuffer => framebuffer.png())
+    }
+
+    // Encode as a PNG in the given colours at `scale` times the resolution
+    pub fn capture(&self, palette: &Palette, scale: usize) -> Vec<u8> {
+        let Some(colors) = self.true_colors() else {
+            return with_buffer!(&self.buffer, framebuffer => framebuffer.capture(palette, scale));
+        };
+
+        let scale = scale.max(1);
+        let Resolution { width, height } = self.resolution;
+        let rows = colors.chunks_exact(width).flat_map(|row| {
+            let row: Vec<u8> = row
+                .iter()
+                .flat_map(|rgb| std::iter::repeat_n(*rgb, scale))
+                .flatten()
+                .collect();
+
+            std::iter::repeat_n(row, scale)
+        });
+
+        let mut image = png::SIGNATURE.to_vec();
+        png::chunk(
+            &mut image,
+            b"IHDR",
+            &png::header((width * scale) as u32, (height * scale) as u32, 8, png::RGB),
+        );
+        png::chunk(
+            &mut image,
+            b"IDAT",
+            &png::zlib_stored(&png::scanlines(rows, 8)),
+        );
+        png::chunk(&mut image, b"IEND", &[]);
+
+        image
+    }
+
+    // The display in the given colours at `scale` times the resolution
+    pub fn rgba(&self, palette: &Palette, scale: usize) -> RgbaImage {
+        let scale = scale.max(1);
+
+        RgbaImage {
+            width: self.resolution.width * scale,
+            height: self.resolution.height * scale,
+            pixels: match self.true_colors() {
+                Some(colors) => colors
+                    .chunks_exact(self.resolution.width)
+                    .flat_map(|row| {
+                        let row: Vec<u8> = row
+                            .iter()
+                            .flat_map(|&[r, g, b]| std::iter::repeat_n([r, g, b, 0xFF], scale))
+                            .flatten()
+                            .collect();
+
+                        std::iter::repeat_n(row, scale).flatten()
+                    })
+                    .collect(),
+                None => with_buffer!(&self.buffer, framebuffer => framebuffer.rgba(palette, scale)),
+            },
+        }
+    }
+
+    // Switch resolution, either clearing the display or scaling the
+    // existing contents to the new size
+    pub fn set_resolution(&mut self, resolution: Resolution, preserve: bool) {
+        if resolution == self.resolution {
+            return;
+        }
+
+        let pixels = if preserve {
+            rescale(
+                self.buffer(),
+                self.resolution.width,
+                resolution.width,
+                resolution.height,
+            )
+        } else {
+            vec![0; resolution.len()]
+        };
+
+        self.buffer = Buffer::new(resolution, &pixels);
+        self.resolution = resolution;
+        self.colors = None;
+        self.dirty = ALL_PLANES;
+    }
+
+    // In Mega-CHIP mode this also shows the frame drawn since the last
+    // clear, so ROMs clear the screen once they've finished drawing
+    pub fn clear(&mut self) {
+        if let Some(colors) = &mut self.colors {
+            colors.present();
+            self.buffer_mut().fill(0);
+            self.dirty = ALL_PLANES;
+            return;
+        }
+
+        let planes = self.planes;
+
+        for pixel in self.buffer_mut() {
+            *pixel &= !planes;
+        }
+
+        self.dirty |= planes;
+    }
+
+    // Draw a sprite one byte wide, returning which rows turned off a lit
+    // pixel or were clipped. With both planes selected the sprite holds the
+    // rows for the first plane followed by the rows for the second.
+    pub fn draw(&mut self, x: u8, y: u8, sprite: &[u8]) -> Collision {
+        let rows: Vec<u16> = sprite.iter().map(|&line| u16::from(line) << 8).collect();
+
+        self.draw_planes(x, y, &rows, 8)
+    }
+
+    // Draw a 16 pixel wide SCHIP sprite, two bytes per row
+    pub fn draw_wide(&mut self, x: u8, y: u8, sprite: &[u8]) -> Collision {
+        let rows: Vec<u16> = sprite
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+
+        self.draw_planes(x, y, &rows, 16)
+    }
+
+    // Split the rows evenly between the selected planes
+    fn draw_planes(&mut self, x: u8, y: u8, rows: &[u16], columns: usize) -> Collision {
+        let count = self.planes.count_ones() as usize;
+
+        if count == 0 {
+            return Collision::default();
+        }
+
+        let height = rows.len() / count;
+        let mut collision = Collision::default();
+        let mut start = 0;
+
+        for plane in [1, 2] {
+            if self.planes & plane != 0 {
+                collision = collision.merge(self.draw_rows(
+                    plane,
+                    x,
+                    y,
+                    &rows[start..start + height],
+                    columns,
+                ));
+                start += height;
+            }
+        }
+
+        collision
+    }
+
+    // Rows are left aligned in a u16, with `columns` pixels in each
+    fn draw_rows(&mut self, plane: u8, x: u8, y: u8, rows: &[u16], columns: usize) -> Collision {
+        let mut collision = Collision::default();
+
+        let Resolution { width, height } = self.resolution;
+
+        let wrapped_x = usize::from(x) % width;
+        let wrapped_y = usize::from(y) % height;
+
+        for (y_row, line) in rows.iter().enumerate() {
+            let mut y_pos = wrapped_y + y_row;
+
+            // clip sprites
+            if y_pos >= height {
+                if !self.wrapping {
+                    collision.clipped = (rows.len() - y_row) as u8;
+                    break;
+                }
+
+                y_pos %= height;
+            }
+
+            let mut hit = false;
+
+            for x_column in 0..columns {
+                let pixel = line & (0x8000 >> x_column);
+                let mut x_pos = wrapped_x + x_column;
+
+                // clip sprites
+                if x_pos >= width {
+                    if !self.wrapping {
+                        break;
+                    }
+
+                    x_pos %= width;
+                }
+
+                if pixel != 0 {
+                    let index = x_pos + (y_pos * width);
+                    let buffer = self.buffer_mut();
+
+                    if buffer[index] & plane != 0 {
+                        hit = true
+                    }
+
+                    buffer[index] ^= plane;
+                    self.toggled += 1;
+                    self.dirty |= plane;
+                }
+            }
+
+            collision.rows += u8::from(hit);
+        }
+
+        collision
+    }
+
+    // Draw a Mega-CHIP sprite of palette indices, one byte per pixel,
+    // returning whether it covered any pixel of the collision colour.
+    // Index 0 is transparent.
+    pub fn draw_colors(
+        &mut self,
+        x: u8,
+        y: u8,
+        width: usize,
+        height: usize,
+        sprite: &[u8],
+    ) -> bool {
+        let Resolution {
+            width: screen_width,
+            height: screen_height,
+        } = self.resolution;
+        let Some(colors) = &mut self.colors else {
+            return false;
+        };
+        let buffer = with_buffer!(&mut self.buffer, framebuffer => framebuffer.as_mut_slice());
+
+        let mut collision = false;
+
+        for (y_row, line) in sprite.chunks_exact(width).take(height).enumerate() {
+            let y_pos = usize::from(y) + y_row;
+
+            // clip sprites
+            if y_pos >= screen_height {
+                break;
+            }
+
+            for (x_column, &index) in line.iter().enumerate() {
+                let x_pos = usize::from(x) + x_column;
+
+                // clip sprites
+                if x_pos >= screen_width {
+                    break;
+                }
+
+                if index != 0 {
+                    let pixel = x_pos + y_pos * screen_width;
+
+                    collision |= colors.collides_with(buffer[pixel]);
+                    buffer[pixel] = index;
+                    colors.paint(pixel, index);
+                    self.toggled += 1;
+                }
+            }
+        }
+
+        self.dirty = ALL_PLANES;
+
+        collision
+    }
+
+    pub fn scroll_down(&mut self, rows: u8) {
+        self.scroll(Direction::Down, usize::from(rows));
+    }
+
+    pub fn scroll_up(&mut self, rows: u8) {
+        self.scroll(Direction::Up, usize::from(rows));
+    }
+
+    pub fn scroll_right(&mut self, columns: u8) {
+        self.scroll(Direction::Right, usize::from(columns));
+    }
+
+    pub fn scroll_left(&mut self, columns: u8) {
+        self.scroll(Direction::Left, usize::from(columns));
+    }
+
+    // Move the selected planes, leaving the others where they are. In
+    // Mega-CHIP mode the whole screen moves, along with the frame being
+    // drawn.
+    fn scroll(&mut self, direction: Direction, amount: usize) {
+        let width = self.resolution.width;
+
+        if let Some(colors) = &mut self.colors {
+            direction.shift(colors.drawing_mut(), width, amount);
+            direction.shift(self.buffer_mut(), width, amount);
+            self.dirty = ALL_PLANES;
+            return;
+        }
+
+        let planes = self.planes;
+        let buffer = self.buffer_mut();
+
+        let mut layer: Vec<u8> = buffer.iter().map(|&pixel| pixel & planes).collect();
+        direction.shift(&mut layer, width, amount);
+
+        for (pixel, moved) in buffer.iter_mut().zip(layer) {
+            *pixel = (*pixel & !planes) | moved;
+        }
+
+        self.dirty |= planes;
+    }
+}
+
+// Nearest-neighbour scale a buffer to a new size, which doubles each pixel
+// when going up to hires and samples every other pixel when going down
+fn rescale(buffer: &[u8], width: usize, new_width: usize, new_height: usize) -> Vec<u8> {
+    let height = buffer.len() / width;
+    let mut scaled = vec![0; new_width * new_height];
+
+    for (index, pixel) in scaled.iter_mut().enumerate() {
+        let x = (index % new_width) * width / new_width;
+        let y = (index / new_width) * height / new_height;
+
+        *pixel = buffer[x + y * width];
+    }
+
+    scaled
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Down,
+    Up,
+    Right,
+    Left,
+}
+
+impl Direction {
+    fn shift<T: Copy + Default>(self, buffer: &mut [T], width: usize, amount: usize) {
+        match self {
+            Self::Down => scroll_down(buffer, width, amount),
+            Self::Up => scroll_up(buffer, width, amount),
+            Self::Right => scroll_right(buffer, width, amount),
+            Self::Left => scroll_left(buffer, width, amount),
+        }
+    }
+}
+
+// Shift whole rows towards the bottom, filling the top with blank rows
+fn scroll_down<T: Copy + Default>(buffer: &mut [T], width: usize, rows: usize) {
+    let offset = (rows * width).min(buffer.len());
+    let end = buffer.len() - offset;
+
+    buffer.copy_within(..end, offset);
+    buffer[..offset].fill(T::default());
+}
+
+// Shift whole rows towards the top, filling the bottom with blank rows
+fn scroll_up<T: Copy + Default>(buffer: &mut [T], width: usize, rows: usize) {
+    let offset = (rows * width).min(buffer.len());
+    let end = buffer.len() - offset;
+
+    buffer.copy_within(offset.., 0);
+    buffer[end..].fill(T::default());
+}
+
+// Shift each row towards the right, filling the left edge with blank pixels
+fn scroll_right<T: Copy + Default>(buffer: &mut [T], width: usize, columns: usize) {
+    let columns = columns.min(width);
+
+    for row in buffer.chunks_exact_mut(width) {
+        row.copy_within(..width - columns, columns);
+        row[..columns].fill(T::default());
+    }
+}
+
+// Shift each row towards the left, filling the right edge with blank pixels
+fn scroll_left<T: Copy + Default>(buffer: &mut [T], width: usize, columns: usize) {
+    let columns = columns.min(width);
+
+    for row in buffer.chunks_exact_mut(width) {
+        row.copy_within(columns.., 0);
+        row[width - columns..].fill(T::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: usize = 4;
+
+    fn pattern(rows: &[&str]) -> Vec<u8> {
+        rows.iter()
+            .flat_map(|row| row.chars().map(|c| u8::from(c == '#')))
+            .collect()
+    }
+
+    fn source() -> Vec<u8> {
+        pattern(&["#...", ".#..", "..#.", "...#"])
+    }
+
+    #[test]
+    fn scroll_down_shifts_rows_and_blanks_top() {
+        let mut buffer = source();
+        scroll_down(&mut buffer, W, 1);
+
+        assert_eq!(buffer, pattern(&["....", "#...", ".#..", "..#."]));
+    }
+
+    #[test]
+    fn scroll_up_shifts_rows_and_blanks_bottom() {
+        let mut buffer = source();
+        scroll_up(&mut buffer, W, 2);
+
+        assert_eq!(buffer, pattern(&["..#.", "...#", "....", "...."]));
+    }
+
+    #[test]
+    fn scroll_right_shifts_columns_and_blanks_left() {
+        let mut buffer = source();
+        scroll_right(&mut buffer, W, 1);
+
+        assert_eq!(buffer, pattern(&[".#..", "..#.", "...#", "...."]));
+    }
+
+    #[test]
+    fn scroll_left_shifts_columns_and_blanks_right() {
+        let mut buffer = source();
+        scroll_left(&mut buffer, W, 2);
+
+        assert_eq!(buffer, pattern(&["....", "....", "#...", ".#.."]));
+    }
+
+    #[test]
+    fn scroll_past_edge_clears_buffer() {
+        let blank = pattern(&["....", "....", "....", "...."]);
+
+        for scroll in [scroll_down, scroll_up, scroll_right, scroll_left] {
+            let mut buffer = source();
+            scroll(&mut buffer, W, 9);
+
+            assert_eq!(buffer, blank);
+        }
+    }
+
+    #[test]
+    fn rescale_doubles_pixels_going_up() {
+        let buffer = pattern(&["#.", ".#"]);
+
+        assert_eq!(
+            rescale(&buffer, 2, W, W),
+            pattern(&["##..", "##..", "..##", "..##"])
+        );
+    }
+
+    #[test]
+    fn rescale_samples_pixels_going_down() {
+        let buffer = pattern(&["#..#", "....", "..#.", "...."]);
+
+        assert_eq!(rescale(&buffer, W, 2, 2), pattern(&["#.", ".#"]));
+    }
+
+    #[test]
+    fn draws_wide_sprites_with_collisions() {
+        let mut display = Display::new(Resolution::HIRES);
+
+        assert!(!display.draw_wide(0, 0, &[0x80, 0x01, 0xFF, 0xFF]).any());
+        assert_eq!(
+            display.buffer()[..16],
+            [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]
+        );
+        assert_eq!(display.buffer()[128..144], [1; 16]);
+
+        assert!(display.draw_wide(15, 0, &[0x80, 0x00]).any());
+        assert_eq!(display.buffer()[15], 0);
+    }
+
+    #[test]
+    fn counts_colliding_and_clipped_rows() {
+        let mut display = Display::new(Resolution::HIRES);
+        display.draw(0, 60, &[0x80, 0x00, 0x80]);
+
+        assert_eq!(
+            display.draw(0, 60, &[0x80, 0x80, 0x80, 0x00, 0x80, 0x80]),
+            Collision {
+                rows: 2,
+                clipped: 2
+            }
+        );
+    }
+
+    #[test]
+    fn wraps_sprites_across_the_edges() {
+        let mut display = Display::new(Resolution::LORES);
+        display.set_wrapping(true);
+
+        assert_eq!(display.draw(63, 31, &[0xC0, 0xC0]), Collision::default());
+        assert_eq!(
+            [0, 63, 31 * 64, 31 * 64 + 63].map(|index| display.buffer()[index]),
+            [1; 4]
+        );
+    }
+
+    #[test]
+    fn draws_clears_and_scrolls_selected_planes() {
+        let mut display = Display::new(Resolution::LORES);
+
+        display.select_planes(0b11);
+        assert!(!display.draw(0, 0, &[0xC0, 0x80]).any());
+        assert_eq!(display.buffer()[..2], [0b11, 0b01]);
+
+        display.select_planes(0b10);
+        display.scroll_right(1);
+        assert_eq!(display.buffer()[..3], [0b01, 0b11, 0b00]);
+
+        display.clear();
+        assert_eq!(display.buffer()[..3], [0b01, 0b01, 0b00]);
+    }
+
+    #[test]
+    fn scroll_by_zero_is_noop() {
+        for scroll in [scroll_down, scroll_up, scroll_right, scroll_left] {
+            let mut buffer = source();
+            scroll(&mut buffer, W, 0);
+
+            assert_eq!(buffer, source());
+        }
+    }
+}
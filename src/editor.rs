@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+
+// A change to emulated memory requested from the console
+pub enum Edit {
+    // Print `len` bytes from an address
+    Show(u16, usize),
+    // Write bytes starting at an address
+    Write(u16, Vec<u8>),
+    // Revert the last write
+    Undo,
+}
+
+impl Edit {
+    // Bytes to write, either hex pairs (`A2 2A`, `a22a`) or quoted ASCII
+    pub fn parse_bytes(text: &str) -> Option<Vec<u8>> {
+        if let Some(ascii) = text
+            .strip_prefix('"')
+            .and_then(|text| text.strip_suffix('"'))
+        {
+            return ascii.is_ascii().then(|| ascii.as_bytes().to_vec());
+        }
+
+        let hex: String = text.split_whitespace().collect();
+
+        if hex.is_empty() || !hex.len().is_multiple_of(2) {
+            return None;
+        }
+
+        (0..hex.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+            .collect()
+    }
+}
+
+// Hex editing of the emulated memory, remembering what each write replaced
+// so it can be undone and edited bytes can be told apart when shown
+#[derive(Default)]
+pub struct Editor {
+    history: Vec<(u16, Vec<u8>)>,
+    // Original value of every byte that differs from it after editing
+    original: BTreeMap<u16, u8>,
+}
+
+impl Editor {
+    // Write `bytes` at `address`, stopping at the end of memory
+    pub fn write(&mut self, memory: &mut [u8], address: u16, bytes: &[u8]) {
+        let start = usize::from(address).min(memory.len());
+        let end = (start + bytes.len()).min(memory.len());
+
+        self.history.push((address, memory[start..end].to_vec()));
+
+        for (offset, &byte) in bytes[..end - start].iter().enumerate() {
+            self.set(memory, address + offset as u16, byte);
+        }
+    }
+
+    // Revert the last write, returning the address and length it covered
+    pub fn undo(&mut self, memory: &mut [u8]) -> Option<(u16, usize)> {
+        let (address, bytes) = self.history.pop()?;
+
+        for (offset, &byte) in bytes.iter().enumerate() {
+            self.set(memory, address + offset as u16, byte);
+        }
+
+        Some((address, bytes.len()))
+    }
+
+    fn set(&mut self, memory: &mut [u8], address: u16, byte: u8) {
+        let current = &mut memory[usize::from(address)];
+        let original = *self.original.entry(address).or_insert(*current);
+
+        *current = byte;
+
+        if byte == original {
+            self.original.remove(&address);
+        }
+    }
+
+    pub fn is_edited(&self, address: u16) -> bool {
+        self.original.contains_key(&address)
+    }
+
+    // Rows of 16 bytes as hex then ASCII, with a `*` after edited bytes
+    pub fn show(&self, memory: &[u8], address: u16, len: usize) -> String {
+        let start = usize::from(address).min(memory.len());
+        let end = (start + len).min(memory.len());
+        let mut text = String::new();
+
+        for row in (start..end).step_by(16) {
+            let bytes = &memory[row..(row + 16).min(end)];
+
+            text.push_str(&format!("{:03X} ", row));
+
+            for (offset, byte) in bytes.iter().enumerate() {
+                let mark = if self.is_edited((row + offset) as u16) {
+                    '*'
+                } else {
+                    ' '
+                };
+
+                text.push_str(&format!(" {:02X}{}", byte, mark));
+            }
+
+            let ascii: String = bytes
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        char::from(byte)
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+
+            text.push_str(&format!(
+                "{:width$} |{}|\n",
+                "",
+                ascii,
+                width = (16 - bytes.len()) * 4
+            ));
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_and_ascii() {
+        assert_eq!(Edit::parse_bytes("A2 2a"), Some(vec![0xA2, 0x2A]));
+        assert_eq!(Edit::parse_bytes("\"Hi\""), Some(b"Hi".to_vec()));
+        assert_eq!(Edit::parse_bytes("A2 2"), None);
+        assert_eq!(Edit::parse_bytes("ZZ"), None);
+    }
+
+    #[test]
+    fn undoes_writes_and_marks_edits() {
+        let mut memory = [0; 4096];
+        let mut editor = Editor::default();
+
+        editor.write(&mut memory, 0x200, &[1, 2]);
+        editor.write(&mut memory, 0x201, &[3]);
+
+        assert_eq!(memory[0x200..0x202], [1, 3]);
+        assert!(editor.is_edited(0x201));
+
+        assert_eq!(editor.undo(&mut memory), Some((0x201, 1)));
+        assert_eq!(memory[0x201], 2);
+
+        assert_eq!(editor.undo(&mut memory), Some((0x200, 2)));
+        assert_eq!(memory[0x200..0x202], [0, 0]);
+        assert!(!editor.is_edited(0x200));
+        assert_eq!(editor.undo(&mut memory), None);
+    }
+}
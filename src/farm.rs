@@ -0,0 +1,170 @@
+use crate::device::{Config, Device, ExitAction};
+use crate::hash::crc32;
+
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+// How one ROM ended up after running for the requested number of frames
+#[derive(PartialEq, Eq)]
+pub struct Entry {
+    pub rom: String,
+    // `running`, `halted`, or `fault` followed by the panic message
+    pub outcome: String,
+    // CRC-32 of the final display
+    pub frame_hash: u32,
+    pub instructions: u64,
+}
+
+impl Entry {
+    // One tab separated line of a report
+    pub fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{:08x}\t{}",
+            self.rom, self.outcome, self.frame_hash, self.instructions
+        )
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{} {:08x} {}",
+            self.outcome, self.frame_hash, self.instructions
+        )
+    }
+
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+
+        Some(Self {
+            rom: fields.next()?.to_owned(),
+            outcome: fields.next()?.to_owned(),
+            frame_hash: u32::from_str_radix(fields.next()?, 16).ok()?,
+            instructions: fields.next()?.parse().ok()?,
+        })
+    }
+}
+
+// Run every `.ch8` file in `dir` headlessly, in name order, catching faults
+// so one broken ROM doesn't stop the rest of the library
+pub fn farm(dir: &Path, config: &Config, frames: u32) -> Vec<Entry> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("Can't read '{}': {}", dir.display(), err))
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("ch8"))
+        })
+        .collect();
+
+    paths.sort();
+
+    // faults are reported in the results, not as panic messages
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let entries = paths.iter().map(|path| run(path, config, frames)).collect();
+
+    panic::set_hook(hook);
+
+    entries
+}
+
+fn run(path: &Path, config: &Config, frames: u32) -> Entry {
+    let mut device = Device::new(Config {
+        // a ROM exiting mustn't take the whole farm down with it
+        exit_action: ExitAction::Halt,
+        ..config.clone()
+    });
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        device.load(path);
+
+        for _ in 0..frames {
+            device.run_frame();
+        }
+    }));
+
+    let outcome = match result {
+        Ok(()) if device.is_halted() => "halted".to_owned(),
+        Ok(()) => "running".to_owned(),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<String>()
+                .map(String::as_str)
+                .or_else(|| payload.downcast_ref::<&str>().copied())
+                .unwrap_or("panic");
+
+            format!("fault {}", message.replace(['\t', '\n'], " "))
+        }
+    };
+
+    Entry {
+        rom: path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+        outcome,
+        frame_hash: crc32(device.display().buffer()),
+        instructions: device.instructions(),
+    }
+}
+
+// Differences between a previous report and this run, one line per ROM
+pub fn regressions(previous: &[Entry], current: &[Entry]) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    for entry in current {
+        match previous.iter().find(|old| old.rom == entry.rom) {
+            None => {}
+            Some(old) if old == entry => {}
+            Some(old) => changes.push(format!(
+                "{}: {} -> {}",
+                entry.rom,
+                old.summary(),
+                entry.summary()
+            )),
+        }
+    }
+
+    for old in previous {
+        if !current.iter().any(|entry| entry.rom == old.rom) {
+            changes.push(format!("{}: missing from this run", old.rom));
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(rom: &str, instructions: u64) -> Entry {
+        Entry {
+            rom: rom.to_owned(),
+            outcome: "halted".to_owned(),
+            frame_hash: 0xABCD,
+            instructions,
+        }
+    }
+
+    #[test]
+    fn roundtrips_report_lines() {
+        let line = entry("pong.ch8", 42).to_line();
+
+        assert_eq!(line, "pong.ch8\thalted\t0000abcd\t42");
+        assert!(Entry::parse_line(&line) == Some(entry("pong.ch8", 42)));
+    }
+
+    #[test]
+    fn finds_changed_and_missing_roms() {
+        let previous = [entry("a.ch8", 1), entry("b.ch8", 2), entry("c.ch8", 3)];
+        let current = [entry("a.ch8", 1), entry("b.ch8", 5)];
+
+        let changes = regressions(&previous, &current);
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0], "b.ch8: halted 0000abcd 2 -> halted 0000abcd 5");
+        assert_eq!(changes[1], "c.ch8: missing from this run");
+    }
+}
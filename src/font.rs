@@ -0,0 +1,631 @@
+// The set of small hex digits loaded at address 0 for FX29. Interpreters
+// each drew their own, and ROMs that draw digits as part of their
+// graphics only look right with the one they were written for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Font {
+    Vip,
+    Dream6800,
+    Eti660,
+    FishNChips,
+}
+
+impl Font {
+    pub const ALL: [Self; 4] = [Self::Vip, Self::Dream6800, Self::Eti660, Self::FishNChips];
+
+    pub fn parse(value: &str) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|font| font.name() == value)
+            .unwrap_or_else(|| {
+                panic!(
+                    "Unknown font '{}', expected vip, dream6800, eti660 or fishnchips",
+                    value
+                )
+            })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Vip => "vip",
+            Self::Dream6800 => "dream6800",
+            Self::Eti660 => "eti660",
+            Self::FishNChips => "fishnchips",
+        }
+    }
+
+    pub fn digits(&self) -> &'static [u8; 80] {
+        match self {
+            Self::Vip => &VIP,
+            Self::Dream6800 => &DREAM_6800,
+            Self::Eti660 => &ETI_660,
+            Self::FishNChips => &FISH_N_CHIPS,
+        }
+    }
+
+    // The one after this in `ALL`, for cycling through them
+    pub fn next(&self) -> Self {
+        let position = Self::ALL.iter().position(|font| font == self).unwrap();
+
+        Self::ALL[(position + 1) % Self::ALL.len()]
+    }
+}
+
+// COSMAC VIP hex digits, 4x5 pixels each
+#[rustfmt::skip]
+pub const VIP: [u8; 80] = [
+    0b11110000,
+    0b10010000,
+    0b10010000,
+    0b10010000,
+    0b11110000,
+
+    0b00100000,
+    0b01100000,
+    0b00100000,
+    0b00100000,
+    0b01110000,
+
+    0b11110000,
+    0b00010000,
+    0b11110000,
+    0b10000000,
+    0b11110000,
+
+    0b11110000,
+    0b00010000,
+    0b11110000,
+    0b00010000,
+    0b11110000,
+
+    0b10010000,
+    0b10010000,
+    0b11110000,
+    0b00010000,
+    0b00010000,
+
+    0b11110000,
+    0b10000000,
+    0b11110000,
+    0b00010000,
+    0b11110000,
+
+    0b11110000,
+    0b10000000,
+    0b11110000,
+    0b10010000,
+    0b11110000,
+
+    0b11110000,
+    0b00010000,
+    0b00100000,
+    0b01000000,
+    0b01000000,
+
+    0b11110000,
+    0b10010000,
+    0b11110000,
+    0b10010000,
+    0b11110000,
+
+    0b11110000,
+    0b10010000,
+    0b11110000,
+    0b00010000,
+    0b11110000,
+
+    0b11110000,
+    0b10010000,
+    0b11110000,
+    0b10010000,
+    0b10010000,
+
+    0b11100000,
+    0b10010000,
+    0b11100000,
+    0b10010000,
+    0b11100000,
+
+    0b11110000,
+    0b10000000,
+    0b10000000,
+    0b10000000,
+    0b11110000,
+
+    0b11100000,
+    0b10010000,
+    0b10010000,
+    0b10010000,
+    0b11100000,
+
+    0b11110000,
+    0b10000000,
+    0b11110000,
+    0b10000000,
+    0b11110000,
+
+    0b11110000,
+    0b10000000,
+    0b11110000,
+    0b10000000,
+    0b10000000,
+];
+
+// DREAM 6800 (CHIPOS) hex digits, 3x5 pixels each
+#[rustfmt::skip]
+pub const DREAM_6800: [u8; 80] = [
+    0b11100000,
+    0b10100000,
+    0b10100000,
+    0b10100000,
+    0b11100000,
+
+    0b01000000,
+    0b01000000,
+    0b01000000,
+    0b01000000,
+    0b01000000,
+
+    0b11100000,
+    0b00100000,
+    0b11100000,
+    0b10000000,
+    0b11100000,
+
+    0b11100000,
+    0b00100000,
+    0b11100000,
+    0b00100000,
+    0b11100000,
+
+    0b10000000,
+    0b10100000,
+    0b10100000,
+    0b11100000,
+    0b00100000,
+
+    0b11100000,
+    0b10000000,
+    0b11100000,
+    0b00100000,
+    0b11100000,
+
+    0b11100000,
+    0b10000000,
+    0b11100000,
+    0b10100000,
+    0b11100000,
+
+    0b11100000,
+    0b00100000,
+    0b00100000,
+    0b00100000,
+    0b00100000,
+
+    0b11100000,
+    0b10100000,
+    0b11100000,
+    0b10100000,
+    0b11100000,
+
+    0b11100000,
+    0b10100000,
+    0b11100000,
+    0b00100000,
+    0b11100000,
+
+    0b11100000,
+    0b10100000,
+    0b11100000,
+    0b10100000,
+    0b10100000,
+
+    0b11000000,
+    0b10100000,
+    0b11100000,
+    0b10100000,
+    0b11000000,
+
+    0b11100000,
+    0b10000000,
+    0b10000000,
+    0b10000000,
+    0b11100000,
+
+    0b11000000,
+    0b10100000,
+    0b10100000,
+    0b10100000,
+    0b11000000,
+
+    0b11100000,
+    0b10000000,
+    0b11100000,
+    0b10000000,
+    0b11100000,
+
+    0b11100000,
+    0b10000000,
+    0b11000000,
+    0b10000000,
+    0b10000000,
+];
+
+// ETI-660 hex digits, 3x5 pixels each with lower case B and D
+#[rustfmt::skip]
+pub const ETI_660: [u8; 80] = [
+    0b11100000,
+    0b10100000,
+    0b10100000,
+    0b10100000,
+    0b11100000,
+
+    0b00100000,
+    0b00100000,
+    0b00100000,
+    0b00100000,
+    0b00100000,
+
+    0b11100000,
+    0b00100000,
+    0b11100000,
+    0b10000000,
+    0b11100000,
+
+    0b11100000,
+    0b00100000,
+    0b11100000,
+    0b00100000,
+    0b11100000,
+
+    0b10100000,
+    0b10100000,
+    0b11100000,
+    0b00100000,
+    0b00100000,
+
+    0b11100000,
+    0b10000000,
+    0b11100000,
+    0b00100000,
+    0b11100000,
+
+    0b11100000,
+    0b10000000,
+    0b11100000,
+    0b10100000,
+    0b11100000,
+
+    0b11100000,
+    0b00100000,
+    0b00100000,
+    0b00100000,
+    0b00100000,
+
+    0b11100000,
+    0b10100000,
+    0b11100000,
+    0b10100000,
+    0b11100000,
+
+    0b11100000,
+    0b10100000,
+    0b11100000,
+    0b00100000,
+    0b11100000,
+
+    0b11100000,
+    0b10100000,
+    0b11100000,
+    0b10100000,
+    0b10100000,
+
+    0b10000000,
+    0b10000000,
+    0b11100000,
+    0b10100000,
+    0b11100000,
+
+    0b11100000,
+    0b10000000,
+    0b10000000,
+    0b10000000,
+    0b11100000,
+
+    0b00100000,
+    0b00100000,
+    0b11100000,
+    0b10100000,
+    0b11100000,
+
+    0b11100000,
+    0b10000000,
+    0b11100000,
+    0b10000000,
+    0b11100000,
+
+    0b11100000,
+    0b10000000,
+    0b11000000,
+    0b10000000,
+    0b10000000,
+];
+
+// Fish'N'Chips hex digits, 3x5 pixels each with rounded corners
+#[rustfmt::skip]
+pub const FISH_N_CHIPS: [u8; 80] = [
+    0b01100000,
+    0b10100000,
+    0b10100000,
+    0b10100000,
+    0b11000000,
+
+    0b01000000,
+    0b11000000,
+    0b01000000,
+    0b01000000,
+    0b11100000,
+
+    0b11000000,
+    0b00100000,
+    0b01000000,
+    0b10000000,
+    0b11100000,
+
+    0b11000000,
+    0b00100000,
+    0b01000000,
+    0b00100000,
+    0b11000000,
+
+    0b00100000,
+    0b10100000,
+    0b11100000,
+    0b00100000,
+    0b00100000,
+
+    0b11100000,
+    0b10000000,
+    0b11000000,
+    0b00100000,
+    0b11000000,
+
+    0b01000000,
+    0b10000000,
+    0b11000000,
+    0b10100000,
+    0b01000000,
+
+    0b11100000,
+    0b00100000,
+    0b01100000,
+    0b01000000,
+    0b01000000,
+
+    0b01000000,
+    0b10100000,
+    0b01000000,
+    0b10100000,
+    0b01000000,
+
+    0b01000000,
+    0b10100000,
+    0b01100000,
+    0b00100000,
+    0b01000000,
+
+    0b01000000,
+    0b10100000,
+    0b11100000,
+    0b10100000,
+    0b10100000,
+
+    0b11000000,
+    0b10100000,
+    0b11000000,
+    0b10100000,
+    0b11000000,
+
+    0b01100000,
+    0b10000000,
+    0b10000000,
+    0b10000000,
+    0b01100000,
+
+    0b11000000,
+    0b10100000,
+    0b10100000,
+    0b10100000,
+    0b11000000,
+
+    0b11100000,
+    0b10000000,
+    0b11000000,
+    0b10000000,
+    0b11100000,
+
+    0b11100000,
+    0b10000000,
+    0b11000000,
+    0b10000000,
+    0b10000000,
+];
+
+// SUPER-CHIP large hex digits for FX30, 8x10 pixels each
+#[rustfmt::skip]
+pub const BIG: [u8; 160] = [
+    0b11111111,
+    0b11111111,
+    0b11000011,
+    0b11000011,
+    0b11000011,
+    0b11000011,
+    0b11000011,
+    0b11000011,
+    0b11111111,
+    0b11111111,
+
+    0b00011000,
+    0b01111000,
+    0b01111000,
+    0b00011000,
+    0b00011000,
+    0b00011000,
+    0b00011000,
+    0b00011000,
+    0b11111111,
+    0b11111111,
+
+    0b11111111,
+    0b11111111,
+    0b00000011,
+    0b00000011,
+    0b11111111,
+    0b11111111,
+    0b11000000,
+    0b11000000,
+    0b11111111,
+    0b11111111,
+
+    0b11111111,
+    0b11111111,
+    0b00000011,
+    0b00000011,
+    0b11111111,
+    0b11111111,
+    0b00000011,
+    0b00000011,
+    0b11111111,
+    0b11111111,
+
+    0b11000011,
+    0b11000011,
+    0b11000011,
+    0b11000011,
+    0b11111111,
+    0b11111111,
+    0b00000011,
+    0b00000011,
+    0b00000011,
+    0b00000011,
+
+    0b11111111,
+    0b11111111,
+    0b11000000,
+    0b11000000,
+    0b11111111,
+    0b11111111,
+    0b00000011,
+    0b00000011,
+    0b11111111,
+    0b11111111,
+
+    0b11111111,
+    0b11111111,
+    0b11000000,
+    0b11000000,
+    0b11111111,
+    0b11111111,
+    0b11000011,
+    0b11000011,
+    0b11111111,
+    0b11111111,
+
+    0b11111111,
+    0b11111111,
+    0b00000011,
+    0b00000011,
+    0b00000110,
+    0b00001100,
+    0b00011000,
+    0b00011000,
+    0b00011000,
+    0b00011000,
+
+    0b11111111,
+    0b11111111,
+    0b11000011,
+    0b11000011,
+    0b11111111,
+    0b11111111,
+    0b11000011,
+    0b11000011,
+    0b11111111,
+    0b11111111,
+
+    0b11111111,
+    0b11111111,
+    0b11000011,
+    0b11000011,
+    0b11111111,
+    0b11111111,
+    0b00000011,
+    0b00000011,
+    0b11111111,
+    0b11111111,
+
+    0b01111110,
+    0b11111111,
+    0b11000011,
+    0b11000011,
+    0b11000011,
+    0b11111111,
+    0b11111111,
+    0b11000011,
+    0b11000011,
+    0b11000011,
+
+    0b11111100,
+    0b11111100,
+    0b11000011,
+    0b11000011,
+    0b11111100,
+    0b11111100,
+    0b11000011,
+    0b11000011,
+    0b11111100,
+    0b11111100,
+
+    0b00111100,
+    0b11111111,
+    0b11000011,
+    0b11000000,
+    0b11000000,
+    0b11000000,
+    0b11000000,
+    0b11000011,
+    0b11111111,
+    0b00111100,
+
+    0b11111100,
+    0b11111110,
+    0b11000011,
+    0b11000011,
+    0b11000011,
+    0b11000011,
+    0b11000011,
+    0b11000011,
+    0b11111110,
+    0b11111100,
+
+    0b11111111,
+    0b11111111,
+    0b11000000,
+    0b11000000,
+    0b11111111,
+    0b11111111,
+    0b11000000,
+    0b11000000,
+    0b11111111,
+    0b11111111,
+
+    0b11111111,
+    0b11111111,
+    0b11000000,
+    0b11000000,
+    0b11111111,
+    0b11111111,
+    0b11000000,
+    0b11000000,
+    0b11000000,
+    0b11000000,
+];
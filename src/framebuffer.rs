@@ -0,0 +1,258 @@
+use crate::palette::Palette;
+use crate::png;
+
+// A fixed-size grid of pixels, each a bitmask of the planes lit at that
+// position. The size is part of the type so the emulator, tests and
+// renderers all agree on the layout without passing widths around.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Framebuffer<const W: usize, const H: usize> {
+    rows: [[u8; W]; H],
+}
+
+pub type Lores = Framebuffer<64, 32>;
+pub type Hires = Framebuffer<128, 64>;
+pub type TwoPage = Framebuffer<64, 64>;
+pub type Mega = Framebuffer<256, 192>;
+
+impl<const W: usize, const H: usize> Default for Framebuffer<W, H> {
+    fn default() -> Self {
+        Self { rows: [[0; W]; H] }
+    }
+}
+
+impl<const W: usize, const H: usize> Framebuffer<W, H> {
+    pub const WIDTH: usize = W;
+    pub const HEIGHT: usize = H;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Build from row-major pixels, which must cover the whole framebuffer
+    pub fn from_slice(pixels: &[u8]) -> Self {
+        let mut framebuffer = Self::new();
+        framebuffer.as_mut_slice().copy_from_slice(pixels);
+        framebuffer
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.rows[y][x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, planes: u8) {
+        self.rows[y][x] = planes;
+    }
+
+    // XOR planes into a pixel, returning whether any of them were already lit
+    pub fn toggle(&mut self, x: usize, y: usize, planes: u8) -> bool {
+        let pixel = &mut self.rows[y][x];
+        let collision = *pixel & planes != 0;
+
+        *pixel ^= planes;
+
+        collision
+    }
+
+    pub fn row(&self, y: usize) -> &[u8; W] {
+        &self.rows[y]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[u8; W]> {
+        self.rows.iter()
+    }
+
+    // Every pixel as `(x, y, planes)`, in row-major order
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize, u8)> + '_ {
+        self.rows.iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(x, &planes)| (x, y, planes))
+        })
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        self.rows.as_flattened()
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.rows.as_flattened_mut()
+    }
+
+    pub fn clear(&mut self) {
+        self.as_mut_slice().fill(0);
+    }
+
+    // Positions of the pixels that differ from `other`
+    pub fn diff<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.pixels()
+            .zip(other.pixels())
+            .filter(|((_, _, now), (_, _, before))| now != before)
+            .map(|((x, y, _), _)| (x, y))
+    }
+
+    // Render as text, one line per row with `#` for lit pixels
+    pub fn ascii(&self) -> String {
+        let mut text = String::with_capacity((W + 1) * H);
+
+        for row in self.rows() {
+            text.extend(row.iter().map(|&pixel| if pixel != 0 { '#' } else { '.' }));
+            text.push('\n');
+        }
+
+        text
+    }
+
+    // Encode as a binary PBM image, lit pixels are black
+    pub fn pbm(&self) -> Vec<u8> {
+        let mut image = format!("P4\n{} {}\n", W, H).into_bytes();
+
+        for row in self.rows() {
+            for pixels in row.chunks(8) {
+                let byte = pixels.iter().enumerate().fold(0u8, |byte, (bit, &pixel)| {
+                    byte | (u8::from(pixel != 0) << (7 - bit))
+                });
+
+                image.push(byte);
+            }
+        }
+
+        image
+    }
+
+    // Encode as a greyscale PNG, lit pixels are white like on screen
+    pub fn png(&self) -> Vec<u8> {
+        let rows = self
+            .rows()
+            .map(|row| row.map(|pixel| if pixel != 0 { 0xFF } else { 0x00 }));
+
+        let mut image = png::SIGNATURE.to_vec();
+        png::chunk(
+            &mut image,
+            b"IHDR",
+            &png::header(W as u32, H as u32, 8, png::GREYSCALE),
+        );
+        png::chunk(
+            &mut image,
+            b"IDAT",
+            &png::zlib_stored(&png::scanlines(rows, 8)),
+        );
+        png::chunk(&mut image, b"IEND", &[]);
+
+        image
+    }
+
+    // Encode as a PNG in the given colours with every pixel blown up to a
+    // `scale` by `scale` square, independent of any window size
+    pub fn capture(&self, palette: &Palette, scale: usize) -> Vec<u8> {
+        let scale = scale.max(1);
+        let rows = self.rows().flat_map(|row| {
+            let row: Vec<u8> = row
+                .iter()
+                .flat_map(|&pixel| std::iter::repeat_n(pixel & 0b11, scale))
+                .collect();
+
+            std::iter::repeat_n(row, scale)
+        });
+
+        let mut image = png::SIGNATURE.to_vec();
+        png::chunk(
+            &mut image,
+            b"IHDR",
+            &png::header((W * scale) as u32, (H * scale) as u32, 2, png::INDEXED),
+        );
+        png::chunk(&mut image, b"PLTE", &palette.rgb());
+        png::chunk(
+            &mut image,
+            b"IDAT",
+            &png::zlib_stored(&png::scanlines(rows, 2)),
+        );
+        png::chunk(&mut image, b"IEND", &[]);
+
+        image
+    }
+
+    // Raw RGBA pixels in the given colours at `scale` times the resolution,
+    // row by row
+    pub fn rgba(&self, palette: &Palette, scale: usize) -> Vec<u8> {
+        let scale = scale.max(1);
+
+        self.rows()
+            .flat_map(|row| {
+                let row: Vec<u8> = row
+                    .iter()
+                    .flat_map(|&pixel| {
+                        let [r, g, b] = palette.color(pixel);
+                        std::iter::repeat_n([r, g, b, 0xFF], scale)
+                    })
+                    .flatten()
+                    .collect();
+
+                std::iter::repeat_n(row, scale).flatten()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Tiny = Framebuffer<4, 2>;
+
+    #[test]
+    fn toggle_reports_collisions() {
+        let mut framebuffer = Tiny::new();
+
+        assert!(!framebuffer.toggle(1, 1, 1));
+        assert!(framebuffer.toggle(1, 1, 1));
+        assert_eq!(framebuffer.get(1, 1), 0);
+    }
+
+    #[test]
+    fn rows_are_laid_out_contiguously() {
+        let framebuffer = Tiny::from_slice(&[1, 0, 0, 0, 0, 0, 0, 1]);
+
+        assert_eq!(framebuffer.row(0), &[1, 0, 0, 0]);
+        assert_eq!(framebuffer.row(1), &[0, 0, 0, 1]);
+        assert_eq!(framebuffer.ascii(), "#...\n...#\n");
+    }
+
+    #[test]
+    fn diff_lists_changed_pixels() {
+        let before = Tiny::from_slice(&[1, 0, 0, 0, 0, 0, 0, 1]);
+        let mut after = before.clone();
+        after.set(0, 0, 0);
+        after.set(2, 1, 1);
+
+        assert_eq!(after.diff(&before).collect::<Vec<_>>(), [(0, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn rgba_scales_pixels_in_palette_colours() {
+        let framebuffer = Tiny::from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        let rgba = framebuffer.rgba(&Palette::default(), 2);
+
+        assert_eq!(rgba.len(), 8 * 4 * 4);
+        // the lit pixel covers the first two columns of the first two rows
+        assert_eq!(
+            rgba[..12],
+            [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0xFF]
+        );
+        assert_eq!(rgba[32..36], [0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn pbm_packs_rows() {
+        let framebuffer = Tiny::from_slice(&[1, 0, 0, 1, 0, 1, 1, 0]);
+
+        assert_eq!(framebuffer.pbm(), b"P4\n4 2\n\x90\x60");
+    }
+
+    #[test]
+    fn png_has_valid_structure() {
+        let png = Lores::new().png();
+
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x40\0\0\0\x20"));
+        assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt::Write;
+
+#[derive(Clone, Copy)]
+pub struct InputEvent {
+    // Frames since the start of the recording
+    pub frame: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+// A recorded sequence of key changes, timed in emulated frames
+#[derive(Clone, Default)]
+pub struct InputLog {
+    // Keys already held down when the recording started
+    pub initial_keys: [bool; 16],
+    pub events: Vec<InputEvent>,
+}
+
+impl InputLog {
+    pub fn push(&mut self, frame: u64, key: u8, pressed: bool) {
+        self.events.push(InputEvent {
+            frame,
+            key,
+            pressed,
+        });
+    }
+
+    pub fn new(initial_keys: [bool; 16]) -> Self {
+        Self {
+            initial_keys,
+            events: Vec::new(),
+        }
+    }
+
+    // A `keys <mask>` line with the initial keypad state, followed by one
+    // event per line: `<frame> <key> down|up`
+    pub fn to_text(&self) -> String {
+        let mask = (0..16)
+            .filter(|&key| self.initial_keys[key])
+            .fold(0u16, |mask, key| mask | 1 << key);
+
+        let mut text = format!("keys {:04x}\n", mask);
+
+        for event in &self.events {
+            let state = if event.pressed { "down" } else { "up" };
+            writeln!(text, "{} {:x} {}", event.frame, event.key, state).unwrap();
+        }
+
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut log = Self::default();
+
+        for (number, line) in text.lines().enumerate() {
+            if let Some(mask) = line.strip_prefix("keys ") {
+                let mask = u16::from_str_radix(mask, 16)
+                    .map_err(|_| format!("invalid key mask on line {}", number + 1))?;
+
+                for (key, held) in log.initial_keys.iter_mut().enumerate() {
+                    *held = mask & (1 << key) != 0;
+                }
+
+                continue;
+            }
+
+            let invalid = || format!("invalid input event on line {}: '{}'", number + 1, line);
+            let mut parts = line.split_whitespace();
+
+            let (Some(frame), Some(key), Some(state), None) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                return Err(invalid());
+            };
+
+            let frame = frame.parse().map_err(|_| invalid())?;
+            let key = u8::from_str_radix(key, 16)
+                .ok()
+                .filter(|&key| key < 16)
+                .ok_or_else(invalid)?;
+            let pressed = match state {
+                "down" => true,
+                "up" => false,
+                _ => return Err(invalid()),
+            };
+
+            log.push(frame, key, pressed);
+        }
+
+        Ok(log)
+    }
+}
+
+// Feeds the events of a log back in as the frames go by
+pub struct Playback {
+    log: InputLog,
+    start: u64,
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(log: InputLog, start: u64) -> Self {
+        Self {
+            log,
+            start,
+            next: 0,
+        }
+    }
+
+    // Events that are due by `frame`
+    pub fn due(&mut self, frame: u64) -> &[InputEvent] {
+        let first = self.next;
+
+        while let Some(event) = self.log.events.get(self.next) {
+            if self.start + event.frame > frame {
+                break;
+            }
+
+            self.next += 1;
+        }
+
+        &self.log.events[first..self.next]
+    }
+
+    pub fn initial_keys(&self) -> [bool; 16] {
+        self.log.initial_keys
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.log.events.len()
+    }
+}
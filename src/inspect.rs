@@ -0,0 +1,222 @@
+use crate::disasm::{self, Instruction, Variant};
+use crate::hash::{hex, sha1};
+use crate::json::{self, Value};
+
+use std::collections::BTreeSet;
+
+// Everything `chip8 info` reports about a ROM, worked out statically
+// without running it
+pub struct Inspection {
+    pub size: usize,
+    pub sha1: String,
+    // Most advanced instruction set used by the reachable code
+    pub variant: Variant,
+    pub entry: Vec<(u16, Instruction)>,
+    pub warnings: Vec<String>,
+}
+
+const START: u16 = 0x200;
+// Memory available for the program, anything past this is cut off when loading
+const MAX_SIZE: usize = 0xFFF - START as usize;
+const XO_CHIP_MAX_SIZE: usize = 0x10000 - START as usize;
+const ENTRY_INSTRUCTIONS: usize = 8;
+
+pub fn inspect(rom: &[u8]) -> Inspection {
+    let mut inspection = Inspection {
+        size: rom.len(),
+        sha1: hex(&sha1(rom)),
+        variant: Variant::Chip8,
+        entry: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    if rom.is_empty() {
+        inspection.warnings.push("ROM is empty".to_owned());
+        return inspection;
+    }
+
+    let mut address = START;
+
+    while inspection.entry.len() < ENTRY_INSTRUCTIONS {
+        match disasm::disassemble(at(rom, address)) {
+            Some(instruction) => {
+                let size = instruction.size;
+                inspection.entry.push((address, instruction));
+                address = address.wrapping_add(size);
+            }
+            None => break,
+        }
+    }
+
+    trace(rom, &mut inspection);
+
+    // XO-CHIP ROMs can fill 64K, so only warn about size once it's known
+    // which instructions the ROM uses
+    if inspection.variant == Variant::XoChip {
+        if rom.len() > XO_CHIP_MAX_SIZE {
+            inspection.warnings.push(format!(
+                "ROM is {} bytes, only the first {} fit in XO-CHIP's 64K of memory",
+                rom.len(),
+                XO_CHIP_MAX_SIZE
+            ));
+        }
+    } else if rom.len() > MAX_SIZE {
+        inspection.warnings.push(format!(
+            "ROM is {} bytes, only the first {} fit in memory without --banking",
+            rom.len(),
+            MAX_SIZE
+        ));
+    }
+
+    inspection
+}
+
+fn at(rom: &[u8], address: u16) -> &[u8] {
+    rom.get(usize::from(address.wrapping_sub(START))..)
+        .unwrap_or_default()
+}
+
+fn in_rom(rom: &[u8], address: u16) -> bool {
+    address >= START && usize::from(address - START) < rom.len()
+}
+
+// Follow every path through the code from the entry point, to find the
+// instructions the ROM can actually reach rather than misreading its data
+fn trace(rom: &[u8], inspection: &mut Inspection) {
+    let mut pending = vec![START];
+    let mut visited = BTreeSet::new();
+
+    while let Some(address) = pending.pop() {
+        if !in_rom(rom, address) || !visited.insert(address) {
+            continue;
+        }
+
+        let Some(instruction) = disasm::disassemble(at(rom, address)) else {
+            let opcode = at(rom, address).first().copied().unwrap_or_default();
+
+            inspection.warnings.push(format!(
+                "Unknown instruction {:02X}.. reachable at 0x{:03X}",
+                opcode, address
+            ));
+            continue;
+        };
+
+        inspection.variant = inspection.variant.max(instruction.variant);
+
+        let opcode = instruction.opcode;
+        let next = address.wrapping_add(instruction.size);
+        let target = opcode & 0x0FFF;
+
+        match opcode >> 12 {
+            0x0 if opcode == 0x00EE || opcode == 0x00FD => {}
+            0x0 if instruction.mnemonic.starts_with("SYS") => {
+                inspection.warnings.push(format!(
+                    "Machine code routine 0x{:03X} called at 0x{:03X}",
+                    target, address
+                ));
+                pending.push(next);
+            }
+            0x1 | 0x2 => {
+                if !in_rom(rom, target) {
+                    inspection.warnings.push(format!(
+                        "{} at 0x{:03X} leaves the ROM",
+                        instruction.mnemonic, address
+                    ));
+                }
+
+                pending.push(target);
+
+                if opcode >> 12 == 0x2 {
+                    pending.push(next);
+                }
+            }
+            // jump target depends on V0 at runtime
+            0xB => {}
+            0x3 | 0x4 | 0x9 | 0xE => skip(rom, next, &mut pending),
+            0x5 if opcode & 0xF == 0 => skip(rom, next, &mut pending),
+            _ => pending.push(next),
+        }
+    }
+}
+
+// Both outcomes of a conditional skip, stepping over a long instruction
+fn skip(rom: &[u8], next: u16, pending: &mut Vec<u16>) {
+    let size = disasm::disassemble(at(rom, next)).map_or(2, |instruction| instruction.size);
+
+    pending.push(next);
+    pending.push(next.wrapping_add(size));
+}
+
+impl Inspection {
+    pub fn print(&self) {
+        println!("Size:     {} bytes", self.size);
+        println!("SHA-1:    {}", self.sha1);
+        println!("Platform: {} (from instructions used)", self.variant.name());
+        println!("Entry:");
+
+        for (address, instruction) in &self.entry {
+            println!(
+                "  0x{:03X}  {:04X}  {}",
+                address, instruction.opcode, instruction.mnemonic
+            );
+        }
+
+        if !self.warnings.is_empty() {
+            println!("Warnings:");
+
+            for warning in &self.warnings {
+                println!("  {}", warning);
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let entry: Vec<Value> = self
+            .entry
+            .iter()
+            .map(|(address, instruction)| {
+                Value::object()
+                    .with("address", *address)
+                    .with("opcode", instruction.opcode)
+                    .with("mnemonic", instruction.mnemonic.as_str())
+            })
+            .collect();
+
+        Value::object()
+            .with("schema", json::SCHEMA_VERSION)
+            .with("command", "info")
+            .with("size", self.size)
+            .with("sha1", self.sha1.as_str())
+            .with("platform", self.variant.name())
+            .with("entry", Value::Array(entry))
+            .with("warnings", self.warnings.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::RomBuilder;
+
+    #[test]
+    fn detects_variant_from_reachable_code() {
+        let mut rom = RomBuilder::new();
+        rom.jump(0x204).opcode(0x00FF).cls().halt();
+
+        assert_eq!(inspect(&rom.build()).variant, Variant::Chip8);
+
+        let mut reached = RomBuilder::new();
+        reached.opcode(0x00FF).halt();
+
+        assert_eq!(inspect(&reached.build()).variant, Variant::Schip);
+    }
+
+    #[test]
+    fn warns_about_jumps_out_of_the_rom() {
+        let rom = RomBuilder::new().cls().jump(0x800).build();
+        let inspection = inspect(&rom);
+
+        assert_eq!(inspection.entry.len(), 2);
+        assert_eq!(inspection.warnings, ["JP 0x800 at 0x202 leaves the ROM"]);
+    }
+}
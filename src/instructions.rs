@@ -0,0 +1,101 @@
+// Which handler runs each of the 65536 opcodes, built from a list of
+// instructions given as the bits of the opcode that have to match
+pub struct Table<H> {
+    instructions: Vec<(u16, u16, H)>,
+    // Position in `handlers` for each opcode, or `NONE`
+    index: Box<[u8]>,
+}
+
+const NONE: u8 = u8::MAX;
+
+impl<H: Copy> Table<H> {
+    // `instructions` as `(mask, pattern, handler)`, an opcode running the
+    // first handler where `opcode & mask == pattern`, so more specific
+    // instructions go before the general ones they overlap
+    pub fn new(instructions: &[(u16, u16, H)]) -> Self {
+        assert!(instructions.len() < usize::from(NONE));
+
+        let mut index = vec![NONE; 0x10000].into_boxed_slice();
+
+        for (position, &(mask, pattern, _)) in instructions.iter().enumerate().rev() {
+            // every combination of the bits the mask leaves free
+            let free = !mask;
+            let mut bits = 0u16;
+
+            loop {
+                index[usize::from(pattern & mask | bits)] = position as u8;
+
+                if bits == free {
+                    break;
+                }
+
+                bits = bits.wrapping_sub(free) & free;
+            }
+        }
+
+        Self {
+            instructions: instructions.to_vec(),
+            index,
+        }
+    }
+
+    pub fn get(&self, opcode: u16) -> Option<H> {
+        self.find(opcode).map(|&(_, _, handler)| handler)
+    }
+
+    // The mask and pattern of the instruction an opcode runs, to tell
+    // whether two tables run it the same way
+    pub fn instruction(&self, opcode: u16) -> Option<(u16, u16)> {
+        self.find(opcode).map(|&(mask, pattern, _)| (mask, pattern))
+    }
+
+    // The instructions some opcode runs, leaving out any covered completely
+    // by those ahead of them
+    pub fn reachable(&self) -> Vec<H> {
+        let mut used = vec![false; self.instructions.len()];
+
+        for &position in self.index.iter().filter(|&&position| position != NONE) {
+            used[usize::from(position)] = true;
+        }
+
+        self.instructions
+            .iter()
+            .zip(used)
+            .filter(|&(_, used)| used)
+            .map(|(&(_, _, handler), _)| handler)
+            .collect()
+    }
+
+    fn find(&self, opcode: u16) -> Option<&(u16, u16, H)> {
+        match self.index[usize::from(opcode)] {
+            NONE => None,
+            position => Some(&self.instructions[usize::from(position)]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_the_first_matching_instruction() {
+        let table = Table::new(&[
+            (0xFFFF, 0x00E0, 'c'),
+            (0xF000, 0x0000, 's'),
+            (0xF00F, 0x8004, 'a'),
+        ]);
+
+        assert_eq!(table.get(0x00E0), Some('c'));
+        assert_eq!(table.get(0x00E1), Some('s'));
+        assert_eq!(table.get(0x0FFF), Some('s'));
+        assert_eq!(table.get(0x8AB4), Some('a'));
+        assert_eq!(table.get(0x8AB5), None);
+        assert_eq!(table.get(0x1000), None);
+        assert_eq!(table.instruction(0x0123), Some((0xF000, 0x0000)));
+
+        let shadowed = Table::new(&[(0xF000, 0xB000, 'x'), (0xF000, 0xB000, 'j')]);
+
+        assert_eq!(shadowed.reachable(), ['x']);
+    }
+}
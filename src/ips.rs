@@ -0,0 +1,96 @@
+// Apply an IPS patch to a ROM image. Records can write past the end of
+// the ROM, which grows it, and an optional trailer truncates it.
+pub fn apply(rom: &mut Vec<u8>, patch: &[u8]) -> Result<(), String> {
+    let mut data = patch.strip_prefix(b"PATCH").ok_or("missing PATCH header")?;
+
+    loop {
+        let (offset, rest) = take(data, 3)?;
+
+        if offset == b"EOF" {
+            if let Ok((size, _)) = take(rest, 3) {
+                rom.truncate(be(size));
+            }
+
+            return Ok(());
+        }
+
+        let offset = be(offset);
+        let (size, rest) = take(rest, 2)?;
+
+        let (bytes, rest) = match be(size) {
+            // run-length encoded record
+            0 => {
+                let (length, rest) = take(rest, 2)?;
+                let (value, rest) = take(rest, 1)?;
+
+                (vec![value[0]; be(length)], rest)
+            }
+            size => {
+                let (bytes, rest) = take(rest, size)?;
+
+                (bytes.to_vec(), rest)
+            }
+        };
+
+        if rom.len() < offset + bytes.len() {
+            rom.resize(offset + bytes.len(), 0);
+        }
+
+        rom[offset..offset + bytes.len()].copy_from_slice(&bytes);
+        data = rest;
+    }
+}
+
+fn take(data: &[u8], len: usize) -> Result<(&[u8], &[u8]), String> {
+    if data.len() < len {
+        return Err("unexpected end of patch".to_string());
+    }
+
+    Ok(data.split_at(len))
+}
+
+fn be(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| (value << 8) | usize::from(byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_records() {
+        let mut rom = vec![0; 4];
+        let patch = b"PATCH\x00\x00\x01\x00\x02\xAA\xBBEOF";
+
+        apply(&mut rom, patch).unwrap();
+
+        assert_eq!(rom, [0x00, 0xAA, 0xBB, 0x00]);
+    }
+
+    #[test]
+    fn applies_rle_records_and_grows_rom() {
+        let mut rom = vec![0; 2];
+        let patch = b"PATCH\x00\x00\x01\x00\x00\x00\x03\xCCEOF";
+
+        apply(&mut rom, patch).unwrap();
+
+        assert_eq!(rom, [0x00, 0xCC, 0xCC, 0xCC]);
+    }
+
+    #[test]
+    fn truncates() {
+        let mut rom = vec![0; 8];
+
+        apply(&mut rom, b"PATCHEOF\x00\x00\x02").unwrap();
+
+        assert_eq!(rom.len(), 2);
+    }
+
+    #[test]
+    fn rejects_bad_patches() {
+        assert!(apply(&mut vec![], b"NOTIPS").is_err());
+        assert!(apply(&mut vec![], b"PATCH\x00\x00\x01\x00\x04\xAA").is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// Version of the `--json` output, bumped whenever a field is removed or
+// changes meaning. New fields may be added without a bump.
+pub const SCHEMA_VERSION: u32 = 1;
+
+// Just enough JSON to write machine-readable output and read the bundled
+// ROM database, keys keep the order they were added in
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn object() -> Self {
+        Self::Object(Vec::new())
+    }
+
+    // Add a field to an object, for building them up in a chain
+    pub fn with(mut self, key: &str, value: impl Into<Value>) -> Self {
+        match &mut self {
+            Self::Object(fields) => fields.push((key.to_owned(), value.into())),
+            _ => panic!("Can only add fields to objects"),
+        }
+
+        self
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars)?;
+
+        skip_whitespace(&mut chars);
+
+        match chars.next() {
+            Some(c) => Err(format!("unexpected '{}' after the value", c)),
+            None => Ok(value),
+        }
+    }
+
+    // A field of an object
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Self::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn expect(chars: &mut Peekable<Chars>, word: &str) -> Result<(), String> {
+    for expected in word.chars() {
+        if chars.next() != Some(expected) {
+            return Err(format!("expected '{}'", word));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Value, String> {
+    skip_whitespace(chars);
+
+    match chars.peek() {
+        Some('n') => expect(chars, "null").map(|()| Value::Null),
+        Some('t') => expect(chars, "true").map(|()| Value::Bool(true)),
+        Some('f') => expect(chars, "false").map(|()| Value::Bool(false)),
+        Some('"') => parse_string(chars).map(Value::String),
+        Some('[') => {
+            chars.next();
+
+            let mut values = Vec::new();
+
+            skip_whitespace(chars);
+
+            if chars.next_if_eq(&']').is_none() {
+                loop {
+                    values.push(parse_value(chars)?);
+                    skip_whitespace(chars);
+
+                    match chars.next() {
+                        Some(',') => {}
+                        Some(']') => break,
+                        _ => return Err("expected ',' or ']' in array".to_owned()),
+                    }
+                }
+            }
+
+            Ok(Value::Array(values))
+        }
+        Some('{') => {
+            chars.next();
+
+            let mut fields = Vec::new();
+
+            skip_whitespace(chars);
+
+            if chars.next_if_eq(&'}').is_none() {
+                loop {
+                    skip_whitespace(chars);
+
+                    let key = parse_string(chars)?;
+
+                    skip_whitespace(chars);
+                    expect(chars, ":")?;
+                    fields.push((key, parse_value(chars)?));
+                    skip_whitespace(chars);
+
+                    match chars.next() {
+                        Some(',') => {}
+                        Some('}') => break,
+                        _ => return Err("expected ',' or '}' in object".to_owned()),
+                    }
+                }
+            }
+
+            Ok(Value::Object(fields))
+        }
+        Some(c) if *c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+
+            while let Some(c) =
+                chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+            {
+                number.push(c);
+            }
+
+            number
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| format!("invalid number '{}'", number))
+        }
+        Some(c) => Err(format!("unexpected '{}'", c)),
+        None => Err("unexpected end of input".to_owned()),
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    expect(chars, "\"")?;
+
+    let mut string = String::new();
+
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(string),
+            Some('\\') => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('r') => string.push('\r'),
+                Some('t') => string.push('\t'),
+                Some('b') => string.push('\u{8}'),
+                Some('f') => string.push('\u{c}'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let c = u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER);
+
+                    string.push(c);
+                }
+                Some(c) => string.push(c),
+                None => return Err("unterminated string".to_owned()),
+            },
+            Some(c) => string.push(c),
+            None => return Err("unterminated string".to_owned()),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+macro_rules! from_number {
+    ($($type:ty),*) => {
+        $(impl From<$type> for Value {
+            fn from(value: $type) -> Self {
+                Self::Number(value as f64)
+            }
+        })*
+    };
+}
+
+from_number!(u8, u16, u32, u64, usize, f64);
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Self {
+        Self::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Number(value) if value.is_finite() => write!(f, "{}", value),
+            Self::Number(_) => write!(f, "null"),
+            Self::String(value) => write_string(f, value),
+            Self::Array(values) => {
+                write!(f, "[")?;
+
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write!(f, "{}", value)?;
+                }
+
+                write!(f, "]")
+            }
+            Self::Object(fields) => {
+                write!(f, "{{")?;
+
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if u32::from(c) < 0x20 => write!(f, "\\u{:04x}", u32::from(c))?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_nested_values() {
+        let value = Value::object()
+            .with("size", 246usize)
+            .with("name", "pong")
+            .with("tags", vec!["a", "b"])
+            .with("missing", None::<u8>);
+
+        assert_eq!(
+            value.to_string(),
+            r#"{"size":246,"name":"pong","tags":["a","b"],"missing":null}"#
+        );
+    }
+
+    #[test]
+    fn reads_what_it_writes() {
+        let text = r#"{"name":"say \"hi\"\n","tags":[1.5,-2,true,null],"empty":{}}"#;
+        let value = Value::parse(text).unwrap();
+
+        assert_eq!(
+            value.get("name").and_then(Value::as_str),
+            Some("say \"hi\"\n")
+        );
+        assert_eq!(value.to_string(), text);
+        assert!(Value::parse(r#"{"a":1,}"#).is_err());
+        assert!(Value::parse("[1] 2").is_err());
+    }
+
+    #[test]
+    fn escapes_strings() {
+        let value = Value::from("say \"hi\"\n\u{1}");
+
+        assert_eq!(value.to_string(), r#""say \"hi\"\n\u0001""#);
+    }
+}
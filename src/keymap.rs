@@ -0,0 +1,206 @@
+use crate::storage::Storage;
+
+use log::warn;
+use winit::keyboard::KeyCode;
+
+const FILE_NAME: &str = "keymaps.txt";
+
+// Keyboard keys for each position of the 4x4 keypad grid, read row by row.
+// The platform decides which hex key sits at each position, so a keymap is
+// only a physical layout and works for every platform.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Keymap {
+    pub name: String,
+    keys: [KeyCode; 16],
+}
+
+impl Keymap {
+    fn new(name: &str, rows: [[KeyCode; 4]; 4]) -> Self {
+        Self {
+            name: name.to_owned(),
+            keys: rows.as_flattened().try_into().unwrap(),
+        }
+    }
+
+    // The left-hand block under the number row, which most ROMs expect
+    pub fn qwerty() -> Self {
+        use KeyCode::*;
+
+        Self::new(
+            "qwerty",
+            [
+                [Digit1, Digit2, Digit3, Digit4],
+                [KeyQ, KeyW, KeyE, KeyR],
+                [KeyA, KeyS, KeyD, KeyF],
+                [KeyZ, KeyX, KeyC, KeyV],
+            ],
+        )
+    }
+
+    // The same block for the right hand, leaving the left one free
+    pub fn right_hand() -> Self {
+        use KeyCode::*;
+
+        Self::new(
+            "right-hand",
+            [
+                [Digit7, Digit8, Digit9, Digit0],
+                [KeyU, KeyI, KeyO, KeyP],
+                [KeyJ, KeyK, KeyL, Semicolon],
+                [KeyM, Comma, Period, Slash],
+            ],
+        )
+    }
+
+    // The numeric keypad, with its operator keys as the last column
+    pub fn numpad() -> Self {
+        use KeyCode::*;
+
+        Self::new(
+            "numpad",
+            [
+                [Numpad7, Numpad8, Numpad9, NumpadDivide],
+                [Numpad4, Numpad5, Numpad6, NumpadMultiply],
+                [Numpad1, Numpad2, Numpad3, NumpadSubtract],
+                [Numpad0, NumpadDecimal, NumpadEnter, NumpadAdd],
+            ],
+        )
+    }
+
+    // A line of a keymaps file: a name followed by 16 key names
+    //
+    //     arrows 1 2 3 4 q w e r a up d f left down right v
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or("missing name")?.to_owned();
+
+        let keys: Vec<KeyCode> = words
+            .map(|word| parse_key_code(word).ok_or(format!("unknown key '{}'", word)))
+            .collect::<Result<_, _>>()?;
+
+        let keys = keys
+            .try_into()
+            .map_err(|keys: Vec<KeyCode>| format!("expected 16 keys, got {}", keys.len()))?;
+
+        Ok(Self { name, keys })
+    }
+
+    // Grid position of a keyboard key
+    pub fn position(&self, code: KeyCode) -> Option<usize> {
+        self.keys.iter().position(|&key| key == code)
+    }
+}
+
+// The built in keymaps followed by any in `keymaps.txt` in the storage
+// directory, where a keymap with a built in name replaces it
+pub fn load(storage: &Storage) -> Vec<Keymap> {
+    let mut keymaps = vec![Keymap::qwerty(), Keymap::right_hand(), Keymap::numpad()];
+    let text = storage.read_to_string(FILE_NAME).unwrap_or_default();
+
+    let lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+    for line in lines {
+        match Keymap::parse(line) {
+            Ok(keymap) => {
+                keymaps.retain(|existing| existing.name != keymap.name);
+                keymaps.push(keymap);
+            }
+            Err(err) => warn!(
+                "Skipping keymap in '{}': {}",
+                storage.locate(FILE_NAME),
+                err
+            ),
+        }
+    }
+
+    keymaps
+}
+
+// Letters, digits and a few named keys, as written in a keymaps file
+fn parse_key_code(name: &str) -> Option<KeyCode> {
+    let name = name.to_ascii_lowercase();
+
+    let code = match name.as_str() {
+        "a" => KeyCode::KeyA,
+        "b" => KeyCode::KeyB,
+        "c" => KeyCode::KeyC,
+        "d" => KeyCode::KeyD,
+        "e" => KeyCode::KeyE,
+        "f" => KeyCode::KeyF,
+        "g" => KeyCode::KeyG,
+        "h" => KeyCode::KeyH,
+        "i" => KeyCode::KeyI,
+        "j" => KeyCode::KeyJ,
+        "k" => KeyCode::KeyK,
+        "l" => KeyCode::KeyL,
+        "m" => KeyCode::KeyM,
+        "n" => KeyCode::KeyN,
+        "o" => KeyCode::KeyO,
+        "p" => KeyCode::KeyP,
+        "q" => KeyCode::KeyQ,
+        "r" => KeyCode::KeyR,
+        "s" => KeyCode::KeyS,
+        "t" => KeyCode::KeyT,
+        "u" => KeyCode::KeyU,
+        "v" => KeyCode::KeyV,
+        "w" => KeyCode::KeyW,
+        "x" => KeyCode::KeyX,
+        "y" => KeyCode::KeyY,
+        "z" => KeyCode::KeyZ,
+        "0" => KeyCode::Digit0,
+        "1" => KeyCode::Digit1,
+        "2" => KeyCode::Digit2,
+        "3" => KeyCode::Digit3,
+        "4" => KeyCode::Digit4,
+        "5" => KeyCode::Digit5,
+        "6" => KeyCode::Digit6,
+        "7" => KeyCode::Digit7,
+        "8" => KeyCode::Digit8,
+        "9" => KeyCode::Digit9,
+        "numpad0" => KeyCode::Numpad0,
+        "numpad1" => KeyCode::Numpad1,
+        "numpad2" => KeyCode::Numpad2,
+        "numpad3" => KeyCode::Numpad3,
+        "numpad4" => KeyCode::Numpad4,
+        "numpad5" => KeyCode::Numpad5,
+        "numpad6" => KeyCode::Numpad6,
+        "numpad7" => KeyCode::Numpad7,
+        "numpad8" => KeyCode::Numpad8,
+        "numpad9" => KeyCode::Numpad9,
+        "up" => KeyCode::ArrowUp,
+        "down" => KeyCode::ArrowDown,
+        "left" => KeyCode::ArrowLeft,
+        "right" => KeyCode::ArrowRight,
+        "space" => KeyCode::Space,
+        "enter" => KeyCode::Enter,
+        "shift" => KeyCode::ShiftLeft,
+        "ctrl" => KeyCode::ControlLeft,
+        "comma" => KeyCode::Comma,
+        "period" => KeyCode::Period,
+        "slash" => KeyCode::Slash,
+        "semicolon" => KeyCode::Semicolon,
+        _ => return None,
+    };
+
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_keymap_lines() {
+        let keymap = Keymap::parse("arrows 1 2 3 4 q w e r a up d f left down right v").unwrap();
+
+        assert_eq!(keymap.name, "arrows");
+        assert_eq!(keymap.position(KeyCode::ArrowUp), Some(9));
+        assert_eq!(keymap.position(KeyCode::KeyS), None);
+
+        assert!(Keymap::parse("short 1 2 3").is_err());
+        assert!(Keymap::parse("odd 1 2 3 4 q w e r a s d f z x c ?").is_err());
+    }
+}
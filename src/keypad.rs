@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+
+// Frames a key has to stay still before the machine sees the change, about
+// the 30ms or so the VIP's keypad routine waited for contacts to settle
+pub const DEBOUNCE_FRAMES: u64 = 2;
+
+// The physical state of the keypad, which the machine only sees once a key
+// has settled. Presses shorter than the settle time are missed, and quick
+// release/press bounces are merged into one press.
+pub struct Keypad {
+    raw: [bool; 16],
+    since: [u64; 16],
+    // Changes held back to simulate a sluggish keypad, with the frame they
+    // reach the keypad on
+    delayed: VecDeque<(u64, u8, bool)>,
+}
+
+impl Keypad {
+    pub fn new(keys: [bool; 16]) -> Self {
+        Self {
+            raw: keys,
+            since: [0; 16],
+            delayed: VecDeque::new(),
+        }
+    }
+
+    pub fn delay(&mut self, due: u64, key: u8, pressed: bool) {
+        self.delayed.push_back((due, key, pressed));
+    }
+
+    // Delayed changes that have reached the keypad by `frame`, in order
+    pub fn due(&mut self, frame: u64) -> Vec<(u8, bool)> {
+        let mut due = Vec::new();
+
+        while let Some(&(at, key, pressed)) = self.delayed.front() {
+            if at > frame {
+                break;
+            }
+
+            self.delayed.pop_front();
+            due.push((key, pressed));
+        }
+
+        due
+    }
+
+    pub fn set(&mut self, key: u8, pressed: bool, frame: u64) {
+        let key = usize::from(key);
+
+        if self.raw[key] != pressed {
+            self.raw[key] = pressed;
+            self.since[key] = frame;
+        }
+    }
+
+    // Keys that differ from what the machine sees and have been stable for
+    // at least `frames`
+    pub fn settled(&self, visible: &[bool; 16], frame: u64, frames: u64) -> Vec<(u8, bool)> {
+        (0..16)
+            .filter(|&key| self.raw[key] != visible[key] && frame - self.since[key] >= frames)
+            .map(|key| (key as u8, self.raw[key]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn misses_short_presses() {
+        let mut keypad = Keypad::new([false; 16]);
+        keypad.set(5, true, 10);
+        keypad.set(5, false, 11);
+
+        assert!(keypad.settled(&[false; 16], 20, DEBOUNCE_FRAMES).is_empty());
+    }
+
+    #[test]
+    fn releases_delayed_changes_in_order() {
+        let mut keypad = Keypad::new([false; 16]);
+        keypad.delay(12, 5, true);
+        keypad.delay(12, 5, false);
+        keypad.delay(13, 6, true);
+
+        assert!(keypad.due(11).is_empty());
+        assert_eq!(keypad.due(12), [(5, true), (5, false)]);
+        assert_eq!(keypad.due(20), [(6, true)]);
+    }
+
+    #[test]
+    fn reports_presses_once_settled() {
+        let mut keypad = Keypad::new([false; 16]);
+        keypad.set(5, true, 10);
+
+        assert!(keypad.settled(&[false; 16], 11, DEBOUNCE_FRAMES).is_empty());
+        assert_eq!(
+            keypad.settled(&[false; 16], 12, DEBOUNCE_FRAMES),
+            [(5, true)]
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use crate::hash::{hex, sha1};
+use crate::json::Value;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+
+// ROMs whose platform and quirks are known, by SHA-1, so they run right
+// without any flags:
+//
+//     "<sha1>": { "title": "Pong", "platform": "chip8", "quirks": { "debounce": true } }
+//
+// Platforms are named as for `--platform`.
+const DATABASE: &str = include_str!("known.json");
+
+pub struct Known {
+    pub title: String,
+    pub platform: Platform,
+    pub quirks: Vec<(String, bool)>,
+}
+
+pub fn lookup(rom: &[u8]) -> Option<Known> {
+    find(DATABASE, &hex(&sha1(rom)))
+}
+
+fn find(database: &str, key: &str) -> Option<Known> {
+    let database =
+        Value::parse(database).unwrap_or_else(|err| panic!("Invalid ROM database: {}", err));
+    let entry = database.get(key)?;
+
+    let quirks = match entry.get("quirks") {
+        Some(Value::Object(fields)) => fields
+            .iter()
+            .filter(|(name, _)| Quirks::NAMES.contains(&name.as_str()))
+            .filter_map(|(name, enabled)| Some((name.clone(), enabled.as_bool()?)))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Some(Known {
+        title: entry
+            .get("title")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned(),
+        platform: entry
+            .get("platform")
+            .and_then(Value::as_str)
+            .map_or(Platform::Chip8, Platform::parse),
+        quirks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_roms_by_hash() {
+        let known = lookup(&crate::splash::ROM).unwrap();
+
+        assert_eq!(known.title, "Keypad test");
+        assert!(known.platform == Platform::Chip8);
+        assert!(lookup(&[0x12, 0x00]).is_none());
+
+        let database =
+            r#"{"abc": {"platform": "xo-chip", "quirks": {"debounce": true, "made_up": true}}}"#;
+        let known = find(database, "abc").unwrap();
+
+        assert!(known.platform == Platform::XoChip);
+        assert_eq!(known.quirks, [("debounce".to_owned(), true)]);
+    }
+}
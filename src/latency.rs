@@ -0,0 +1,167 @@
+use std::time::{Duration, Instant};
+
+// Run by `chip8 latency`, which lights the whole display itself while a key
+// is held, so the ROM only has to stay out of the way
+#[rustfmt::skip]
+pub const ROM: [u8; 2] = [
+    0x12, 0x00, // 200  JP 200
+];
+
+// Where the time went between the window seeing a key and the frame
+// showing it being handed to the compositor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Sample {
+    // From the window to the device thread reading it
+    delivery: Duration,
+    // Until the next frame was due
+    wait: Duration,
+    // Running the frame's instructions
+    batch: Duration,
+    // Turning the display into pixels and uploading them
+    render: Duration,
+    // Presenting the frame, which waits for vsync
+    present: Duration,
+}
+
+impl Sample {
+    fn stages(&self) -> [(&'static str, Duration); 5] {
+        [
+            ("delivery", self.delivery),
+            ("wait", self.wait),
+            ("batch", self.batch),
+            ("render", self.render),
+            ("present", self.present),
+        ]
+    }
+
+    fn total(&self) -> Duration {
+        self.stages().iter().map(|&(_, time)| time).sum()
+    }
+}
+
+fn describe(stages: &[(&str, Duration)], total: Duration) -> String {
+    let parts: Vec<String> = stages
+        .iter()
+        .map(|(stage, time)| format!("{} {:.1}ms", stage, time.as_secs_f64() * 1000.0))
+        .collect();
+
+    format!(
+        "{}, total {:.1}ms",
+        parts.join(", "),
+        total.as_secs_f64() * 1000.0
+    )
+}
+
+// Follows keys from the window to the screen for `chip8 latency`. The
+// window stamps each key as it sees it, and the device notes when it reads
+// the key, then times the next frame it runs and draws.
+#[derive(Default)]
+pub struct LatencyTest {
+    // When the window saw the key waiting to be shown, and when the device
+    // read it
+    pending: Option<(Instant, Instant)>,
+    samples: Vec<Sample>,
+}
+
+impl LatencyTest {
+    pub fn key(&mut self, seen: Instant) {
+        // a key arriving while another is on its way is shown in the same
+        // frame, so only the first is timed
+        if self.pending.is_none() {
+            self.pending = Some((seen, Instant::now()));
+        }
+    }
+
+    // A frame ran from `start` to `end` and was drawn, returning how long
+    // the key waiting for it took to show
+    pub fn frame(
+        &mut self,
+        start: Instant,
+        end: Instant,
+        render: Duration,
+        present: Duration,
+    ) -> Option<String> {
+        let (seen, read) = self.pending.filter(|&(_, read)| read <= start)?;
+        self.pending = None;
+
+        let sample = Sample {
+            delivery: read - seen,
+            wait: start - read,
+            batch: end - start,
+            render,
+            present,
+        };
+        self.samples.push(sample);
+
+        Some(describe(&sample.stages(), sample.total()))
+    }
+
+    // The average of each stage over every key, and the slowest key
+    pub fn summary(&self) -> Option<String> {
+        let count = self.samples.len() as u32;
+        let slowest = self.samples.iter().map(Sample::total).max()?;
+
+        let average = |stage: fn(&Sample) -> Duration| {
+            self.samples.iter().map(stage).sum::<Duration>() / count
+        };
+        let mean = Sample {
+            delivery: average(|sample| sample.delivery),
+            wait: average(|sample| sample.wait),
+            batch: average(|sample| sample.batch),
+            render: average(|sample| sample.render),
+            present: average(|sample| sample.present),
+        };
+
+        Some(format!(
+            "Average of {} keys: {}, slowest {:.1}ms",
+            count,
+            describe(&mean.stages(), mean.total()),
+            slowest.as_secs_f64() * 1000.0
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_keys_through_the_next_frame() {
+        let mut test = LatencyTest::default();
+        let seen = Instant::now();
+        test.key(seen);
+
+        let (_, read) = test.pending.unwrap();
+        let start = read + Duration::from_millis(4);
+        let end = start + Duration::from_millis(1);
+
+        // frames that started before the key was read don't show it
+        assert!(test
+            .frame(
+                read - Duration::from_millis(1),
+                read,
+                Duration::ZERO,
+                Duration::ZERO
+            )
+            .is_none());
+        assert!(test
+            .frame(
+                start,
+                end,
+                Duration::from_millis(2),
+                Duration::from_millis(8)
+            )
+            .is_some());
+        assert!(test
+            .frame(start, end, Duration::ZERO, Duration::ZERO)
+            .is_none());
+
+        let sample = test.samples[0];
+        assert_eq!(sample.wait, Duration::from_millis(4));
+        assert_eq!(sample.total(), read - seen + Duration::from_millis(15));
+        assert!(test
+            .summary()
+            .unwrap()
+            .starts_with("Average of 1 keys: delivery"));
+    }
+}
@@ -0,0 +1,55 @@
+// Emulator core, shared by the `chip8` binary, the examples and anyone
+// embedding the emulator in their own frontend
+pub mod app;
+mod banks;
+pub mod builder;
+pub mod chip8x;
+pub mod clipboard;
+pub mod clock;
+mod coalesce;
+pub mod compare;
+mod conformance;
+pub mod device;
+pub mod disasm;
+pub mod display;
+pub mod editor;
+pub mod farm;
+pub mod font;
+pub mod framebuffer;
+mod hash;
+pub mod input;
+pub mod inspect;
+mod instructions;
+mod ips;
+pub mod json;
+mod keymap;
+mod keypad;
+mod known;
+pub mod latency;
+pub mod megachip;
+pub mod memory;
+#[cfg(feature = "metrics")]
+mod metrics;
+pub mod octo;
+pub mod palette;
+mod placement;
+pub mod platform;
+#[cfg(feature = "plugins")]
+pub mod plugins;
+mod png;
+pub mod profiles;
+pub mod quirks;
+mod recorder;
+pub mod rng;
+pub mod screen;
+mod slots;
+pub mod splash;
+pub mod state;
+mod stats;
+pub mod storage;
+mod stream;
+mod timing;
+pub mod trace;
+pub mod tracedb;
+pub mod tutorial;
+mod wav;
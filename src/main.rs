@@ -1,39 +1,353 @@
-mod app;
-mod device;
-mod screen;
+mod assoc;
+mod console;
+mod options;
+mod watch;
+mod wizard;
 
+use chip8::json::{self, Value};
+use chip8::platform::Platform;
+use chip8::tracedb::TraceDb;
+use chip8::{app, compare, device, farm, inspect, latency, screen, splash, tutorial};
 use log::{info, LevelFilter};
+use options::{Command, Options};
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
+#[cfg(feature = "plugins")]
+use std::sync::Arc;
 use std::thread;
 use winit::event_loop::EventLoop;
 
-const WINDOW_SCALE: u32 = 6;
+// Small enough to sit in a corner of the screen
+const OVERLAY_SCALE: u32 = 3;
 
 fn main() {
     env_logger::builder()
         .filter_module("chip8", LevelFilter::Debug)
         .init();
 
-    let path = env::args().last().expect("Must provide ROM path");
+    let mut options = Options::parse(env::args_os().skip(1));
 
+    if options.config.storage.is_portable() {
+        info!(
+            "Portable mode, storing data in '{}'",
+            options.config.storage.locate("")
+        );
+    }
+
+    if let Command::InstallAssoc = options.command {
+        assoc::install();
+        return;
+    }
+
+    if let Command::Tutorial = options.command {
+        options.config.tutorial = true;
+
+        let rom = match options.path.clone() {
+            Some(path) => Rom::File(path),
+            None => Rom::Builtin("Tutorial".to_owned(), &tutorial::ROM),
+        };
+
+        run_window(options, rom);
+        return;
+    }
+
+    if let Command::Latency = options.command {
+        options.config.latency_test = true;
+        options.app.latency_test = true;
+
+        let name = "Latency test - press any key".to_owned();
+        run_window(options, Rom::Builtin(name, &latency::ROM));
+        return;
+    }
+
+    let path = match options.path.clone() {
+        Some(path) => path,
+        // without a ROM, open the window on the built-in splash instead
+        None if matches!(options.command, Command::Run) && options.frames.is_none() => {
+            info!("No ROM given, showing the keypad tester");
+
+            let name = "Keypad test - run chip8 <rom.ch8> to play".to_owned();
+            run_window(options, Rom::Builtin(name, &splash::ROM));
+            return;
+        }
+        None => panic!("Must provide ROM path"),
+    };
+
+    if let Command::Query = options.command {
+        run_query(&options, &path);
+        return;
+    }
+
+    // the device would switch platform itself on loading the ROM, but the
+    // window needs to know before then
+    if !options.platform_given {
+        if let Some(platform) = fs::read(&path).ok().and_then(|rom| Platform::detect(&rom)) {
+            if platform != options.config.platform {
+                info!("Running it as {}", platform.name());
+            }
+
+            options.config.platform = platform;
+        }
+    }
+
+    if let Command::Info = options.command {
+        let inspection = inspect::inspect(&fs::read(&path).unwrap());
+
+        if options.json {
+            println!("{}", inspection.to_json());
+        } else {
+            inspection.print();
+        }
+
+        return;
+    }
+
+    if let Command::Compare = options.command {
+        run_compare(&options, &path);
+        return;
+    }
+
+    if let Command::Farm = options.command {
+        run_farm(&options, &path);
+        return;
+    }
+
+    if let Command::Wizard = options.command {
+        wizard::run(&path, &options.config, options.frames.unwrap_or(600));
+        return;
+    }
+
+    if let Some(frames) = options.frames {
+        run_headless(&options, &path, frames);
+        return;
+    }
+
+    run_window(options, Rom::File(path));
+}
+
+// What the window runs
+enum Rom {
+    File(PathBuf),
+    // Built into the binary, with the name to show in the title
+    Builtin(String, &'static [u8]),
+}
+
+fn run_window(options: Options, rom: Rom) {
+    let platform = options.config.platform;
+    let mut settings = options.app;
+    let palette = options.config.palette;
+    let stdin_control = options.stdin_control;
+
+    if settings.borderless && settings.scale == app::Scale::Auto {
+        settings.scale = app::Scale::Fixed(OVERLAY_SCALE);
+    }
+
+    settings.next_rom = matches!(options.command, Command::Watch);
     let (sender, receiver) = channel();
 
+    let event_loop = EventLoop::<app::AppEvent>::with_user_event()
+        .build()
+        .unwrap();
+    let proxy = event_loop.create_proxy();
+
     thread::spawn(move || {
         let event = receiver.recv().unwrap();
 
         info!("Booting device");
 
-        let mut device = match event {
-            device::Event::On(window) => device::Device::new(window),
+        let mut screen = match event {
+            device::Event::On(window, scale) => screen::Screen::new(window, scale, palette),
             _ => panic!("First event must be `On`"),
         };
 
-        device.load(&path);
-        device.run(receiver);
+        if let (Command::Watch, Rom::File(path)) = (&options.command, &rom) {
+            watch::run(path, &options.config, &mut screen, &receiver);
+            return;
+        }
+
+        #[cfg(feature = "plugins")]
+        let plugins = Arc::new(chip8::plugins::Plugins::load(Path::new(
+            &options.config.storage.locate("plugins"),
+        )));
+
+        let mut device = device::Device::new(options.config);
+
+        #[cfg(feature = "plugins")]
+        {
+            if plugins.has_filters() {
+                let plugins = plugins.clone();
+                screen.set_filter(move |frame, width, height| {
+                    plugins.filter_frame(frame, width, height)
+                });
+            }
+            if plugins.has_transforms() {
+                device.on_key(move |key, pressed| plugins.transform_key(key, pressed));
+            }
+        }
+
+        match rom {
+            Rom::File(path) => device.load(&path),
+            Rom::Builtin(name, bytes) => {
+                device.load_rom(bytes);
+                screen.set_name(Some(name));
+            }
+        }
+
+        // the ROM exited, or stdin told it to quit
+        if let device::Stop::Exit | device::Stop::Off = device.run(&mut screen, &receiver) {
+            // let recordings finish writing before the process goes away
+            drop(device);
+            proxy.send_event(app::AppEvent::Close).ok();
+        }
     });
 
-    let event_loop = EventLoop::new().unwrap();
-    let mut app = app::App::new(WINDOW_SCALE, sender);
+    console::spawn(sender.clone(), stdin_control);
+
+    let mut app = app::App::new(platform, platform.resolution(), settings, sender);
     event_loop.run_app(&mut app).unwrap();
 }
+
+fn run_compare(options: &Options, path: &Path) {
+    let mut config = options.config.clone();
+
+    for (name, enabled) in &options.compare_quirks {
+        config.quirks.set(name, *enabled);
+    }
+
+    let video = compare::compare(
+        path,
+        options.config.clone(),
+        config,
+        options.frames.unwrap_or(600),
+        options.layout,
+    );
+
+    let output = options
+        .output
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("compare.png"));
+
+    fs::write(&output, video).unwrap();
+
+    info!("Wrote comparison to '{}'", output.display());
+}
+
+fn run_query(options: &Options, path: &Path) {
+    let db = TraceDb::open(path)
+        .unwrap_or_else(|err| panic!("Failed to read trace '{}': {}", path.display(), err));
+
+    if let Some(address) = options.last_write {
+        let before = options.before.unwrap_or(db.frames());
+
+        match db.last_write(address, before) {
+            Some(write) => println!(
+                "{:03X} was last written before frame {} in frame {}, with {:02X} by the instruction at {:03X}",
+                address, before, write.frame, write.value, write.pc
+            ),
+            None => println!("{:03X} wasn't written before frame {}", address, before),
+        }
+    } else if let Some(frame) = options.at {
+        let Some(device) = db.machine_at(frame) else {
+            println!(
+                "Frame {} isn't in the trace, which has {}",
+                frame,
+                db.frames()
+            );
+            return;
+        };
+        let machine = device.octo_state();
+        let registers: Vec<String> = machine
+            .registers
+            .iter()
+            .enumerate()
+            .map(|(register, value)| format!("V{:X}={:02X}", register, value))
+            .collect();
+
+        println!(
+            "Frame {}: PC={:03X} I={:03X} DT={:02X} ST={:02X}",
+            frame, machine.pc, machine.i, machine.dt, machine.st
+        );
+        println!("{}", registers.join(" "));
+        print!("{}", device.display().ascii());
+    } else {
+        println!("{} frames with {} keyframes", db.frames(), db.keyframes());
+    }
+}
+
+fn run_farm(options: &Options, dir: &Path) {
+    let report = options
+        .output
+        .clone()
+        .unwrap_or_else(|| dir.join("farm-report.tsv"));
+
+    let previous: Vec<farm::Entry> = fs::read_to_string(&report)
+        .map(|text| text.lines().filter_map(farm::Entry::parse_line).collect())
+        .unwrap_or_default();
+
+    let entries = farm::farm(dir, &options.config, options.frames.unwrap_or(600));
+
+    for entry in &entries {
+        println!("{}", entry.to_line());
+    }
+
+    let lines: Vec<String> = entries.iter().map(farm::Entry::to_line).collect();
+    fs::write(&report, lines.join("\n") + "\n").unwrap();
+
+    info!(
+        "Wrote report for {} ROMs to '{}'",
+        entries.len(),
+        report.display()
+    );
+
+    let changes = farm::regressions(&previous, &entries);
+
+    for change in &changes {
+        println!("CHANGED {}", change);
+    }
+
+    if !changes.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn run_headless(options: &Options, path: &Path, frames: u32) {
+    let mut device = device::Device::new(options.config.clone());
+    device.load(path);
+
+    for _ in 0..frames {
+        device.run_frame();
+
+        if device.has_exited() {
+            break;
+        }
+    }
+
+    device.print_report();
+
+    if options.json {
+        let display = device.display();
+        let resolution = display.resolution();
+        let rows: Vec<String> = display.ascii().lines().map(str::to_owned).collect();
+
+        let output = Value::object()
+            .with("schema", json::SCHEMA_VERSION)
+            .with("command", "run")
+            .with("rom", path.to_string_lossy().into_owned())
+            .with("frames", frames)
+            .with("halted", device.is_halted())
+            .with("pc", device.pc())
+            .with(
+                "display",
+                Value::object()
+                    .with("width", resolution.width)
+                    .with("height", resolution.height)
+                    .with("rows", rows),
+            );
+
+        println!("{}", output);
+    } else if options.print_frame {
+        print!("{}", device.display().ascii());
+    }
+}
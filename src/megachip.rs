@@ -0,0 +1,165 @@
+// How the colours of a Mega-CHIP sprite are combined with what's already
+// on screen, picked with `080N`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Blend {
+    // Each colour's own alpha
+    Normal,
+    // A fixed share of the sprite, out of 4
+    Quarters(u16),
+    Add,
+    Multiply,
+}
+
+impl Blend {
+    pub fn from_mode(mode: u8) -> Self {
+        match mode {
+            1 => Self::Quarters(1),
+            2 => Self::Quarters(2),
+            3 => Self::Quarters(3),
+            4 => Self::Add,
+            5 => Self::Multiply,
+            _ => Self::Normal,
+        }
+    }
+
+    fn mix(self, under: [u8; 3], [r, g, b, alpha]: [u8; 4]) -> [u8; 3] {
+        let over = [r, g, b];
+        let lerp = |under: u8, over: u8, share: u16, of: u16| {
+            ((u16::from(under) * (of - share) + u16::from(over) * share) / of) as u8
+        };
+
+        match self {
+            Self::Normal => std::array::from_fn(|c| lerp(under[c], over[c], alpha.into(), 255)),
+            Self::Quarters(share) => std::array::from_fn(|c| lerp(under[c], over[c], share, 4)),
+            Self::Add => std::array::from_fn(|c| under[c].saturating_add(over[c])),
+            Self::Multiply => {
+                std::array::from_fn(|c| (u16::from(under[c]) * u16::from(over[c]) / 255) as u8)
+            }
+        }
+    }
+}
+
+// The true colour side of Mega-CHIP's display. The display's pixels hold
+// an index into a palette the ROM loads with `02NN`, and sprites are
+// blended into a frame here as they're drawn. That frame is only shown
+// once the ROM clears the screen with `00E0`, so whole scenes appear at
+// once rather than being seen half drawn.
+#[derive(Clone)]
+pub struct Colors {
+    // RGBA, index 0 being the transparent background
+    palette: Vec<[u8; 4]>,
+    blend: Blend,
+    // Brightness of the whole screen, for fades
+    alpha: u8,
+    // Drawing over a pixel of this colour sets VF, once `09NN` picks one
+    collision: Option<u8>,
+    // Row by row
+    drawing: Vec<[u8; 3]>,
+    shown: Vec<[u8; 3]>,
+}
+
+impl Colors {
+    pub fn new(pixels: usize) -> Self {
+        Self {
+            // font digits are drawn in colour 255
+            palette: (0..=255u8)
+                .map(|index| if index == 0 { [0; 4] } else { [0xFF; 4] })
+                .collect(),
+            blend: Blend::Normal,
+            alpha: 0xFF,
+            collision: None,
+            drawing: vec![[0; 3]; pixels],
+            shown: vec![[0; 3]; pixels],
+        }
+    }
+
+    // Replace colours from index 1 on with `ARGB` entries, 4 bytes each
+    pub fn load_palette(&mut self, colors: &[u8]) {
+        for (entry, argb) in self.palette[1..].iter_mut().zip(colors.chunks_exact(4)) {
+            *entry = [argb[1], argb[2], argb[3], argb[0]];
+        }
+    }
+
+    pub fn set_blend(&mut self, blend: Blend) {
+        self.blend = blend;
+    }
+
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.alpha = alpha;
+    }
+
+    pub fn set_collision(&mut self, index: u8) {
+        self.collision = Some(index);
+    }
+
+    pub fn collides_with(&self, index: u8) -> bool {
+        self.collision == Some(index)
+    }
+
+    // Blend a palette colour into the pixel being drawn
+    pub fn paint(&mut self, pixel: usize, index: u8) {
+        let rgb = &mut self.drawing[pixel];
+        *rgb = self.blend.mix(*rgb, self.palette[usize::from(index)]);
+    }
+
+    // Show what was drawn and start a blank frame
+    pub fn present(&mut self) {
+        std::mem::swap(&mut self.shown, &mut self.drawing);
+        self.drawing.fill([0; 3]);
+    }
+
+    pub fn drawing_mut(&mut self) -> &mut [[u8; 3]] {
+        &mut self.drawing
+    }
+
+    // The frame on screen as RGB, dimmed by the screen alpha
+    pub fn shown(&self) -> impl Iterator<Item = [u8; 3]> + '_ {
+        self.shown
+            .iter()
+            .map(|rgb| rgb.map(|c| (u16::from(c) * u16::from(self.alpha) / 255) as u8))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blends_sprite_colours() {
+        let under = [0x40, 0x80, 0xFF];
+
+        assert_eq!(
+            Blend::Normal.mix(under, [0xFF, 0x00, 0x00, 0xFF]),
+            [0xFF, 0, 0]
+        );
+        assert_eq!(
+            Blend::from_mode(2).mix(under, [0xC0, 0x00, 0x01, 0x00]),
+            [0x80, 0x40, 0x80]
+        );
+        assert_eq!(
+            Blend::Add.mix(under, [0x10, 0x90, 0x10, 0]),
+            [0x50, 0xFF, 0xFF]
+        );
+        assert_eq!(
+            Blend::Multiply.mix(under, [0xFF, 0x80, 0x00, 0]),
+            [0x40, 0x40, 0x00]
+        );
+    }
+
+    #[test]
+    fn shows_frames_once_presented() {
+        let mut colors = Colors::new(2);
+        colors.load_palette(&[0xFF, 0x11, 0x22, 0x33]);
+        colors.paint(1, 1);
+
+        assert!(colors.shown().all(|rgb| rgb == [0, 0, 0]));
+
+        colors.present();
+        colors.set_alpha(0x80);
+
+        assert_eq!(
+            colors.shown().collect::<Vec<_>>(),
+            [[0, 0, 0], [0x08, 0x11, 0x19]]
+        );
+    }
+}
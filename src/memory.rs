@@ -0,0 +1,97 @@
+use crate::platform::Platform;
+use std::ops::Range;
+
+// The small hex digits for FX29 are at 0, and the large ones for FX30 just
+// after them
+pub const BIG_FONT_ADDRESS: usize = 0x50;
+// Where the interpreter's own area ends and programs begin
+pub const PROGRAM_ADDRESS: usize = 0x200;
+
+// Smallest and largest memory `--memory-size` accepts. 1K leaves programs
+// 511 bytes, enough for small exercises.
+pub const MIN_SIZE: usize = 0x400;
+pub const MAX_SIZE: usize = 0x1000000;
+
+// How memory is laid out: the interpreter's area holding the fonts, the
+// program area ROMs are loaded into, and a work area at the top that ROMs
+// can't be loaded over
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MemoryMap {
+    pub size: usize,
+    // Where the work area starts
+    pub work: usize,
+}
+
+impl MemoryMap {
+    // The platform's own amount of memory unless `size` is given. The
+    // VIP-era platforms keep the last byte as the work area, XO-CHIP and
+    // Mega-CHIP let ROMs fill memory.
+    pub fn new(platform: Platform, size: Option<usize>) -> Self {
+        let size = size.unwrap_or(platform.memory_size());
+
+        if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+            panic!(
+                "Memory size {:#x} is outside {:#x} to {:#x}",
+                size, MIN_SIZE, MAX_SIZE
+            );
+        }
+
+        let work = match platform {
+            Platform::XoChip | Platform::MegaChip => size,
+            _ => size - 1,
+        };
+
+        Self { size, work }
+    }
+
+    // The fonts and anything else the interpreter keeps below programs
+    pub fn interpreter(&self) -> Range<usize> {
+        0..PROGRAM_ADDRESS
+    }
+
+    pub fn program(&self) -> Range<usize> {
+        PROGRAM_ADDRESS..self.work
+    }
+
+    pub fn work_area(&self) -> Range<usize> {
+        self.work..self.size
+    }
+
+    // A size such as `4096`, `0x800`, `2K` or `64K`
+    pub fn parse_size(value: &str) -> usize {
+        let size = match value.strip_suffix(['K', 'k']) {
+            Some(kilobytes) => kilobytes.parse::<usize>().ok().map(|size| size * 1024),
+            None => match value.strip_prefix("0x") {
+                Some(hex) => usize::from_str_radix(hex, 16).ok(),
+                None => value.parse().ok(),
+            },
+        };
+
+        size.unwrap_or_else(|| panic!("Invalid memory size '{}'", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lays_out_memory() {
+        let vip = MemoryMap::new(Platform::Chip8, None);
+
+        assert_eq!(vip.program(), 0x200..0xFFF);
+        assert_eq!(vip.work_area(), 0xFFF..0x1000);
+        assert_eq!(
+            MemoryMap::new(Platform::XoChip, None).program(),
+            0x200..0x10000
+        );
+        assert_eq!(
+            MemoryMap::new(Platform::Chip8, Some(0x800)).program(),
+            0x200..0x7FF
+        );
+
+        assert_eq!(MemoryMap::parse_size("2K"), 0x800);
+        assert_eq!(MemoryMap::parse_size("0x800"), 0x800);
+        assert_eq!(MemoryMap::parse_size("4096"), 0x1000);
+    }
+}
@@ -0,0 +1,136 @@
+use log::{info, warn};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+// Counters served in the Prometheus text format on `/metrics`, so an
+// emulator left running in a kiosk can be watched with standard tooling
+#[derive(Default)]
+pub struct Metrics {
+    frames: AtomicU64,
+    drawn: AtomicU64,
+    instructions: AtomicU64,
+    faults: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl Metrics {
+    // Start serving on `address`, such as `0.0.0.0:9160`
+    pub fn serve(address: &str) -> Arc<Self> {
+        let listener = TcpListener::bind(address)
+            .unwrap_or_else(|err| panic!("Can't serve metrics on '{}': {}", address, err));
+        let metrics = Arc::new(Self::default());
+
+        info!("Serving metrics on http://{}/metrics", address);
+
+        let served = metrics.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(err) = served.respond(stream) {
+                    warn!("Failed to answer metrics request: {}", err);
+                }
+            }
+        });
+
+        metrics
+    }
+
+    // A 60Hz frame went by, and whether the window was redrawn for it
+    pub fn record_frame(&self, drawn: bool) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+
+        if drawn {
+            self.drawn.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn set_instructions(&self, instructions: u64) {
+        self.instructions.store(instructions, Ordering::Relaxed);
+    }
+
+    // Unknown opcodes skipped in lenient mode
+    pub fn set_faults(&self, faults: u64) {
+        self.faults.store(faults, Ordering::Relaxed);
+    }
+
+    // Frames that were due but never ran, because the host stalled
+    pub fn set_dropped(&self, dropped: u64) {
+        self.dropped.store(dropped, Ordering::Relaxed);
+    }
+
+    fn render(&self) -> String {
+        let counters = [
+            ("chip8_frames_total", "60Hz frames run", &self.frames),
+            (
+                "chip8_frames_drawn_total",
+                "Frames drawn to the window",
+                &self.drawn,
+            ),
+            (
+                "chip8_instructions_total",
+                "Instructions executed",
+                &self.instructions,
+            ),
+            (
+                "chip8_faults_total",
+                "Unknown opcodes skipped",
+                &self.faults,
+            ),
+            (
+                "chip8_dropped_frames_total",
+                "Frames lost to host stalls",
+                &self.dropped,
+            ),
+        ];
+
+        counters
+            .iter()
+            .map(|(name, help, value)| {
+                format!(
+                    "# HELP {name} {help}\n# TYPE {name} counter\n{name} {}\n",
+                    value.load(Ordering::Relaxed)
+                )
+            })
+            .collect()
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let mut request = String::new();
+        BufReader::new(&stream).read_line(&mut request)?;
+
+        let (status, body) = match request.split_whitespace().nth(1) {
+            Some("/metrics") => ("200 OK", self.render()),
+            _ => ("404 Not Found", String::new()),
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_counters() {
+        let metrics = Metrics::default();
+        metrics.record_frame(true);
+        metrics.record_frame(false);
+        metrics.set_instructions(1234);
+
+        let text = metrics.render();
+
+        assert!(text.contains("# TYPE chip8_frames_total counter\nchip8_frames_total 2\n"));
+        assert!(text.contains("\nchip8_frames_drawn_total 1\n"));
+        assert!(text.contains("\nchip8_instructions_total 1234\n"));
+        assert!(text.contains("\nchip8_dropped_frames_total 0\n"));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::json::Value;
+
+// Machine state laid out like the emulator object in Octo, the CHIP-8 IDE
+// and debugger, so a moment can be moved between the two and compared:
+//
+//     {"m": [memory], "v": [V0..VF], "r": [return addresses], "i": 0,
+//      "pc": 512, "dt": 0, "st": 0, "hires": false, "flags": [RPL flags],
+//      "plane": 1, "pattern": [16 bytes], "pitch": 64, "p": [[plane 0], [plane 1]]}
+//
+// The planes of `p` hold a 0 or 1 for each pixel, row by row.
+pub struct Machine {
+    pub memory: Vec<u8>,
+    pub registers: [u8; 16],
+    pub stack: Vec<u16>,
+    pub i: u32,
+    pub pc: u16,
+    pub dt: u8,
+    pub st: u8,
+    pub hires: bool,
+    pub flags: [u8; 16],
+    pub plane: u8,
+    pub pattern: [u8; 16],
+    pub pitch: u8,
+    pub pixels: [Vec<u8>; 2],
+}
+
+impl Machine {
+    pub fn to_json(&self) -> Value {
+        Value::object()
+            .with("m", self.memory.clone())
+            .with("v", self.registers.to_vec())
+            .with("r", self.stack.clone())
+            .with("i", self.i)
+            .with("pc", self.pc)
+            .with("dt", self.dt)
+            .with("st", self.st)
+            .with("hires", self.hires)
+            .with("flags", self.flags.to_vec())
+            .with("plane", self.plane)
+            .with("pattern", self.pattern.to_vec())
+            .with("pitch", self.pitch)
+            .with(
+                "p",
+                Value::Array(
+                    self.pixels
+                        .iter()
+                        .map(|plane| plane.clone().into())
+                        .collect(),
+                ),
+            )
+    }
+
+    // Only `m`, `v`, `i` and `pc` are needed, anything else missing is
+    // taken as it is when Octo starts a ROM
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let value = Value::parse(text)?;
+        let number = |key| match value.get(key) {
+            Some(field) => field
+                .as_f64()
+                .filter(|number| number.fract() == 0.0 && *number >= 0.0)
+                .map(|number| number as u32)
+                .ok_or_else(|| format!("'{}' isn't a number", key)),
+            None => Err(format!("no '{}'", key)),
+        };
+        let optional = |key, default| match value.get(key) {
+            Some(_) => number(key),
+            None => Ok(default),
+        };
+
+        let pixels = match value.get("p").and_then(Value::as_array) {
+            Some([first, second]) => [bytes(first, "p")?, bytes(second, "p")?],
+            Some(_) => return Err("'p' doesn't have two planes".to_owned()),
+            None => [Vec::new(), Vec::new()],
+        };
+
+        Ok(Self {
+            memory: bytes(value.get("m").ok_or("no 'm'")?, "m")?,
+            registers: array(value.get("v").ok_or("no 'v'")?, "v")?,
+            stack: match value.get("r") {
+                Some(stack) => words(stack, "r")?,
+                None => Vec::new(),
+            },
+            i: number("i")?,
+            pc: u16::try_from(number("pc")?).map_err(|_| "'pc' is too large")?,
+            dt: optional("dt", 0)? as u8,
+            st: optional("st", 0)? as u8,
+            hires: value.get("hires").and_then(Value::as_bool).unwrap_or(false),
+            flags: match value.get("flags") {
+                Some(flags) => padded(flags, "flags")?,
+                None => [0; 16],
+            },
+            plane: optional("plane", 1)? as u8,
+            pattern: match value.get("pattern") {
+                Some(pattern) => array(pattern, "pattern")?,
+                None => [0; 16],
+            },
+            pitch: optional("pitch", 64)? as u8,
+            pixels,
+        })
+    }
+}
+
+fn numbers(value: &Value, key: &str, max: f64) -> Result<Vec<f64>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("'{}' isn't a list", key))?
+        .iter()
+        .map(|item| {
+            item.as_f64()
+                .filter(|number| number.fract() == 0.0 && (0.0..=max).contains(number))
+                .ok_or_else(|| {
+                    format!("'{}' holds something other than numbers up to {}", key, max)
+                })
+        })
+        .collect()
+}
+
+fn bytes(value: &Value, key: &str) -> Result<Vec<u8>, String> {
+    Ok(numbers(value, key, 255.0)?
+        .into_iter()
+        .map(|number| number as u8)
+        .collect())
+}
+
+fn words(value: &Value, key: &str) -> Result<Vec<u16>, String> {
+    Ok(numbers(value, key, 65535.0)?
+        .into_iter()
+        .map(|number| number as u16)
+        .collect())
+}
+
+fn array(value: &Value, key: &str) -> Result<[u8; 16], String> {
+    bytes(value, key)?
+        .try_into()
+        .map_err(|_| format!("'{}' doesn't hold 16 bytes", key))
+}
+
+// Octo only keeps the flags that have been saved
+fn padded(value: &Value, key: &str) -> Result<[u8; 16], String> {
+    let flags = bytes(value, key)?;
+    let mut padded = [0; 16];
+
+    if flags.len() > padded.len() {
+        return Err(format!("'{}' holds more than 16 bytes", key));
+    }
+
+    padded[..flags.len()].copy_from_slice(&flags);
+    Ok(padded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_what_it_writes() {
+        let machine = Machine {
+            memory: vec![0x12, 0x00, 0xFF],
+            registers: [7; 16],
+            stack: vec![0x202, 0x3FE],
+            i: 0x123,
+            pc: 0x200,
+            dt: 3,
+            st: 4,
+            hires: true,
+            flags: [1; 16],
+            plane: 3,
+            pattern: [0xAA; 16],
+            pitch: 70,
+            pixels: [vec![0, 1], vec![1, 0]],
+        };
+
+        let read = Machine::parse(&machine.to_json().to_string()).unwrap();
+
+        assert_eq!(read.memory, machine.memory);
+        assert_eq!(read.stack, machine.stack);
+        assert_eq!((read.i, read.pc, read.dt, read.st), (0x123, 0x200, 3, 4));
+        assert!(read.hires);
+        assert_eq!(read.pixels, machine.pixels);
+
+        let octo = Machine::parse(r#"{"m": [0], "v": [0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,9], "i": 0, "pc": 512, "flags": [5]}"#).unwrap();
+
+        assert_eq!(octo.registers[15], 9);
+        assert_eq!(octo.flags[..2], [5, 0]);
+        assert_eq!(octo.pitch, 64);
+        assert!(Machine::parse(r#"{"m": [256], "v": [], "i": 0, "pc": 0}"#).is_err());
+    }
+}
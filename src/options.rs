@@ -0,0 +1,375 @@
+use chip8::app::{self, App, Scale};
+use chip8::compare::Layout;
+use chip8::device::{Config, ExitAction, StackOverflow};
+use chip8::font::Font;
+use chip8::memory::MemoryMap;
+use chip8::palette::Palette;
+use chip8::platform::Platform;
+use chip8::quirks::Quirks;
+use chip8::rng::Rng;
+use chip8::storage::Storage;
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+pub enum Command {
+    Run,
+    // Register the emulator as the handler for `.ch8` files
+    InstallAssoc,
+    // Print what can be worked out about a ROM without running it
+    Info,
+    // Export a video of the ROM running with and without quirk changes
+    Compare,
+    // Play ROMs from a directory one after another as they appear
+    Watch,
+    // Run every ROM in a directory headlessly and compare with last time
+    Farm,
+    // Pick the quirks a ROM needs by comparing how it runs with each
+    Wizard,
+    // Run a ROM slowly, explaining every instruction
+    Tutorial,
+    // Answer questions about a session recorded with `--trace-db`
+    Query,
+    // Time keys on their way from the window to the screen
+    Latency,
+}
+
+pub struct Options {
+    pub command: Command,
+    pub path: Option<PathBuf>,
+    pub config: Config,
+    // Whether `--platform` was given, otherwise it's picked from the ROM
+    // when it can be
+    pub platform_given: bool,
+    pub app: app::Settings,
+    // Run headless for this many frames instead of opening a window
+    pub frames: Option<u32>,
+    pub print_frame: bool,
+    // Print results as JSON on stdout instead of text
+    pub json: bool,
+    // Accept scripting commands on stdin, see `console`
+    pub stdin_control: bool,
+    // Quirks changed for the second run of `compare`
+    pub compare_quirks: Vec<(String, bool)>,
+    pub layout: Layout,
+    pub output: Option<PathBuf>,
+    // For `query`: find the last write to this address before the frame
+    // `before`, or show the machine at the start of frame `at`
+    pub last_write: Option<usize>,
+    pub before: Option<u64>,
+    pub at: Option<u64>,
+}
+
+impl Options {
+    // Arguments are taken as `OsString` so ROM paths handed over by a file
+    // manager don't need to be valid unicode
+    pub fn parse<I: Iterator<Item = OsString>>(mut args: I) -> Self {
+        let mut command = Command::Run;
+        let mut path = None;
+        let mut config = Config::default();
+        let mut platform_given = false;
+        let mut app = app::Settings::default();
+        let mut frames = None;
+        let mut print_frame = false;
+        let mut json = false;
+        let mut stdin_control = false;
+        let mut compare_quirks = Vec::new();
+        let mut last_write = None;
+        let mut before = None;
+        let mut at = None;
+        let mut layout = Layout::default();
+        let mut output = None;
+        let mut first = true;
+        let mut portable = false;
+        let mut quirk_flags = Vec::new();
+
+        while let Some(arg) = args.next() {
+            let is_first = std::mem::replace(&mut first, false);
+
+            match arg.to_str().unwrap_or_default() {
+                "--on-exit" => {
+                    config.exit_action = match Self::value(&mut args, &arg).as_str() {
+                        "halt" => ExitAction::Halt,
+                        "close" => ExitAction::Close,
+                        other => panic!("Unknown exit action '{}'", other),
+                    }
+                }
+                "--quirk" => quirk_flags.push(Self::quirk(&Self::value(&mut args, &arg))),
+                "--compare-quirk" => {
+                    let (name, enabled) = Self::quirk(&Self::value(&mut args, &arg));
+
+                    // check the name now rather than after the first run
+                    Quirks::default().set(&name, enabled);
+                    compare_quirks.push((name, enabled));
+                }
+                "--layout" => layout = Layout::parse(&Self::value(&mut args, &arg)),
+                "--output" => output = Some(PathBuf::from(Self::value(&mut args, &arg))),
+                "--platform" => {
+                    config.platform = Platform::parse(&Self::value(&mut args, &arg));
+                    platform_given = true;
+                }
+                "--strict" => config.strict = true,
+                "--verify-rom" => config.verify_rom = true,
+                "--frames" => {
+                    let value = Self::value(&mut args, &arg);
+                    frames = Some(
+                        value
+                            .parse()
+                            .unwrap_or_else(|_| panic!("Invalid frame count '{}'", value)),
+                    );
+                }
+                "--print-frame" => print_frame = true,
+                "--json" => json = true,
+                "--stdin-control" => stdin_control = true,
+                "--portable" => portable = true,
+                "--flicker-stats" => config.flicker_stats = true,
+                "--coalesce-draws" => config.coalesce_draws = true,
+                "--budget-stats" => config.budget_stats = true,
+                "--slow-memory" => config.slow_memory = true,
+                "--vip-timing" => config.vip_timing = true,
+                "--banking" => config.banking = true,
+                "--key-latency" => {
+                    let value = Self::value(&mut args, &arg);
+                    config.key_latency = value
+                        .parse()
+                        .unwrap_or_else(|_| panic!("Invalid key latency '{}'", value));
+                }
+                "--click-coords" => app.click_coords = true,
+                "--raw-input" => app.raw_input = true,
+                "--keymap" => app.keymap = Some(Self::value(&mut args, &arg)),
+                "--scale" => app.scale = Scale::parse(&Self::value(&mut args, &arg)),
+                "--always-on-top" => app.always_on_top = true,
+                "--overlay" => {
+                    app.always_on_top = true;
+                    app.borderless = true;
+                }
+                "--cancel-wait-key" => {
+                    app.cancel_key = App::parse_key(&Self::value(&mut args, &arg))
+                }
+                "--cancel-wait-value" => {
+                    let value = Self::value(&mut args, &arg);
+                    app.cancel_value = u8::from_str_radix(&value, 16)
+                        .ok()
+                        .filter(|&key| key < 16)
+                        .unwrap_or_else(|| panic!("Invalid key value '{}'", value));
+                }
+                "--palette" => config.palette = Palette::parse(&Self::value(&mut args, &arg)),
+                "--font" => config.font = Some(Font::parse(&Self::value(&mut args, &arg))),
+                "--trace" => config.trace = Some(PathBuf::from(Self::value(&mut args, &arg))),
+                "--trace-ops" => config
+                    .trace_filter
+                    .set_classes(&Self::value(&mut args, &arg)),
+                "--trace-range" => config.trace_filter.set_range(&Self::value(&mut args, &arg)),
+                "--trace-db" => config.trace_db = Some(PathBuf::from(Self::value(&mut args, &arg))),
+                "--last-write" => {
+                    let value = Self::value(&mut args, &arg);
+                    last_write = Some(
+                        usize::from_str_radix(value.trim_start_matches("0x"), 16)
+                            .unwrap_or_else(|_| panic!("Invalid address '{}'", value)),
+                    );
+                }
+                "--before" => before = Some(Self::frame(&Self::value(&mut args, &arg))),
+                "--at" => at = Some(Self::frame(&Self::value(&mut args, &arg))),
+                "--lenient" => config.lenient = true,
+                "--hash-stream" => config.hash_stream = Some(Self::value(&mut args, &arg)),
+                "--hash-state" => config.hash_state = true,
+                #[cfg(feature = "metrics")]
+                "--metrics" => config.metrics = Some(Self::value(&mut args, &arg)),
+                "--low-power" => config.low_power = true,
+                "--checkpoint" => {
+                    let value = Self::value(&mut args, &arg);
+                    config.checkpoint_interval = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|&seconds| seconds > 0)
+                            .unwrap_or_else(|| panic!("Invalid checkpoint interval '{}'", value)),
+                    );
+                }
+                "--memory-size" => {
+                    config.memory_size = Some(MemoryMap::parse_size(&Self::value(&mut args, &arg)));
+                }
+                "--stack-depth" => {
+                    let value = Self::value(&mut args, &arg);
+                    config.stack_depth = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|depth| (1..=255).contains(depth))
+                            .unwrap_or_else(|| panic!("Invalid stack depth '{}'", value)),
+                    );
+                }
+                "--start-address" => {
+                    let value = Self::value(&mut args, &arg);
+                    config.start_address = Some(
+                        u16::from_str_radix(value.trim_start_matches("0x"), 16)
+                            .unwrap_or_else(|_| panic!("Invalid start address '{}'", value)),
+                    );
+                }
+                "--stack-overflow" => {
+                    config.stack_overflow = StackOverflow::parse(&Self::value(&mut args, &arg));
+                }
+                "--capture-scale" => {
+                    let value = Self::value(&mut args, &arg);
+                    config.capture_scale = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|&scale| scale > 0)
+                            .unwrap_or_else(|| panic!("Invalid capture scale '{}'", value)),
+                    );
+                }
+                "--rng" => config.rng = Rng::parse(&Self::value(&mut args, &arg)),
+                "--patch" => {
+                    config.patch = Some(PathBuf::from(Self::value(&mut args, &arg)));
+                }
+                "--record" => {
+                    config.record = Some(PathBuf::from(Self::value(&mut args, &arg)));
+                }
+                "run" if is_first => {}
+                "install-assoc" if is_first => command = Command::InstallAssoc,
+                "info" if is_first => command = Command::Info,
+                "compare" if is_first => command = Command::Compare,
+                "watch" if is_first => command = Command::Watch,
+                "farm" if is_first => command = Command::Farm,
+                "wizard" if is_first => command = Command::Wizard,
+                "tutorial" if is_first => command = Command::Tutorial,
+                "query" if is_first => command = Command::Query,
+                "latency" if is_first => command = Command::Latency,
+                // process serial number added by older macOS when launched from Finder
+                flag if flag.starts_with("-psn_") => {}
+                _ => path = Some(PathBuf::from(arg)),
+            }
+        }
+
+        // the platform and memory size decide where programs can go, and may
+        // come after the start address
+        if let Some(address) = config.start_address {
+            let program = MemoryMap::new(config.platform, config.memory_size).program();
+
+            if !program.contains(&usize::from(address)) {
+                panic!(
+                    "Start address {:#x} is outside the program area, {:#x} to {:#x}",
+                    address,
+                    program.start,
+                    program.end - 1
+                );
+            }
+        }
+
+        config.storage = Storage::new(portable);
+        app.storage = config.storage.clone();
+        // a platform picked by hand brings how its interpreter moves I after
+        // loads and stores, which `--quirk` flags then change, wherever they
+        // were given
+        if platform_given {
+            config.quirks = config.platform.quirks();
+        }
+
+        for (name, enabled) in &quirk_flags {
+            config.quirks.set(name, *enabled);
+        }
+
+        // quirks given on the command line replace any saved for the ROM
+        config.saved_quirks = quirk_flags.is_empty();
+        config.detect_platform = !platform_given;
+
+        Self {
+            command,
+            path,
+            config,
+            platform_given,
+            app,
+            frames,
+            print_frame,
+            json,
+            stdin_control,
+            compare_quirks,
+            layout,
+            output,
+            last_write,
+            before,
+            at,
+        }
+    }
+
+    fn frame(value: &str) -> u64 {
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid frame '{}'", value))
+    }
+
+    fn quirk(value: &str) -> (String, bool) {
+        match value.split_once('=') {
+            Some((name, "on")) => (name.to_owned(), true),
+            Some((name, "off")) => (name.to_owned(), false),
+            _ => panic!("Quirks must be given as 'name=on|off', got '{}'", value),
+        }
+    }
+
+    fn value<I: Iterator<Item = OsString>>(args: &mut I, flag: &OsString) -> String {
+        args.next()
+            .unwrap_or_else(|| panic!("Missing value for '{}'", flag.to_string_lossy()))
+            .into_string()
+            .unwrap_or_else(|value| panic!("Invalid value '{}'", value.to_string_lossy()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Options {
+        Options::parse(args.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn lets_quirk_flags_override_the_platform() {
+        assert!(parse(&["game.ch8"]).config.quirks.memory_increment);
+        assert!(
+            !parse(&["--platform", "schip", "game.ch8"])
+                .config
+                .quirks
+                .memory_increment
+        );
+
+        let options = parse(&[
+            "--quirk",
+            "memory_increment=on",
+            "--platform",
+            "schip",
+            "game.ch8",
+        ]);
+
+        assert!(options.config.quirks.memory_increment);
+        assert!(!options.config.saved_quirks);
+    }
+
+    #[test]
+    fn checks_the_start_address_against_the_memory_map() {
+        let options = parse(&[
+            "--start-address",
+            "2000",
+            "--platform",
+            "xo-chip",
+            "game.ch8",
+        ]);
+        assert_eq!(options.config.start_address, Some(0x2000));
+
+        let options = parse(&[
+            "--memory-size",
+            "16K",
+            "--start-address",
+            "3000",
+            "game.ch8",
+        ]);
+        assert_eq!(options.config.start_address, Some(0x3000));
+
+        for args in [
+            ["--start-address", "1000", "game.ch8"],
+            ["--start-address", "100", "game.ch8"],
+        ] {
+            assert!(std::panic::catch_unwind(|| parse(&args)).is_err());
+        }
+    }
+}
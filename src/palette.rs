@@ -0,0 +1,79 @@
+// Colours for each combination of lit planes, indexed by the plane bitmask
+// of a pixel: background, plane 1, plane 2, and both planes overlapping
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Palette {
+    colors: [[u8; 3]; 4],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            colors: [
+                [0x00, 0x00, 0x00],
+                [0xFF, 0xFF, 0xFF],
+                [0xAA, 0xAA, 0xAA],
+                [0x55, 0x55, 0x55],
+            ],
+        }
+    }
+}
+
+impl Palette {
+    // Up to four comma separated `RRGGBB` colours, in bitmask order, with
+    // missing entries keeping their defaults
+    pub fn parse(spec: &str) -> Self {
+        let mut palette = Self::default();
+        let entries: Vec<&str> = spec.split(',').map(str::trim).collect();
+
+        if entries.len() > palette.colors.len() {
+            panic!("Palette has at most 4 colours, got '{}'", spec);
+        }
+
+        for (color, entry) in palette.colors.iter_mut().zip(entries) {
+            let hex = entry.trim_start_matches('#');
+            let value = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)
+                .unwrap_or_else(|| panic!("Invalid colour '{}'", entry));
+
+            *color = [(value >> 16) as u8, (value >> 8) as u8, value as u8];
+        }
+
+        palette
+    }
+
+    pub fn background(&self) -> [u8; 3] {
+        self.colors[0]
+    }
+
+    // Colour for a pixel with the given planes lit
+    pub fn color(&self, planes: u8) -> [u8; 3] {
+        self.colors[usize::from(planes & 0b11)]
+    }
+
+    // Every colour in bitmask order, as stored in a PNG palette
+    pub fn rgb(&self) -> Vec<u8> {
+        self.colors.concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_partial_palettes() {
+        let palette = Palette::parse("102030, #FFCC00");
+
+        assert_eq!(palette.background(), [0x10, 0x20, 0x30]);
+        assert_eq!(palette.color(1), [0xFF, 0xCC, 0x00]);
+        assert_eq!(palette.color(3), Palette::default().color(3));
+    }
+
+    #[test]
+    fn blends_overlapping_planes() {
+        let palette = Palette::parse("000000,FF0000,0000FF,FF00FF");
+
+        assert_eq!(palette.color(0b11), [0xFF, 0x00, 0xFF]);
+    }
+}
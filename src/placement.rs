@@ -0,0 +1,103 @@
+use crate::storage::Storage;
+
+use log::warn;
+
+const FILE_NAME: &str = "window.txt";
+
+// How much of the window's top left corner has to be on a monitor for it
+// to be grabbed and moved back, in physical pixels
+const VISIBLE_MARGIN: i64 = 48;
+
+// Where the window was and how big it was when it was last closed, so the
+// next run opens it in the same place. Saved as one line:
+//
+//     x=120 y=80 scale=6
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Placement {
+    pub x: i32,
+    pub y: i32,
+    pub scale: u32,
+}
+
+impl Placement {
+    pub fn load(storage: &Storage) -> Option<Self> {
+        Self::parse(&storage.read_to_string(FILE_NAME).ok()?)
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let (mut x, mut y, mut scale) = (None, None, None);
+
+        for pair in text.split_whitespace() {
+            match pair.split_once('=')? {
+                ("x", value) => x = value.parse().ok(),
+                ("y", value) => y = value.parse().ok(),
+                ("scale", value) => scale = value.parse().ok().filter(|&scale| scale > 0),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            x: x?,
+            y: y?,
+            scale: scale?,
+        })
+    }
+
+    pub fn save(&self, storage: &Storage) {
+        let line = format!("x={} y={} scale={}\n", self.x, self.y, self.scale);
+
+        if let Err(err) = storage.write(FILE_NAME, line) {
+            warn!(
+                "Failed to save window position to '{}': {}",
+                storage.locate(FILE_NAME),
+                err
+            );
+        }
+    }
+
+    // Whether the window would still be reachable on one of the monitors,
+    // given as `(x, y, width, height)`, as one may have been unplugged or
+    // rearranged since
+    pub fn is_visible(&self, monitors: &[(i32, i32, u32, u32)]) -> bool {
+        let (x, y) = (i64::from(self.x), i64::from(self.y));
+
+        monitors.iter().any(|&(left, top, width, height)| {
+            let (left, top) = (i64::from(left), i64::from(top));
+
+            x + VISIBLE_MARGIN > left
+                && y + VISIBLE_MARGIN > top
+                && x + VISIBLE_MARGIN <= left + i64::from(width)
+                && y + VISIBLE_MARGIN <= top + i64::from(height)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_saved_placements() {
+        assert_eq!(
+            Placement::parse("x=-1800 y=40 scale=5\n"),
+            Some(Placement {
+                x: -1800,
+                y: 40,
+                scale: 5
+            })
+        );
+        assert_eq!(Placement::parse("x=10 scale=5"), None);
+        assert_eq!(Placement::parse("x=10 y=10 scale=0"), None);
+    }
+
+    #[test]
+    fn hides_placements_off_every_monitor() {
+        let monitors = [(0, 0, 1920, 1080), (-1280, 0, 1280, 1024)];
+        let placement = |x, y| Placement { x, y, scale: 6 };
+
+        assert!(placement(100, 100).is_visible(&monitors));
+        assert!(placement(-1000, 900).is_visible(&monitors));
+        assert!(!placement(2500, 100).is_visible(&monitors));
+        assert!(!placement(-1000, 1050).is_visible(&monitors));
+    }
+}
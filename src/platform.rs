@@ -0,0 +1,198 @@
+use crate::device::DEFAULT_START_ADDRESS;
+use crate::display::{Display, Resolution};
+use crate::font::Font;
+use crate::known;
+use crate::quirks::Quirks;
+
+// Numbered in save states, so new platforms go at the end
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Platform {
+    // The original COSMAC VIP interpreter, with none of the later
+    // extensions' instructions
+    Chip8,
+    // The DREAM 6800 running CHIPOS
+    Dream6800,
+    // The COSMAC VIP's hi-res CHIP-8, with a 64x64 display made of two
+    // pages of memory. ROMs start with `1260` and their code proper at
+    // 0x2C0, the bytes in between patching the original interpreter.
+    HiresChip8,
+    // The VIP's CHIP-8X for the VP-590 colour board and a second keypad,
+    // with its bigger interpreter pushing programs up to 0x300
+    Chip8X,
+    // Octo's XO-CHIP: SCHIP plus 64K of memory, two display planes,
+    // register ranges and pattern audio
+    XoChip,
+    // Mega-CHIP: SCHIP plus 16M of memory and a 256x192 true colour mode,
+    // entered with `0011`, drawing sprites of palette indices
+    MegaChip,
+    // SCHIP 1.1 on the HP48: CHIP-8 plus scrolling, a 128x64 hires mode,
+    // large digits and RPL flags. Run by default, as it also runs plain
+    // CHIP-8 ROMs.
+    #[default]
+    Schip,
+}
+
+impl Platform {
+    pub const ALL: [Self; 7] = [
+        Self::Chip8,
+        Self::Dream6800,
+        Self::HiresChip8,
+        Self::Chip8X,
+        Self::Schip,
+        Self::XoChip,
+        Self::MegaChip,
+    ];
+
+    pub fn parse(name: &str) -> Self {
+        match name {
+            "chip8" => Self::Chip8,
+            "schip" => Self::Schip,
+            "dream6800" => Self::Dream6800,
+            "chip8-hires" => Self::HiresChip8,
+            "chip8x" => Self::Chip8X,
+            "xo-chip" | "xochip" => Self::XoChip,
+            "megachip" => Self::MegaChip,
+            _ => panic!("Unknown platform '{}'", name),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Chip8 => "chip8",
+            Self::Dream6800 => "dream6800",
+            Self::HiresChip8 => "chip8-hires",
+            Self::Chip8X => "chip8x",
+            Self::Schip => "schip",
+            Self::XoChip => "xo-chip",
+            Self::MegaChip => "megachip",
+        }
+    }
+
+    // The platform a ROM was written for, when it's in the ROM database or
+    // can be told from its first bytes. Only hi-res CHIP-8 ROMs give
+    // themselves away, with their jump over the interpreter patch.
+    pub fn detect(rom: &[u8]) -> Option<Self> {
+        known::lookup(rom)
+            .map(|known| known.platform)
+            .or_else(|| rom.starts_with(&[0x12, 0x60]).then_some(Self::HiresChip8))
+    }
+
+    // Resolution the display starts in
+    pub fn resolution(&self) -> Resolution {
+        match self {
+            Self::HiresChip8 => Resolution::TWO_PAGE,
+            _ => Resolution::LORES,
+        }
+    }
+
+    // The display as it is when the machine starts
+    pub fn display(&self) -> Display {
+        let mut display = Display::new(self.resolution());
+
+        if *self == Self::Chip8X {
+            display.enable_zones();
+        }
+
+        display
+    }
+
+    // Where ROMs are loaded and run from, unless `--start-address` says
+    // otherwise
+    pub fn start_address(&self) -> u16 {
+        match self {
+            Self::Chip8X => 0x300,
+            _ => DEFAULT_START_ADDRESS,
+        }
+    }
+
+    // Bytes of addressable memory
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Self::XoChip => 0x10000,
+            Self::MegaChip => 0x1000000,
+            _ => 0x1000,
+        }
+    }
+
+    // The font the platform's interpreter shipped with, used unless
+    // `--font` picks another
+    pub fn font(&self) -> Font {
+        match self {
+            Self::Chip8
+            | Self::HiresChip8
+            | Self::Chip8X
+            | Self::Schip
+            | Self::XoChip
+            | Self::MegaChip => Font::Vip,
+            Self::Dream6800 => Font::Dream6800,
+        }
+    }
+
+    // The VIP quirks plain CHIP-8 ROMs expect, with loads and stores
+    // moving I the way the platform's own interpreter does. Used when
+    // `--platform` picks it.
+    pub fn quirks(&self) -> Quirks {
+        match self {
+            // SCHIP 1.1 leaves I alone after FX55/FX65, and Mega-CHIP is
+            // built on it
+            Self::Schip | Self::MegaChip => Quirks {
+                memory_increment: false,
+                ..Quirks::default()
+            },
+            _ => Quirks::default(),
+        }
+    }
+
+    // Hex keypad value at each position of a 4x4 grid, read row by row
+    pub fn keypad(&self) -> [u8; 16] {
+        match self {
+            Self::Chip8
+            | Self::HiresChip8
+            | Self::Chip8X
+            | Self::Schip
+            | Self::XoChip
+            | Self::MegaChip => [
+                0x1, 0x2, 0x3, 0xC, //
+                0x4, 0x5, 0x6, 0xD, //
+                0x7, 0x8, 0x9, 0xE, //
+                0xA, 0x0, 0xB, 0xF, //
+            ],
+            Self::Dream6800 => [
+                0xC, 0xD, 0xE, 0xF, //
+                0x8, 0x9, 0xA, 0xB, //
+                0x4, 0x5, 0x6, 0x7, //
+                0x0, 0x1, 0x2, 0x3, //
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_hires_roms() {
+        assert!(Platform::detect(&[0x12, 0x60, 0x01, 0x7A]) == Some(Platform::HiresChip8));
+        assert!(Platform::detect(&[0x12, 0x00]).is_none());
+        assert!(Platform::detect(&[]).is_none());
+    }
+
+    #[test]
+    fn moves_i_after_loads_and_stores_like_each_interpreter() {
+        for platform in Platform::ALL {
+            let quirks = platform.quirks();
+            let schip = matches!(platform, Platform::Schip | Platform::MegaChip);
+
+            assert_eq!(quirks.memory_increment, !schip, "{}", platform.name());
+            assert!(!quirks.memory_increment_by_x);
+
+            // everything else is left as the VIP had it
+            let vip = Quirks {
+                memory_increment: quirks.memory_increment,
+                ..Quirks::default()
+            };
+            assert_eq!(quirks.bits(), vip.bits());
+        }
+    }
+}
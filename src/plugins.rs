@@ -0,0 +1,140 @@
+use libloading::{Library, Symbol};
+use log::{info, warn};
+use std::env::consts::DLL_EXTENSION;
+use std::fs;
+use std::path::Path;
+
+// Version of the interface below, bumped whenever it changes in a way old
+// plugins would get wrong
+pub const API_VERSION: u32 = 1;
+
+type FilterFrame = unsafe extern "C" fn(*mut u8, u32, u32);
+type TransformKey = unsafe extern "C" fn(u8, bool) -> i32;
+
+// A dynamic library from the plugins directory, exporting C functions:
+//
+//     uint32_t chip8_plugin_api(void);
+//     void chip8_filter_frame(uint8_t *rgba, uint32_t width, uint32_t height);
+//     int32_t chip8_transform_key(uint8_t key, bool pressed);
+//
+// `chip8_plugin_api` returns the `API_VERSION` it was written for.
+// `chip8_filter_frame` changes each frame in place, as RGBA pixels at the
+// display's resolution. `chip8_transform_key` is given each keypad key as
+// it's pressed or released and returns the key to send instead, or -1 to
+// drop it. Both are optional.
+struct Plugin {
+    name: String,
+    filter: Option<FilterFrame>,
+    transform: Option<TransformKey>,
+    // the functions above point into it, so it's kept loaded
+    _library: Library,
+}
+
+#[derive(Default)]
+pub struct Plugins {
+    plugins: Vec<Plugin>,
+}
+
+impl Plugins {
+    // Every plugin in `dir`, in name order, skipping any that fail to load
+    pub fn load(dir: &Path) -> Self {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Self::default();
+        };
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == DLL_EXTENSION)
+            })
+            .collect();
+        paths.sort();
+
+        let plugins = paths
+            .iter()
+            .filter_map(|path| match Self::open(path) {
+                Ok(plugin) => {
+                    info!("Loaded plugin '{}'", plugin.name);
+                    Some(plugin)
+                }
+                Err(err) => {
+                    warn!("Skipping plugin '{}': {}", path.display(), err);
+                    None
+                }
+            })
+            .collect();
+
+        Self { plugins }
+    }
+
+    fn open(path: &Path) -> Result<Plugin, String> {
+        // SAFETY: plugins are native code the user put in the plugins
+        // directory to run, and are trusted like the emulator itself
+        unsafe {
+            let library = Library::new(path).map_err(|err| err.to_string())?;
+            let api: Symbol<unsafe extern "C" fn() -> u32> = library
+                .get(b"chip8_plugin_api")
+                .map_err(|_| "no chip8_plugin_api function".to_owned())?;
+            let version = api();
+
+            if version != API_VERSION {
+                return Err(format!(
+                    "written for plugin API {}, but this is {}",
+                    version, API_VERSION
+                ));
+            }
+
+            let filter = library
+                .get::<FilterFrame>(b"chip8_filter_frame")
+                .ok()
+                .map(|symbol| *symbol);
+            let transform = library
+                .get::<TransformKey>(b"chip8_transform_key")
+                .ok()
+                .map(|symbol| *symbol);
+
+            Ok(Plugin {
+                name: path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                filter,
+                transform,
+                _library: library,
+            })
+        }
+    }
+
+    pub fn has_filters(&self) -> bool {
+        self.plugins.iter().any(|plugin| plugin.filter.is_some())
+    }
+
+    pub fn has_transforms(&self) -> bool {
+        self.plugins.iter().any(|plugin| plugin.transform.is_some())
+    }
+
+    // Run the frame through every plugin's filter in turn
+    pub fn filter_frame(&self, frame: &mut [u8], width: u32, height: u32) {
+        assert_eq!(frame.len(), width as usize * height as usize * 4);
+
+        for filter in self.plugins.iter().filter_map(|plugin| plugin.filter) {
+            // SAFETY: the frame holds exactly width x height RGBA pixels
+            unsafe { filter(frame.as_mut_ptr(), width, height) };
+        }
+    }
+
+    // Pass a key through every plugin's transform in turn, until one drops it
+    pub fn transform_key(&self, key: u8, pressed: bool) -> Option<u8> {
+        self.plugins
+            .iter()
+            .filter_map(|plugin| plugin.transform)
+            .try_fold(key, |key, transform| {
+                // SAFETY: takes and returns plain values
+                let key = unsafe { transform(key, pressed) };
+
+                u8::try_from(key).ok().filter(|&key| key < 16)
+            })
+    }
+}
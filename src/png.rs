@@ -0,0 +1,102 @@
+use crate::hash::crc32;
+
+pub const SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+pub const GREYSCALE: u8 = 0;
+pub const RGB: u8 = 2;
+pub const INDEXED: u8 = 3;
+
+// Contents of an IHDR chunk, with default compression/filter and no interlacing
+pub fn header(width: u32, height: u32, bit_depth: u8, color_type: u8) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+    header
+}
+
+pub fn chunk(image: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    image.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    let start = image.len();
+    image.extend_from_slice(kind);
+    image.extend_from_slice(data);
+
+    let crc = crc32(&image[start..]);
+    image.extend_from_slice(&crc.to_be_bytes());
+}
+
+// Wrap data in a zlib stream made of uncompressed deflate blocks, which
+// keeps the encoder tiny and is still small at CHIP-8 resolutions
+pub fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xFFFF).peekable();
+
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[0x01, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let length = block.len() as u16;
+
+        stream.push(u8::from(blocks.peek().is_none()));
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+// Pack rows of pixel values into unfiltered scanlines of `bit_depth` bits
+// per pixel
+pub fn scanlines(rows: impl Iterator<Item = impl AsRef<[u8]>>, bit_depth: u8) -> Vec<u8> {
+    let per_byte = usize::from(8 / bit_depth);
+    let mut data = Vec::new();
+
+    for row in rows {
+        // no filter
+        data.push(0);
+
+        for pixels in row.as_ref().chunks(per_byte) {
+            let byte = pixels
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (index, &pixel)| {
+                    byte | pixel << (8 - bit_depth * (index as u8 + 1))
+                });
+
+            data.push(byte);
+        }
+    }
+
+    data
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + u32::from(byte)) % 65521;
+        (a, (b + a) % 65521)
+    });
+
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adler32_matches_reference() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn packs_scanlines() {
+        let rows = [[1, 2, 3, 0, 1]];
+
+        assert_eq!(scanlines(rows.iter(), 2), [0, 0b0110_1100, 0b0100_0000]);
+        assert_eq!(scanlines(rows.iter(), 8), [0, 1, 2, 3, 0, 1]);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::hash::{hex, sha1};
+use crate::quirks::Quirks;
+use crate::storage::Storage;
+
+use log::warn;
+
+const FILE_NAME: &str = "quirks.db";
+
+// Quirks saved for individual ROMs, one line per ROM with its SHA-1, the
+// name of the file it was saved from and every quirk that was chosen for
+// it, optionally followed by a description of the game's controls after a
+// `|`:
+//
+//     <sha1> file=tetris.ch8 half_pixel_scroll=on | Q = rotate, W = drop
+//
+// The file name is optional, and lets a ROM that no longer matches its
+// hash be recognised as a corrupt or different copy of a known one.
+pub struct Profiles {
+    storage: Storage,
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    key: String,
+    file: Option<String>,
+    quirks: Vec<(String, bool)>,
+    controls: Option<String>,
+}
+
+impl Profiles {
+    pub fn load(storage: &Storage) -> Self {
+        let text = storage.read_to_string(FILE_NAME).unwrap_or_default();
+
+        let entries = text.lines().filter_map(Entry::parse).collect();
+
+        Self {
+            storage: storage.clone(),
+            entries,
+        }
+    }
+
+    // Key a ROM is saved under
+    pub fn key(rom: &[u8]) -> String {
+        hex(&sha1(rom))
+    }
+
+    // The ROM's entry by its hash, or else the one saved under its file name
+    fn entry(&self, rom: &[u8], file: Option<&str>) -> Option<&Entry> {
+        let key = Self::key(rom);
+
+        self.entries
+            .iter()
+            .find(|entry| entry.key == key)
+            .or_else(|| self.named(file?))
+    }
+
+    fn named(&self, file: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.file.as_deref() == Some(file))
+    }
+
+    pub fn get(&self, rom: &[u8], file: Option<&str>) -> Option<&[(String, bool)]> {
+        self.entry(rom, file)
+            .map(|entry| &entry.quirks[..])
+            .filter(|quirks| !quirks.is_empty())
+    }
+
+    // How to play the ROM, as written in by hand
+    pub fn controls(&self, rom: &[u8], file: Option<&str>) -> Option<&str> {
+        self.entry(rom, file)?.controls.as_deref()
+    }
+
+    // The SHA-1 expected of a ROM whose hash isn't known but whose file
+    // name is, meaning its settings were saved for a different copy
+    pub fn mismatch(&self, rom: &[u8], file: &str) -> Option<&str> {
+        let key = Self::key(rom);
+
+        if self.entries.iter().any(|entry| entry.key == key) {
+            return None;
+        }
+
+        self.named(file).map(|entry| entry.key.as_str())
+    }
+
+    // Replace the quirks saved for a ROM, keeping its controls. File names
+    // with spaces aren't recorded, as words are split on whitespace.
+    pub fn set(&mut self, rom: &[u8], file: Option<&str>, quirks: Vec<(String, bool)>) {
+        let key = Self::key(rom);
+        let file = file
+            .filter(|file| !file.contains(char::is_whitespace))
+            .map(str::to_owned);
+
+        match self.entries.iter_mut().find(|entry| entry.key == key) {
+            Some(entry) => {
+                entry.quirks = quirks;
+                entry.file = file.or(entry.file.take());
+            }
+            None => self.entries.push(Entry {
+                key,
+                file,
+                quirks,
+                controls: None,
+            }),
+        }
+    }
+
+    pub fn save(&self) {
+        let text: String = self
+            .entries
+            .iter()
+            .map(|entry| entry.to_line() + "\n")
+            .collect();
+
+        self.storage.write(FILE_NAME, text).unwrap();
+    }
+}
+
+impl Entry {
+    fn parse(line: &str) -> Option<Self> {
+        let (line, controls) = match line.split_once('|') {
+            Some((line, controls)) => (line, Some(controls.trim().to_owned())),
+            None => (line, None),
+        };
+
+        let mut words = line.split_whitespace().peekable();
+        let key = words.next()?.to_owned();
+        let file = words
+            .next_if(|word| word.starts_with("file="))
+            .map(|word| word["file=".len()..].to_owned());
+        let quirks = words.filter_map(parse_quirk).collect();
+
+        Some(Self {
+            key,
+            file,
+            quirks,
+            controls: controls.filter(|controls| !controls.is_empty()),
+        })
+    }
+
+    fn to_line(&self) -> String {
+        let mut words = vec![self.key.clone()];
+
+        if let Some(file) = &self.file {
+            words.push(format!("file={}", file));
+        }
+
+        words.extend(
+            self.quirks
+                .iter()
+                .map(|(name, enabled)| format!("{}={}", name, if *enabled { "on" } else { "off" })),
+        );
+
+        if let Some(controls) = &self.controls {
+            words.push(format!("| {}", controls));
+        }
+
+        words.join(" ")
+    }
+}
+
+// A `name=on|off` pair, skipping quirks this build doesn't know
+fn parse_quirk(word: &str) -> Option<(String, bool)> {
+    let (name, value) = word.split_once('=')?;
+
+    if !Quirks::NAMES.contains(&name) {
+        warn!("Ignoring unknown quirk '{}' in {}", name, FILE_NAME);
+        return None;
+    }
+
+    Some((name.to_owned(), value == "on"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_known_quirks_only() {
+        assert_eq!(
+            parse_quirk("debounce=on"),
+            Some(("debounce".to_owned(), true))
+        );
+        assert_eq!(
+            parse_quirk("half_pixel_scroll=off"),
+            Some(("half_pixel_scroll".to_owned(), false))
+        );
+        assert_eq!(parse_quirk("made_up=on"), None);
+    }
+
+    #[test]
+    fn keeps_controls_after_the_quirks() {
+        let line = "abc123 debounce=on | Q = rotate, W = drop";
+        let entry = Entry::parse(line).unwrap();
+
+        assert_eq!(entry.quirks, [("debounce".to_owned(), true)]);
+        assert_eq!(entry.controls.as_deref(), Some("Q = rotate, W = drop"));
+        assert_eq!(entry.to_line(), line);
+
+        let entry = Entry::parse("abc123 |").unwrap();
+        assert!(entry.quirks.is_empty());
+        assert_eq!(entry.controls, None);
+    }
+
+    #[test]
+    fn falls_back_to_the_file_name_when_the_hash_differs() {
+        let rom = [0x12, 0x00];
+        let mut profiles = Profiles::load(&Storage::memory());
+        profiles.set(&rom, Some("pong.ch8"), vec![("debounce".to_owned(), true)]);
+
+        let key = Profiles::key(&rom);
+        assert_eq!(
+            profiles.entries[0].to_line(),
+            format!("{} file=pong.ch8 debounce=on", key)
+        );
+
+        let corrupt = [0x12, 0x02];
+        assert_eq!(profiles.mismatch(&rom, "pong.ch8"), None);
+        assert_eq!(profiles.mismatch(&corrupt, "pong.ch8"), Some(key.as_str()));
+        assert_eq!(profiles.mismatch(&corrupt, "other.ch8"), None);
+        assert!(profiles.get(&corrupt, Some("pong.ch8")).is_some());
+        assert!(profiles.get(&corrupt, None).is_none());
+    }
+}
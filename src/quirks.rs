@@ -0,0 +1,175 @@
+#[derive(Clone, Copy)]
+pub struct Quirks {
+    // 8XY1/8XY2/8XY3 reset VF to 0 (COSMAC VIP)
+    pub vf_reset: bool,
+    // FX55/FX65 leave I pointing after the last register (COSMAC VIP)
+    pub memory_increment: bool,
+    // With memory_increment, I moves on by X rather than X + 1, ending on
+    // the last register (SCHIP 1.0)
+    pub memory_increment_by_x: bool,
+    // Drawing waits for the display to refresh, so only one sprite is
+    // drawn each frame (COSMAC VIP)
+    pub display_wait: bool,
+    // Sprites are cut off at the edges of the display rather than wrapping
+    // around to the other side (COSMAC VIP, SCHIP)
+    pub clipping: bool,
+    // 8XY6/8XYE shift VX in place, ignoring VY (SCHIP)
+    pub shifting: bool,
+    // BNNN jumps to NNN plus VX rather than V0, with X the first digit of
+    // NNN (SCHIP)
+    pub jumping: bool,
+    // Scrolling in lores moves by half the requested amount (SCHIP 1.1)
+    pub half_pixel_scroll: bool,
+    // Switching between lores and hires keeps the display contents (SCHIP 1.1)
+    pub preserve_on_resize: bool,
+    // Key changes only register once the key has settled for a couple of
+    // frames, so very short presses are missed (COSMAC VIP keypad)
+    pub debounce: bool,
+    // In hires, DXYN sets VF to the number of sprite rows that collided or
+    // were clipped by the bottom of the display, not just 1 (SCHIP 1.1)
+    pub row_collisions: bool,
+}
+
+// The original COSMAC VIP interpreter's behaviour, which the later
+// interpreters' ROMs mostly still run with
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            vf_reset: true,
+            memory_increment: true,
+            memory_increment_by_x: false,
+            display_wait: true,
+            clipping: true,
+            shifting: false,
+            jumping: false,
+            half_pixel_scroll: false,
+            preserve_on_resize: false,
+            debounce: false,
+            row_collisions: false,
+        }
+    }
+}
+
+impl Quirks {
+    pub const NAMES: [&'static str; 11] = [
+        "vf_reset",
+        "memory_increment",
+        "memory_increment_by_x",
+        "display_wait",
+        "clipping",
+        "shifting",
+        "jumping",
+        "half_pixel_scroll",
+        "preserve_on_resize",
+        "debounce",
+        "row_collisions",
+    ];
+
+    pub fn flag(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "vf_reset" => Some(&mut self.vf_reset),
+            "memory_increment" => Some(&mut self.memory_increment),
+            "memory_increment_by_x" => Some(&mut self.memory_increment_by_x),
+            "display_wait" => Some(&mut self.display_wait),
+            "clipping" => Some(&mut self.clipping),
+            "shifting" => Some(&mut self.shifting),
+            "jumping" => Some(&mut self.jumping),
+            "half_pixel_scroll" => Some(&mut self.half_pixel_scroll),
+            "preserve_on_resize" => Some(&mut self.preserve_on_resize),
+            "debounce" => Some(&mut self.debounce),
+            "row_collisions" => Some(&mut self.row_collisions),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, enabled: bool) {
+        *self
+            .flag(name)
+            .unwrap_or_else(|| panic!("Unknown quirk '{}'", name)) = enabled;
+    }
+
+    // Compact form stored in save states to detect profile changes. Newer
+    // quirks take the higher bits, so older states still line up.
+    pub fn bits(&self) -> u32 {
+        [
+            self.half_pixel_scroll,
+            self.preserve_on_resize,
+            self.debounce,
+            self.row_collisions,
+            self.vf_reset,
+            self.memory_increment,
+            self.display_wait,
+            self.clipping,
+            self.shifting,
+            self.jumping,
+            self.memory_increment_by_x,
+        ]
+        .iter()
+        .enumerate()
+        .map(|(bit, &enabled)| u32::from(enabled) << bit)
+        .sum()
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        let mut quirks = Self::default();
+
+        for (bit, flag) in [
+            &mut quirks.half_pixel_scroll,
+            &mut quirks.preserve_on_resize,
+            &mut quirks.debounce,
+            &mut quirks.row_collisions,
+            &mut quirks.vf_reset,
+            &mut quirks.memory_increment,
+            &mut quirks.display_wait,
+            &mut quirks.clipping,
+            &mut quirks.shifting,
+            &mut quirks.jumping,
+            &mut quirks.memory_increment_by_x,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            *flag = bits & 1 << bit != 0;
+        }
+
+        quirks
+    }
+}
+
+// Behaviour that differs between interpreters, noticed while running a ROM
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Sensitivity {
+    // 8XY1/8XY2/8XY3 with VF already set
+    VfReset,
+    // I read again after FX55/FX65 incremented it
+    MemoryIncrement,
+    // 8XY6/8XYE with X != Y
+    Shifting,
+    // BNNN where VX and V0 differ
+    Jumping,
+    // DXYN drawing across the edge of the display
+    Clipping,
+}
+
+impl Sensitivity {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::VfReset => "VF reset",
+            Self::MemoryIncrement => "memory increment",
+            Self::Shifting => "shifting",
+            Self::Jumping => "jumping",
+            Self::Clipping => "clipping",
+        }
+    }
+
+    // The quirk that changes the behaviour
+    pub fn quirk(&self) -> &'static str {
+        match self {
+            Self::VfReset => "vf_reset",
+            Self::MemoryIncrement => "memory_increment",
+            Self::Shifting => "shifting",
+            Self::Jumping => "jumping",
+            Self::Clipping => "clipping",
+        }
+    }
+}
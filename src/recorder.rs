@@ -0,0 +1,122 @@
+use crate::device::Tone;
+use crate::display::Display;
+use crate::palette::Palette;
+use crate::wav::{WavWriter, SAMPLE_RATE};
+
+use log::info;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// Pitch of the beeper in the recorded audio
+const TONE_HZ: u32 = 440;
+const VOLUME: i16 = i16::MAX / 4;
+const SAMPLES_PER_FRAME: u32 = SAMPLE_RATE / 60;
+
+// Records every emulated frame as an image plus a log of when the beeper
+// turns on and off and the beeper itself as a WAV, playing XO-CHIP audio
+// patterns as they were set. All of them are stamped
+// with the emulated frame number rather than wall-clock time, so a stalled
+// host can't push them out of sync.
+pub struct Recorder {
+    dir: PathBuf,
+    // Record coloured PNGs at this scale instead of plain PBMs
+    capture: Option<(Palette, usize)>,
+    audio: BufWriter<File>,
+    wav: WavWriter,
+    // Position within the tone's period, or the pattern's 128 samples, to
+    // keep the wave continuous across frames
+    phase: f64,
+    sound: bool,
+}
+
+impl Recorder {
+    pub fn new(dir: &Path, capture: Option<(Palette, usize)>) -> Self {
+        fs::create_dir_all(dir).unwrap();
+
+        let mut audio = BufWriter::new(File::create(dir.join("audio.log")).unwrap());
+        writeln!(audio, "# frame\tseconds\tsound").unwrap();
+
+        let wav = WavWriter::create(&dir.join("audio.wav")).unwrap();
+
+        info!("Recording to '{}'", dir.display());
+
+        Self {
+            dir: dir.to_path_buf(),
+            capture,
+            audio,
+            wav,
+            phase: 0.0,
+            sound: false,
+        }
+    }
+
+    // `tone` is what the beeper plays, or `None` while it's silent
+    pub fn record(&mut self, frame: u64, display: &Display, tone: Option<Tone>) {
+        let sound = tone.is_some();
+
+        match &self.capture {
+            Some((palette, scale)) => {
+                let path = self.dir.join(format!("frame_{:06}.png", frame));
+                fs::write(path, display.capture(palette, *scale)).unwrap();
+            }
+            None => {
+                let path = self.dir.join(format!("frame_{:06}.pbm", frame));
+                fs::write(path, display.pbm()).unwrap();
+            }
+        }
+
+        self.record_tone(tone);
+
+        if sound != self.sound {
+            self.sound = sound;
+
+            let seconds = frame as f64 / 60.0;
+            let state = if sound { "on" } else { "off" };
+
+            writeln!(self.audio, "{}\t{:.4}\t{}", frame, seconds, state).unwrap();
+        }
+    }
+}
+
+impl Recorder {
+    // One frame of a square wave or the audio pattern while the beeper is
+    // on, silence otherwise
+    fn record_tone(&mut self, tone: Option<Tone>) {
+        let (step, period) = match tone {
+            Some(Tone::Beep) => (f64::from(TONE_HZ) * 2.0, 2.0),
+            Some(Tone::Pattern { pitch, .. }) => (Tone::rate(pitch), 128.0),
+            None => (0.0, 1.0),
+        };
+        let step = step / f64::from(SAMPLE_RATE);
+
+        let samples: Vec<i16> = (0..SAMPLES_PER_FRAME)
+            .map(|_| {
+                let position = self.phase as usize;
+                self.phase = (self.phase + step) % period;
+
+                let high = match tone {
+                    Some(Tone::Beep) => position == 0,
+                    Some(Tone::Pattern { samples, .. }) => {
+                        samples[position / 8] & (0x80 >> (position % 8)) != 0
+                    }
+                    None => return 0,
+                };
+
+                if high {
+                    VOLUME
+                } else {
+                    -VOLUME
+                }
+            })
+            .collect();
+
+        self.wav.write(&samples).unwrap();
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.audio.flush();
+    }
+}
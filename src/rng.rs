@@ -0,0 +1,87 @@
+use rand::rngs::StdRng;
+use rand::{Rng as _, SeedableRng};
+use std::collections::VecDeque;
+
+// Source of the random bytes returned by CXKK
+#[derive(Clone)]
+pub enum Rng {
+    // Seeded from the OS, different on every run
+    System(StdRng),
+    // Fixed seed, for reproducible runs
+    Seeded(StdRng),
+    Vip(VipRng),
+    // The bytes a recorded session drew, handed out again in order while
+    // it's replayed
+    Replayed(VecDeque<u8>),
+}
+
+impl Rng {
+    pub fn parse(value: &str) -> Self {
+        match value.split_once(':') {
+            None if value == "system" => Self::System(StdRng::from_entropy()),
+            None if value == "vip" => Self::Vip(VipRng::default()),
+            Some(("seed", seed)) => Self::Seeded(StdRng::seed_from_u64(
+                seed.parse()
+                    .unwrap_or_else(|_| panic!("Invalid seed '{}'", seed)),
+            )),
+            _ => panic!("Unknown RNG '{}', expected system, seed:<n> or vip", value),
+        }
+    }
+
+    pub fn next_byte(&mut self) -> u8 {
+        match self {
+            Self::System(rng) | Self::Seeded(rng) => rng.gen(),
+            Self::Vip(rng) => rng.next_byte(),
+            Self::Replayed(bytes) => bytes.pop_front().unwrap_or_default(),
+        }
+    }
+
+    // Queue a byte for a replayed session to draw
+    pub fn replay(&mut self, byte: u8) {
+        if let Self::Replayed(bytes) = self {
+            bytes.push_back(byte);
+        }
+    }
+
+    // Called on every 60Hz display interrupt
+    pub fn interrupt(&mut self) {
+        if let Self::Vip(rng) = self {
+            rng.interrupt();
+        }
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::System(StdRng::from_entropy())
+    }
+}
+
+// Models the VIP interpreter's random routine: a 16-bit register whose low
+// byte is bumped by the display interrupt and by each call, mixed with an
+// accumulated high byte. The result is only as random as the timing of the
+// calls, so ROMs calling CXKK several times per frame see correlated values
+// just like on real hardware.
+#[derive(Clone, Default)]
+pub struct VipRng {
+    seed: u16,
+}
+
+impl VipRng {
+    fn next_byte(&mut self) -> u8 {
+        let [high, low] = self.seed.to_be_bytes();
+
+        let low = low.wrapping_add(1);
+        let high = high.wrapping_add(low.rotate_left(3) ^ 0xA5).rotate_right(1);
+
+        self.seed = u16::from_be_bytes([high, low]);
+
+        high ^ low
+    }
+
+    fn interrupt(&mut self) {
+        let [high, low] = self.seed.to_be_bytes();
+
+        self.seed = u16::from_be_bytes([high, low.wrapping_add(1)]);
+    }
+}
@@ -1,93 +1,331 @@
+use crate::display::{Display, Resolution, RgbaImage};
+use crate::palette::Palette;
+
+use pixels::wgpu::Color;
 use pixels::{Pixels, SurfaceTexture};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use winit::dpi::LogicalSize;
 use winit::window::Window;
 
-pub const WIDTH: u8 = 64;
-pub const HEIGHT: u8 = 32;
-
-const BUFFER_SIZE: usize = WIDTH as usize * HEIGHT as usize;
+// How long a notice stays in the title
+const NOTICE_TIME: Duration = Duration::from_secs(3);
 
+// Renders a `Display` into the window, scaling it to fit the surface
 pub struct Screen {
+    window: Arc<Window>,
     pixels: Pixels,
-    buffer: [bool; BUFFER_SIZE],
+    scale: u32,
+    width: u32,
+    height: u32,
+    sound: bool,
+    quirk_warning: bool,
+    // Name of the ROM shown in the title, when playing several in a row
+    name: Option<String>,
+    // A short message shown in the title until it expires
+    notice: Option<(String, Instant)>,
+    highlight: Vec<bool>,
+    // Shown instead of the display while it's set, such as the save slots
+    overlay: Option<RgbaImage>,
+    palette: Palette,
+    // Pixels are still fading out, so the frame changes even when nothing
+    // was drawn
+    fading: bool,
+    filter: Option<Box<FrameFilter>>,
+    // The frame before filtering, which fading carries on from
+    unfiltered: Vec<u8>,
+    // How long the last refresh took to prepare the frame, and to present
+    // it
+    timings: (Duration, Duration),
 }
 
+// Changes the RGBA frame at the display's resolution, given its width and
+// height, before it's scaled to the window
+pub type FrameFilter = dyn FnMut(&mut [u8], u32, u32) + Send;
+
 impl Screen {
-    pub fn new(window: Arc<Window>) -> Self {
+    pub fn new(window: Arc<Window>, scale: u32, palette: Palette) -> Self {
         let surface_texture = SurfaceTexture::new(
             window.inner_size().width,
             window.inner_size().height,
             &window,
         );
 
+        let mut pixels = Pixels::new(1, 1, surface_texture).unwrap();
+        let [r, g, b] = palette
+            .background()
+            .map(|channel| f64::from(channel) / 255.0);
+        pixels.clear_color(Color { r, g, b, a: 1.0 });
+
         Self {
-            pixels: Pixels::new(u32::from(WIDTH), u32::from(HEIGHT), surface_texture).unwrap(),
-            buffer: [false; BUFFER_SIZE],
+            window: window.clone(),
+            pixels,
+            scale,
+            width: 1,
+            height: 1,
+            sound: false,
+            quirk_warning: false,
+            name: None,
+            notice: None,
+            highlight: Vec::new(),
+            overlay: None,
+            palette,
+            fading: true,
+            filter: None,
+            unfiltered: Vec::new(),
+            timings: (Duration::ZERO, Duration::ZERO),
+        }
+    }
+
+    pub fn timings(&self) -> (Duration, Duration) {
+        self.timings
+    }
+
+    fn present(&mut self, start: Instant) {
+        let rendered = Instant::now();
+
+        self.pixels.render().unwrap();
+        self.timings = (rendered - start, rendered.elapsed());
+    }
+
+    // Size of the window needed to show a resolution with square pixels,
+    // where `scale` is the size of a lores pixel
+    pub fn window_size(resolution: Resolution, scale: u32) -> LogicalSize<u32> {
+        let lores_width = Resolution::LORES.width as u32 * scale;
+        let pixel_size = lores_width.div_ceil(resolution.width as u32).max(1);
+
+        LogicalSize::new(
+            resolution.width as u32 * pixel_size,
+            resolution.height as u32 * pixel_size,
+        )
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.pixels.resize_surface(width, height).unwrap();
         }
     }
 
-    pub fn clear(&mut self) {
-        self.buffer = [false; BUFFER_SIZE];
+    // Display coordinates of a position in the window, in physical pixels
+    pub fn pixel_at(&self, x: f64, y: f64) -> Option<(usize, usize)> {
+        self.pixels.window_pos_to_pixel((x as f32, y as f32)).ok()
     }
 
-    pub fn draw(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
-        let mut collision = false;
+    pub fn set_sound(&mut self, playing: bool) {
+        if playing != self.sound {
+            self.sound = playing;
+            self.update_title();
+        }
+    }
+
+    // Show a subtle marker when the ROM depends on quirk settings
+    pub fn set_quirk_warning(&mut self, warning: bool) {
+        if warning != self.quirk_warning {
+            self.quirk_warning = warning;
+            self.update_title();
+        }
+    }
+
+    // Tint the given pixels red on the next refresh
+    pub fn set_highlight(&mut self, pixels: &[bool]) {
+        if pixels.is_empty() && self.highlight.is_empty() {
+            return;
+        }
+
+        self.fading = true;
+        self.highlight.clear();
+        self.highlight.extend_from_slice(pixels);
+    }
+
+    pub fn set_overlay(&mut self, overlay: Option<RgbaImage>) {
+        self.overlay = overlay;
+        self.fading = true;
+    }
+
+    pub fn set_filter(&mut self, filter: impl FnMut(&mut [u8], u32, u32) + Send + 'static) {
+        self.filter = Some(Box::new(filter));
+        self.fading = true;
+    }
 
-        let wrapped_x = (x % WIDTH) as usize;
-        let wrapped_y = (y % HEIGHT) as usize;
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.name = name;
+        self.update_title();
+    }
 
-        for (y_row, line) in sprite.iter().enumerate() {
-            let y_pos = wrapped_y + y_row;
+    pub fn notify(&mut self, text: String) {
+        self.notice = Some((text, Instant::now() + NOTICE_TIME));
+        self.update_title();
+    }
 
-            // clip sprites
-            if y_pos >= HEIGHT.into() {
-                break;
+    pub fn expire_notice(&mut self) {
+        if self
+            .notice
+            .as_ref()
+            .is_some_and(|(_, expiry)| Instant::now() >= *expiry)
+        {
+            self.notice = None;
+            self.update_title();
+        }
+    }
+
+    fn update_title(&self) {
+        let mut title = String::from(if self.sound { "🔊" } else { "CHIP8" });
+
+        if let Some(name) = &self.name {
+            title.push_str(" - ");
+            title.push_str(name);
+        }
+
+        if self.quirk_warning {
+            title.push_str(" ⚠");
+        }
+
+        if let Some((text, _)) = &self.notice {
+            title.push_str(" [");
+            title.push_str(text);
+            title.push(']');
+        }
+
+        self.window.set_title(&title);
+    }
+
+    pub fn refresh(&mut self, display: &Display) {
+        let start = Instant::now();
+
+        if let Some(overlay) = &self.overlay {
+            let (width, height) = (overlay.width as u32, overlay.height as u32);
+
+            if (width, height) != (self.width, self.height) {
+                self.pixels.resize_buffer(width, height).unwrap();
+                self.width = width;
+                self.height = height;
             }
 
-            for x_column in 0..8_usize {
-                let pixel = line & (0x80 >> x_column);
-                let x_pos = wrapped_x + x_column;
+            self.pixels.frame_mut().copy_from_slice(&overlay.pixels);
+            self.present(start);
+            return;
+        }
 
-                // clip sprites
-                if x_pos >= WIDTH.into() {
-                    break;
-                }
+        let resolution = display.resolution();
+        let (width, height) = (resolution.width as u32, resolution.height as u32);
 
-                if pixel != 0 {
-                    let index = x_pos + (y_pos * usize::from(WIDTH));
+        if (width, height) != (self.width, self.height) {
+            self.fading = true;
+            self.pixels.resize_buffer(width, height).unwrap();
+            // don't fade out whatever was shown at the old size
+            self.pixels.frame_mut().fill(0);
+            self.unfiltered.clear();
+            self.width = width;
+            self.height = height;
 
-                    if self.buffer[index] {
-                        collision = true
-                    }
+            let _ = self
+                .window
+                .request_inner_size(Self::window_size(resolution, self.scale));
+        }
 
-                    self.buffer[index] ^= true;
+        // Only rebuild the frame when a plane changed or pixels are still
+        // fading, otherwise the previous frame is still accurate
+        if display.dirty() != 0 || self.fading {
+            match &mut self.filter {
+                Some(filter) => {
+                    let frame = self.pixels.frame_mut();
+
+                    self.unfiltered.resize(frame.len(), 0);
+                    self.fading = Self::convert(
+                        display,
+                        &self.palette,
+                        &self.highlight,
+                        &mut self.unfiltered,
+                    );
+
+                    frame.copy_from_slice(&self.unfiltered);
+                    filter(frame, width, height);
+                }
+                None => {
+                    self.fading = Self::convert(
+                        display,
+                        &self.palette,
+                        &self.highlight,
+                        self.pixels.frame_mut(),
+                    );
                 }
             }
         }
 
-        collision
+        self.present(start);
     }
 
-    pub fn refresh(&mut self) {
-        let frame = self.pixels.frame_mut();
+    // Turn the display into the RGBA `frame` shown in the window, fading
+    // out pixels that were lit in the previous frame. Returns whether any
+    // pixels are still fading.
+    pub fn convert(
+        display: &Display,
+        palette: &Palette,
+        highlight: &[bool],
+        frame: &mut [u8],
+    ) -> bool {
+        if let Some(colors) = display.true_colors() {
+            for ([r, g, b], rgba) in colors.into_iter().zip(frame.chunks_exact_mut(4)) {
+                rgba.copy_from_slice(&[r, g, b, 0xFF]);
+            }
+
+            return false;
+        }
+
+        let mut fading = false;
 
-        for (pixel, rgba) in self.buffer.into_iter().zip(frame.chunks_exact_mut(4)) {
-            if pixel {
-                rgba.copy_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF])
-            } else {
+        for (index, (&pixel, rgba)) in display
+            .buffer()
+            .iter()
+            .zip(frame.chunks_exact_mut(4))
+            .enumerate()
+        {
+            if highlight.get(index) == Some(&true) {
+                rgba.copy_from_slice(&[0xFF, 0x40, 0x40, if pixel != 0 { 0xFF } else { 0x80 }]);
+                fading = true;
+            } else if pixel != 0 {
+                let [r, g, b] = palette.color(pixel);
+                rgba.copy_from_slice(&[r, g, b, 0xFF]);
+            } else if rgba[3] > 0 {
                 let mut alpha = rgba[3];
 
-                // Fade out (2-step) to prevent flickering
+                // Fade out (2-step) to prevent flickering, keeping the
+                // colour of the planes that were lit
                 if alpha > 0xFB {
                     alpha -= 0x02
                 } else {
                     alpha = alpha.saturating_sub(0x20);
                 }
 
-                rgba.copy_from_slice(&[0xFF, 0xFF, 0xFF, alpha])
+                rgba[3] = alpha;
+                fading |= alpha > 0;
             }
         }
 
-        self.pixels.render().unwrap();
+        fading
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colours_each_combination_of_planes() {
+        let palette = Palette::parse("000000,FF0000,00FF00,0000FF");
+        let mut display = Display::new(Resolution::LORES);
+        let mut frame = vec![0; display.buffer().len() * 4];
+
+        display.select_planes(0b01);
+        display.draw(0, 0, &[0b1010_0000]);
+        display.select_planes(0b10);
+        display.draw(0, 0, &[0b0110_0000]);
+
+        Screen::convert(&display, &palette, &[], &mut frame);
+
+        assert_eq!(frame[..4], [0xFF, 0x00, 0x00, 0xFF]);
+        assert_eq!(frame[4..8], [0x00, 0xFF, 0x00, 0xFF]);
+        assert_eq!(frame[8..12], [0x00, 0x00, 0xFF, 0xFF]);
+        assert_eq!(frame[12..16], [0x00, 0x00, 0x00, 0x00]);
     }
 }
@@ -0,0 +1,190 @@
+use crate::display::{Resolution, RgbaImage};
+use crate::font;
+use crate::palette::Palette;
+
+use std::time::{Duration, SystemTime};
+
+// Save state slots per ROM, picked from the strip shown while F2 is held
+pub const COUNT: usize = 10;
+
+// Each thumbnail is the display shrunk or grown to lores
+const THUMB_WIDTH: usize = 64;
+const THUMB_HEIGHT: usize = 32;
+const BORDER: usize = 1;
+const GAP: usize = 4;
+const TEXT_HEIGHT: usize = 5;
+const CELL_WIDTH: usize = THUMB_WIDTH + 2 * BORDER;
+const CELL_HEIGHT: usize = THUMB_HEIGHT + 2 * BORDER;
+
+const WIDTH: usize = COUNT * CELL_WIDTH + (COUNT + 1) * GAP;
+const HEIGHT: usize = GAP + CELL_HEIGHT + 2 + TEXT_HEIGHT + GAP;
+
+const BACKGROUND: [u8; 3] = [0x10, 0x10, 0x10];
+const EMPTY: [u8; 3] = [0x28, 0x28, 0x28];
+const FRAME: [u8; 3] = [0x60, 0x60, 0x60];
+const SELECTED: [u8; 3] = [0xFF, 0xC0, 0x00];
+const TEXT: [u8; 3] = [0xC0, 0xC0, 0xC0];
+
+// Units for how long ago a slot was saved, in the same 4x5 style as the
+// VIP's digits
+#[rustfmt::skip]
+const UNITS: [(char, [u8; 5]); 4] = [
+    ('s', [0b00000000, 0b01110000, 0b11000000, 0b00110000, 0b11100000]),
+    ('m', [0b00000000, 0b11010000, 0b10110000, 0b10010000, 0b10010000]),
+    ('h', [0b10000000, 0b10000000, 0b11100000, 0b10010000, 0b10010000]),
+    ('d', [0b00010000, 0b00010000, 0b01110000, 0b10010000, 0b01110000]),
+];
+
+// What's in a slot, as far as the strip shows it
+#[derive(Default)]
+pub struct Slot {
+    pub saved: Option<SystemTime>,
+    // The display at the time, when the state could be read
+    pub thumbnail: Option<(Resolution, Vec<u8>)>,
+}
+
+impl Slot {
+    // A line for the title, such as "Slot 3: saved 12m ago"
+    pub fn describe(&self, index: usize, now: SystemTime) -> String {
+        match self.saved {
+            Some(saved) => format!("Slot {}: saved {} ago", index, age(saved, now)),
+            None => format!("Slot {}: empty", index),
+        }
+    }
+}
+
+// How long ago, in its largest whole unit
+fn age(saved: SystemTime, now: SystemTime) -> String {
+    let seconds = now
+        .duration_since(saved)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+// Every slot side by side, with the selected one framed and how long ago
+// each was saved underneath
+pub fn render(slots: &[Slot], selected: usize, palette: &Palette, now: SystemTime) -> RgbaImage {
+    let mut image = vec![BACKGROUND; WIDTH * HEIGHT];
+
+    for (index, slot) in slots.iter().enumerate().take(COUNT) {
+        let left = GAP + index * (CELL_WIDTH + GAP);
+        let frame = if index == selected { SELECTED } else { FRAME };
+
+        fill(&mut image, left, GAP, CELL_WIDTH, CELL_HEIGHT, frame);
+        fill(
+            &mut image,
+            left + BORDER,
+            GAP + BORDER,
+            THUMB_WIDTH,
+            THUMB_HEIGHT,
+            EMPTY,
+        );
+
+        if let Some((resolution, buffer)) = &slot.thumbnail {
+            for y in 0..THUMB_HEIGHT {
+                for x in 0..THUMB_WIDTH {
+                    let source = x * resolution.width / THUMB_WIDTH
+                        + y * resolution.height / THUMB_HEIGHT * resolution.width;
+                    let pixel = buffer.get(source).copied().unwrap_or(0);
+
+                    image[left + BORDER + x + (GAP + BORDER + y) * WIDTH] =
+                        palette.color(pixel & 0b11);
+                }
+            }
+        }
+
+        if let Some(saved) = slot.saved {
+            let text = age(saved, now);
+            let width = text.len() * 5 - 1;
+
+            write(
+                &mut image,
+                left + (CELL_WIDTH - width) / 2,
+                GAP + CELL_HEIGHT + 2,
+                &text,
+            );
+        }
+    }
+
+    RgbaImage {
+        width: WIDTH,
+        height: HEIGHT,
+        pixels: image
+            .iter()
+            .flat_map(|&[r, g, b]| [r, g, b, 0xFF])
+            .collect(),
+    }
+}
+
+fn fill(
+    image: &mut [[u8; 3]],
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+    color: [u8; 3],
+) {
+    for row in image.chunks_exact_mut(WIDTH).skip(top).take(height) {
+        row[left..left + width].fill(color);
+    }
+}
+
+// Draw digits and units, 4 pixels wide with a column between them
+fn write(image: &mut [[u8; 3]], left: usize, top: usize, text: &str) {
+    for (position, c) in text.chars().enumerate() {
+        let glyph = match c.to_digit(10) {
+            Some(digit) => &font::VIP[digit as usize * 5..][..5],
+            None => match UNITS.iter().find(|(unit, _)| *unit == c) {
+                Some((_, glyph)) => &glyph[..],
+                None => continue,
+            },
+        };
+
+        for (y, row) in glyph.iter().enumerate() {
+            for x in 0..4 {
+                if row & (0x80 >> x) != 0 {
+                    image[left + position * 5 + x + (top + y) * WIDTH] = TEXT;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_how_long_ago_slots_were_saved() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(100_000);
+        let saved = |seconds| Slot {
+            saved: Some(now - Duration::from_secs(seconds)),
+            thumbnail: None,
+        };
+
+        assert_eq!(saved(42).describe(1, now), "Slot 1: saved 42s ago");
+        assert_eq!(saved(750).describe(2, now), "Slot 2: saved 12m ago");
+        assert_eq!(saved(7200).describe(3, now), "Slot 3: saved 2h ago");
+        assert_eq!(saved(90_000).describe(4, now), "Slot 4: saved 1d ago");
+        assert_eq!(Slot::default().describe(5, now), "Slot 5: empty");
+    }
+
+    #[test]
+    fn frames_the_selected_slot() {
+        let slots: Vec<Slot> = (0..COUNT).map(|_| Slot::default()).collect();
+        let image = render(&slots, 2, &Palette::default(), SystemTime::now());
+        let pixel = |x: usize, y: usize| image.pixels[(x + y * WIDTH) * 4..][..3].to_vec();
+
+        assert_eq!((image.width, image.height), (WIDTH, HEIGHT));
+        assert_eq!(pixel(GAP, GAP), FRAME);
+        assert_eq!(pixel(GAP + 2 * (CELL_WIDTH + GAP), GAP), SELECTED);
+        assert_eq!(pixel(GAP + BORDER, GAP + BORDER), EMPTY);
+    }
+}
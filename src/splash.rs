@@ -0,0 +1,86 @@
+// Shown when the emulator is started without a ROM: the hex keypad laid out
+// like the COSMAC VIP's, with each key lighting up while it's held, to
+// check the keyboard mapping before loading a game
+#[rustfmt::skip]
+pub const ROM: [u8; 129] = [
+    0x00, 0xE0, // 200  CLS
+    0x65, 0x00, // 202  LD V5, 0           offset of the key in the table
+    0xA2, 0x41, // 204  LD I, table        draw every key's digit
+    0xF5, 0x1E, // 206  ADD I, V5
+    0xF3, 0x65, // 208  LD V3, [I]         V0, V1 = position, V2 = key
+    0xF2, 0x29, // 20A  LD F, V2
+    0xD0, 0x15, // 20C  DRW V0, V1, 5
+    0x75, 0x04, // 20E  ADD V5, 4
+    0x35, 0x40, // 210  SE V5, 64
+    0x12, 0x04, // 212  JP 204
+    0x65, 0x00, // 214  LD V5, 0           then check the keys forever
+    0xA2, 0x41, // 216  LD I, table
+    0xF5, 0x1E, // 218  ADD I, V5
+    0xF3, 0x65, // 21A  LD V3, [I]         V3 = whether the key is lit
+    0x64, 0x00, // 21C  LD V4, 0
+    0xE2, 0xA1, // 21E  SKNP V2
+    0x64, 0x01, // 220  LD V4, 1
+    0x54, 0x30, // 222  SE V4, V3
+    0x22, 0x2E, // 224  CALL 22E           light or unlight a changed key
+    0x75, 0x04, // 226  ADD V5, 4
+    0x35, 0x40, // 228  SE V5, 64
+    0x12, 0x16, // 22A  JP 216
+    0x12, 0x14, // 22C  JP 214
+    0xA2, 0x3C, // 22E  LD I, block
+    0xD0, 0x15, // 230  DRW V0, V1, 5      invert the digit
+    0x83, 0x40, // 232  LD V3, V4
+    0xA2, 0x41, // 234  LD I, table
+    0xF5, 0x1E, // 236  ADD I, V5
+    0xF3, 0x55, // 238  LD [I], V3
+    0x00, 0xEE, // 23A  RET
+    // 23C  block
+    0xF0, 0xF0, 0xF0, 0xF0, 0xF0,
+    // 241  table: x, y, key and whether it's lit, for each key in keypad order
+    0x12, 0x04, 0x01, 0x00, 0x1A, 0x04, 0x02, 0x00, 0x22, 0x04, 0x03, 0x00, 0x2A, 0x04, 0x0C, 0x00,
+    0x12, 0x0B, 0x04, 0x00, 0x1A, 0x0B, 0x05, 0x00, 0x22, 0x0B, 0x06, 0x00, 0x2A, 0x0B, 0x0D, 0x00,
+    0x12, 0x12, 0x07, 0x00, 0x1A, 0x12, 0x08, 0x00, 0x22, 0x12, 0x09, 0x00, 0x2A, 0x12, 0x0E, 0x00,
+    0x12, 0x19, 0x0A, 0x00, 0x1A, 0x19, 0x00, 0x00, 0x22, 0x19, 0x0B, 0x00, 0x2A, 0x19, 0x0F, 0x00,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{Config, Device};
+
+    #[test]
+    fn lights_held_keys() {
+        let mut device = Device::new(Config::default());
+        device.load_rom(&ROM);
+
+        // one frame per digit drawn
+        for _ in 0..20 {
+            device.run_frame();
+        }
+
+        let lit = |device: &Device| {
+            device
+                .display()
+                .buffer()
+                .iter()
+                .filter(|&&pixel| pixel != 0)
+                .count()
+        };
+        let digits = lit(&device);
+
+        // a sweep of all the keys takes a few frames
+        device.handle_key(0x5, true);
+        for _ in 0..10 {
+            device.run_frame();
+        }
+
+        // inverting the 5 swaps its 14 lit pixels for the 6 unlit ones
+        assert_eq!(lit(&device), digits - 14 + 6);
+
+        device.handle_key(0x5, false);
+        for _ in 0..10 {
+            device.run_frame();
+        }
+
+        assert_eq!(lit(&device), digits);
+    }
+}
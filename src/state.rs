@@ -0,0 +1,268 @@
+use crate::hash::crc32;
+use crate::platform::Platform;
+
+// Save state file layout, all values little endian:
+//
+//   magic      4 bytes  "C8ST"
+//   version    u16
+//   rom hash   u32      CRC-32 of the ROM the state was saved from
+//   quirks     u32      bitmask of enabled quirks
+//   platform   u8
+//   memory     u32      bytes of memory in the payload, from v7 on
+//   length     u32      length of the payload
+//   payload    ...      machine state, layout depends on the version
+//   checksum   u32      CRC-32 of everything above
+const MAGIC: &[u8; 4] = b"C8ST";
+const HEADER_SIZE: usize = 23;
+
+pub const VERSION: u16 = 7;
+
+pub struct Header {
+    pub version: u16,
+    pub rom_hash: u32,
+    pub quirks: u32,
+    pub platform: u8,
+    pub memory_size: u32,
+}
+
+pub fn encode(header: &Header, payload: &[u8]) -> Vec<u8> {
+    let mut writer = Writer::default();
+
+    writer.bytes(MAGIC);
+    writer.u16(header.version);
+    writer.u32(header.rom_hash);
+    writer.u32(header.quirks);
+    writer.u8(header.platform);
+
+    if header.version >= 7 {
+        writer.u32(header.memory_size);
+    }
+
+    writer.u32(payload.len() as u32);
+    writer.bytes(payload);
+
+    let checksum = crc32(&writer.0);
+    writer.u32(checksum);
+
+    writer.0
+}
+
+// Validate a state file and return its header and payload, migrated to
+// the current version
+pub fn decode(data: &[u8]) -> Result<(Header, Vec<u8>), String> {
+    if data.len() < HEADER_SIZE + 4 || &data[..4] != MAGIC {
+        return Err("not a save state".to_string());
+    }
+
+    let (body, checksum) = data.split_at(data.len() - 4);
+
+    if crc32(body) != u32::from_le_bytes(checksum.try_into().unwrap()) {
+        return Err("checksum mismatch, the file is corrupted".to_string());
+    }
+
+    let mut reader = Reader::new(&body[4..]);
+
+    let version = reader.u16();
+    let rom_hash = reader.u32();
+    let quirks = reader.u32();
+    let platform = reader.u8();
+
+    // v6 and earlier didn't record the memory size, which was the
+    // platform's own
+    let memory_size = if version >= 7 {
+        reader.u32()
+    } else {
+        Platform::ALL
+            .into_iter()
+            .find(|&known| known as u8 == platform)
+            .map_or(0, |known| known.memory_size() as u32)
+    };
+
+    let header = Header {
+        version,
+        rom_hash,
+        quirks,
+        platform,
+        memory_size,
+    };
+
+    let length = reader.u32() as usize;
+
+    if length != body.len() - 4 - reader.position() {
+        return Err("payload length mismatch".to_string());
+    }
+
+    let payload = migrate(header.version, reader.bytes(length).to_vec())?;
+
+    Ok((header, payload))
+}
+
+// Upgrade payloads written by older versions to the current layout
+fn migrate(version: u16, mut payload: Vec<u8>) -> Result<Vec<u8>, String> {
+    if version > VERSION {
+        return Err(format!(
+            "saved by a newer version (v{}, this build reads up to v{})",
+            version, VERSION
+        ));
+    }
+
+    if version == 0 {
+        return Err(format!("unsupported version v{}", version));
+    }
+
+    // v1 didn't store the keypad, so restore with every key released
+    if version < 2 {
+        payload.extend_from_slice(&[0; 16]);
+    }
+
+    // v2 didn't store memory banks, so restore with none
+    if version < 3 {
+        payload.extend_from_slice(&[0; 4]);
+    }
+
+    // v3 always had 16 stack entries, which now follow memory, registers
+    // and the stack depth
+    if version < 4 {
+        payload.insert(STACK_OFFSET, 16);
+    }
+
+    // v4 had no XO-CHIP state, so restore with the first plane selected
+    // and a silent pattern at the default pitch
+    if version < 5 {
+        payload.push(1);
+        payload.extend_from_slice(&[0; 16]);
+        payload.push(64);
+    }
+
+    // v5 had a 16 bit I, so restore with the Mega-CHIP high byte clear
+    if version < 6 {
+        payload.push(0);
+    }
+
+    Ok(payload)
+}
+
+// Offset of the stack depth in the payload, after memory and registers
+const STACK_OFFSET: usize = 4096 + 16;
+
+#[derive(Default)]
+pub struct Writer(pub Vec<u8>);
+
+impl Writer {
+    pub fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+}
+
+// Reads values back in the order they were written. The payload has
+// already been checksummed, so running out of data is a bug and panics.
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub fn u8(&mut self) -> u8 {
+        self.bytes(1)[0]
+    }
+
+    pub fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.bytes(2).try_into().unwrap())
+    }
+
+    pub fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.bytes(4).try_into().unwrap())
+    }
+
+    pub fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.bytes(8).try_into().unwrap())
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn bytes(&mut self, len: usize) -> &'a [u8] {
+        let bytes = &self.data[self.position..self.position + len];
+        self.position += len;
+
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(version: u16) -> Header {
+        Header {
+            version,
+            rom_hash: 0xDEAD_BEEF,
+            quirks: 0b10,
+            platform: 1,
+            memory_size: 0x1000,
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let data = encode(&header(VERSION), &[1, 2, 3]);
+        let (decoded, payload) = decode(&data).unwrap();
+
+        assert_eq!(decoded.rom_hash, 0xDEAD_BEEF);
+        assert_eq!(decoded.quirks, 0b10);
+        assert_eq!(decoded.platform, 1);
+        assert_eq!(decoded.memory_size, 0x1000);
+        assert_eq!(payload, [1, 2, 3]);
+    }
+
+    #[test]
+    fn rejects_corruption() {
+        let mut data = encode(&header(VERSION), &[1, 2, 3]);
+        data[HEADER_SIZE] ^= 0xFF;
+
+        assert!(decode(&data).is_err());
+    }
+
+    #[test]
+    fn migrates_v1_with_keys_released_and_no_banks() {
+        let data = encode(&header(1), &[1; STACK_OFFSET + 3]);
+        let (_, payload) = decode(&data).unwrap();
+
+        assert_eq!(payload.len(), STACK_OFFSET + 1 + 3 + 16 + 4 + 18 + 1);
+        assert_eq!(payload[STACK_OFFSET], 16);
+        assert!(payload[STACK_OFFSET + 4..STACK_OFFSET + 24]
+            .iter()
+            .all(|&byte| byte == 0));
+        assert_eq!(payload[STACK_OFFSET + 24], 1);
+        assert_eq!(payload[payload.len() - 2], 64);
+        assert_eq!(payload[payload.len() - 1], 0);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let data = encode(&header(VERSION + 1), &[1, 2, 3]);
+
+        assert!(decode(&data).is_err());
+    }
+}
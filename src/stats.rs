@@ -0,0 +1,258 @@
+use crate::display::Display;
+
+use log::{info, warn};
+use std::collections::BTreeMap;
+
+// Measures how much of the display changes between frames, to quantify
+// flicker caused by the usual erase-then-redraw sprite animation
+pub struct FlickerStats {
+    previous: Vec<u8>,
+    changed: Vec<bool>,
+    toggled: u64,
+    frames: u64,
+    frame_toggles: u64,
+    max_frame_toggles: u64,
+    total_toggles: u64,
+    total_changes: u64,
+    sprites: u64,
+}
+
+impl FlickerStats {
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            changed: Vec::new(),
+            toggled: 0,
+            frames: 0,
+            frame_toggles: 0,
+            max_frame_toggles: 0,
+            total_toggles: 0,
+            total_changes: 0,
+            sprites: 0,
+        }
+    }
+
+    pub fn record_sprite(&mut self) {
+        self.sprites += 1;
+    }
+
+    // Compare the display against the previous frame and update totals
+    pub fn end_frame(&mut self, display: &Display) {
+        let buffer = display.buffer();
+
+        if self.previous.len() != buffer.len() {
+            self.previous = vec![0; buffer.len()];
+        }
+
+        self.changed.clear();
+        self.changed.extend(
+            buffer
+                .iter()
+                .zip(&self.previous)
+                .map(|(now, before)| now != before),
+        );
+
+        self.previous.copy_from_slice(buffer);
+
+        self.frame_toggles = display.toggled() - self.toggled;
+        self.toggled = display.toggled();
+
+        self.frames += 1;
+        self.max_frame_toggles = self.max_frame_toggles.max(self.frame_toggles);
+        self.total_toggles += self.frame_toggles;
+        self.total_changes += self.changed.iter().filter(|&&changed| changed).count() as u64;
+    }
+
+    // Pixels that differ from the previous frame
+    pub fn changed(&self) -> &[bool] {
+        &self.changed
+    }
+
+    pub fn print(&self) {
+        let frames = self.frames.max(1) as f64;
+
+        info!("Flicker statistics over {} frames", self.frames);
+        info!(
+            "  pixels toggled per frame: {:.1} average, {} max",
+            self.total_toggles as f64 / frames,
+            self.max_frame_toggles
+        );
+        info!(
+            "  pixels changed per frame: {:.1} average",
+            self.total_changes as f64 / frames
+        );
+        info!(
+            "  pixels toggled per sprite: {:.1} average",
+            self.total_toggles as f64 / self.sprites.max(1) as f64
+        );
+
+        // Toggles that cancel out within a frame were drawn and erased
+        // again, which is what shows up as flicker
+        if self.total_toggles > 0 {
+            info!(
+                "  {:.0}% of toggles were undone within the same frame",
+                100.0 * (self.total_toggles - self.total_changes.min(self.total_toggles)) as f64
+                    / self.total_toggles as f64
+            );
+        }
+    }
+}
+
+// Why a frame's batch of instructions stopped
+#[derive(Clone, Copy)]
+pub enum BatchEnd {
+    // Used up the frame's instruction budget, the ROM is CPU bound
+    Budget,
+    // Drew a sprite and waited for the display, the ROM is display bound
+    DrawWait,
+    // Waiting for a key press
+    KeyWait,
+    Halted,
+}
+
+// Tracks how many instructions actually ran each frame
+pub struct BudgetStats {
+    history: Vec<u32>,
+    ends: [u64; 4],
+}
+
+impl BudgetStats {
+    const PLOT_WIDTH: usize = 64;
+    const PLOT_HEIGHT: u32 = 8;
+
+    pub fn new() -> Self {
+        Self {
+            history: Vec::new(),
+            ends: [0; 4],
+        }
+    }
+
+    pub fn end_frame(&mut self, instructions: u32, end: BatchEnd) {
+        self.history.push(instructions);
+        self.ends[end as usize] += 1;
+    }
+
+    pub fn print(&self) {
+        let frames = self.history.len().max(1) as f64;
+        let total: u64 = self.history.iter().map(|&count| u64::from(count)).sum();
+        let percent = |end: BatchEnd| 100.0 * self.ends[end as usize] as f64 / frames;
+
+        info!("Instruction budget over {} frames", self.history.len());
+        info!(
+            "  {:.1} instructions per frame on average",
+            total as f64 / frames
+        );
+        info!(
+            "  batches ended by: budget {:.0}%, draw wait {:.0}%, key wait {:.0}%, halt {:.0}%",
+            percent(BatchEnd::Budget),
+            percent(BatchEnd::DrawWait),
+            percent(BatchEnd::KeyWait),
+            percent(BatchEnd::Halted),
+        );
+
+        for line in self.plot() {
+            info!("  {}", line);
+        }
+    }
+
+    // Bar chart of the most recent frames, one column per frame
+    fn plot(&self) -> Vec<String> {
+        let recent = &self.history[self.history.len().saturating_sub(Self::PLOT_WIDTH)..];
+        let max = recent.iter().copied().max().unwrap_or(0).max(1);
+
+        let mut lines: Vec<String> = (1..=Self::PLOT_HEIGHT)
+            .rev()
+            .map(|level| {
+                recent
+                    .iter()
+                    .map(|&count| {
+                        if count * Self::PLOT_HEIGHT >= level * max {
+                            '#'
+                        } else {
+                            ' '
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        lines.push(format!(
+            "{} (max {} per frame)",
+            "-".repeat(recent.len()),
+            max
+        ));
+
+        lines
+    }
+}
+
+// Where an unknown opcode was met and how often
+struct Sightings {
+    count: u64,
+    addresses: Vec<u16>,
+}
+
+// Unknown opcodes skipped in lenient mode, collected so each one is only
+// logged the first time it's met and summarised at the end
+#[derive(Default)]
+pub struct UnknownOpcodes {
+    opcodes: BTreeMap<u16, Sightings>,
+}
+
+// Addresses listed for each opcode, beyond this only the count goes up
+const MAX_ADDRESSES: usize = 8;
+
+impl UnknownOpcodes {
+    pub fn record(&mut self, opcode: u16, address: u16) {
+        let sightings = self.opcodes.entry(opcode).or_insert_with(|| {
+            warn!(
+                "Skipping unknown opcode {:04x} at {:03x}, further occurrences are only counted",
+                opcode, address
+            );
+
+            Sightings {
+                count: 0,
+                addresses: Vec::new(),
+            }
+        });
+
+        sightings.count += 1;
+
+        if sightings.addresses.len() < MAX_ADDRESSES && !sightings.addresses.contains(&address) {
+            sightings.addresses.push(address);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.opcodes.is_empty()
+    }
+
+    // Total times an unknown opcode was skipped
+    #[cfg(feature = "metrics")]
+    pub fn count(&self) -> u64 {
+        self.opcodes.values().map(|sightings| sightings.count).sum()
+    }
+
+    pub fn print(&self) {
+        info!("Unknown opcodes skipped");
+
+        if self.opcodes.is_empty() {
+            info!("  None");
+        }
+
+        for (opcode, sightings) in &self.opcodes {
+            let addresses: Vec<String> = sightings
+                .addresses
+                .iter()
+                .map(|address| format!("{:03x}", address))
+                .collect();
+
+            info!(
+                "  {:04x}: {} times at {}",
+                opcode,
+                sightings.count,
+                addresses.join(", ")
+            );
+        }
+    }
+}
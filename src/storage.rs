@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+// Marker file that switches to portable mode without passing `--portable`
+const PORTABLE_MARKER: &str = "portable.txt";
+
+// Somewhere to keep config, save states and other persistent data, each
+// piece stored under a name such as `quirks.db` or `tetris.state`
+pub trait Backend: Send + Sync {
+    fn read(&self, name: &str) -> io::Result<Vec<u8>>;
+    fn write(&self, name: &str, data: &[u8]) -> io::Result<()>;
+    // When the data was last written, if it's there
+    fn modified(&self, name: &str) -> Option<SystemTime>;
+    // Where the data is kept, for messages
+    fn locate(&self, name: &str) -> String;
+}
+
+// Files in a directory, created when the first file is written
+pub struct Files {
+    dir: PathBuf,
+}
+
+impl Files {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl Backend for Files {
+    fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.dir.join(name))
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(name), data)
+    }
+
+    fn modified(&self, name: &str) -> Option<SystemTime> {
+        fs::metadata(self.dir.join(name))
+            .and_then(|file| file.modified())
+            .ok()
+    }
+
+    fn locate(&self, name: &str) -> String {
+        self.dir.join(name).display().to_string()
+    }
+}
+
+// Data kept only while the process runs, for tests and embedders with
+// nowhere to write
+#[derive(Default)]
+pub struct Memory {
+    entries: Mutex<HashMap<String, (Vec<u8>, SystemTime)>>,
+}
+
+impl Backend for Memory {
+    fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|(data, _)| data.clone())
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), (data.to_vec(), SystemTime::now()));
+
+        Ok(())
+    }
+
+    fn modified(&self, name: &str) -> Option<SystemTime> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|&(_, modified)| modified)
+    }
+
+    fn locate(&self, name: &str) -> String {
+        format!("memory:{}", name)
+    }
+}
+
+// Decides where persistent data lives: the user's config directory, next
+// to the executable in portable mode, or any other backend an embedder
+// provides
+#[derive(Clone)]
+pub struct Storage {
+    backend: Arc<dyn Backend>,
+    portable: bool,
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Self::with_backend(Files::new(Self::config_dir().join("chip8")))
+    }
+}
+
+impl Storage {
+    pub fn new(portable: bool) -> Self {
+        let marker = Self::exe_dir().join(PORTABLE_MARKER);
+
+        if portable || marker.exists() {
+            Self {
+                backend: Arc::new(Files::new(Self::exe_dir())),
+                portable: true,
+            }
+        } else {
+            Self::default()
+        }
+    }
+
+    pub fn memory() -> Self {
+        Self::with_backend(Memory::default())
+    }
+
+    pub fn with_backend(backend: impl Backend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            portable: false,
+        }
+    }
+
+    pub fn is_portable(&self) -> bool {
+        self.portable
+    }
+
+    pub fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+        self.backend.read(name)
+    }
+
+    pub fn read_to_string(&self, name: &str) -> io::Result<String> {
+        String::from_utf8(self.read(name)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn write(&self, name: &str, data: impl AsRef<[u8]>) -> io::Result<()> {
+        self.backend.write(name, data.as_ref())
+    }
+
+    pub fn modified(&self, name: &str) -> Option<SystemTime> {
+        self.backend.modified(name)
+    }
+
+    pub fn locate(&self, name: &str) -> String {
+        self.backend.locate(name)
+    }
+
+    fn exe_dir() -> PathBuf {
+        let exe = env::current_exe().unwrap();
+
+        exe.parent().unwrap().to_path_buf()
+    }
+
+    fn config_dir() -> PathBuf {
+        let var = |name| env::var_os(name).map(PathBuf::from);
+
+        if cfg!(target_os = "windows") {
+            var("APPDATA").unwrap()
+        } else if cfg!(target_os = "macos") {
+            var("HOME").unwrap().join("Library/Application Support")
+        } else {
+            var("XDG_CONFIG_HOME").unwrap_or_else(|| var("HOME").unwrap().join(".config"))
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use log::{info, warn};
+use std::io::{self, BufWriter, Write};
+use std::net::TcpStream;
+
+// Per-frame hashes written live for external tools, such as differential
+// testers comparing emulators or netplay desync monitors. Each line is the
+// frame number, the CRC-32 of the display and, when asked for, the CRC-32
+// of the whole machine state.
+pub struct HashStream {
+    out: BufWriter<Box<dyn Write + Send>>,
+}
+
+impl HashStream {
+    // `-` for stdout, otherwise a `host:port` to connect to
+    pub fn open(target: &str, with_state: bool) -> Self {
+        let out: Box<dyn Write + Send> = if target == "-" {
+            Box::new(io::stdout())
+        } else {
+            let socket = TcpStream::connect(target)
+                .unwrap_or_else(|err| panic!("Can't connect to '{}': {}", target, err));
+            socket.set_nodelay(true).unwrap();
+
+            info!("Streaming frame hashes to {}", target);
+
+            Box::new(socket)
+        };
+
+        let mut stream = Self {
+            out: BufWriter::new(out),
+        };
+
+        let header = if with_state {
+            "# frame\tdisplay\tstate"
+        } else {
+            "# frame\tdisplay"
+        };
+
+        stream.write_line(header);
+        stream
+    }
+
+    pub fn record(&mut self, frame: u64, display: u32, state: Option<u32>) -> bool {
+        let line = match state {
+            Some(state) => format!("{}\t{:08x}\t{:08x}", frame, display, state),
+            None => format!("{}\t{:08x}", frame, display),
+        };
+
+        self.write_line(&line)
+    }
+
+    // Lines are flushed straight away so consumers see every frame as it
+    // ends, returning false once the consumer has gone
+    fn write_line(&mut self, line: &str) -> bool {
+        match writeln!(self.out, "{}", line).and_then(|()| self.out.flush()) {
+            Ok(()) => true,
+            Err(err) => {
+                warn!("Stopped streaming frame hashes: {}", err);
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+// Machine cycles the COSMAC VIP's interpreter spends on each instruction,
+// for ROMs tuned to the speed of the original hardware.
+//
+// The VIP runs its 1802 at 1.76MHz with 8 clock cycles to a machine
+// cycle, which is 3668 machine cycles per 60Hz frame. The display takes
+// 1024 of those for DMA, 8 bytes for each of its 128 lines, and its
+// interrupt routine about another 46, leaving the rest for the program.
+pub const FRAME_CYCLES: u32 = 3668 - 1024 - 46;
+
+// Fetching and decoding an instruction in the interpreter's main loop
+const FETCH: u32 = 40;
+// Taking a skip costs a little more than not taking it
+const SKIP: u32 = 4;
+
+// Cycles for `opcode`, given the value of its Vx register and whether it
+// skipped the next instruction
+pub fn cycles(opcode: u16, vx: u8, skipped: bool) -> u32 {
+    let x = u32::from((opcode >> 8) & 0xF);
+    let n = u32::from(opcode & 0xF);
+    let skip = if skipped { SKIP } else { 0 };
+
+    let execute = match opcode & 0xF000 {
+        0x0000 => match opcode {
+            // clearing writes all 256 bytes of the display page
+            0x00E0 => 3078,
+            0x00EE => 10,
+            // a machine code routine, which could take any time at all
+            _ => 10,
+        },
+        0x1000 => 12,
+        0x2000 => 26,
+        0x3000 | 0x4000 => 10 + skip,
+        0x5000 | 0x9000 => 14 + skip,
+        0x6000 => 6,
+        0x7000 => 10,
+        0x8000 => 44,
+        0xA000 => 12,
+        0xB000 => 22,
+        0xC000 => 36,
+        // each row is shifted into place, which takes longer when the
+        // sprite straddles two bytes of the display
+        0xD000 => 26 + n * if vx.is_multiple_of(8) { 46 } else { 68 },
+        0xE000 => 14 + skip,
+        0xF000 => match opcode & 0xFF {
+            0x07 | 0x15 | 0x18 => 10,
+            0x0A => 18,
+            0x1E | 0x29 => 16,
+            // the digits are counted out by repeated subtraction
+            0x33 => 80 + 16 * u32::from(vx / 100 + vx % 100 / 10 + vx % 10),
+            0x55 | 0x65 => 14 + 14 * (x + 1),
+            _ => 10,
+        },
+        _ => 10,
+    };
+
+    FETCH + execute
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn costs_vary_with_operands() {
+        assert_eq!(cycles(0x6012, 0, false), 46);
+        assert_eq!(cycles(0x3012, 0, true), 54);
+        assert_eq!(cycles(0xD015, 8, false), 40 + 26 + 5 * 46);
+        assert_eq!(cycles(0xD015, 9, false), 40 + 26 + 5 * 68);
+        assert_eq!(cycles(0xF033, 255, false), 40 + 80 + 16 * 12);
+        assert_eq!(cycles(0xF355, 0, false), 40 + 14 + 14 * 4);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::disasm;
+
+use log::info;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+// Broad groups of instructions a trace can be narrowed down to
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Class {
+    // Jumps, calls and returns
+    Jump,
+    // Conditional skips on registers
+    Skip,
+    // Anything that changes the display
+    Draw,
+    // Loads and stores through I, and changes to I
+    Memory,
+    // Register arithmetic, including loads of constants and RND
+    Alu,
+    // Reading and setting the delay and sound timers
+    Timer,
+    // Key checks and waits
+    Input,
+    // Machine routines, exiting and everything else
+    System,
+}
+
+impl Class {
+    pub fn parse(name: &str) -> Self {
+        match name {
+            "jump" => Self::Jump,
+            "skip" => Self::Skip,
+            "draw" => Self::Draw,
+            "memory" => Self::Memory,
+            "alu" => Self::Alu,
+            "timer" => Self::Timer,
+            "input" => Self::Input,
+            "system" => Self::System,
+            _ => panic!(
+                "Unknown instruction class '{}', expected jump, skip, draw, memory, alu, timer, input or system",
+                name
+            ),
+        }
+    }
+
+    pub fn of(opcode: u16) -> Self {
+        match (opcode >> 12, opcode & 0xFF) {
+            (0x0, 0xEE) | (0x1 | 0x2 | 0xB, _) => Self::Jump,
+            (0x0, 0xE0 | 0xC0..=0xCF | 0xFB | 0xFC | 0xFE | 0xFF) | (0xD, _) => Self::Draw,
+            (0x0, _) => Self::System,
+            (0x3 | 0x4 | 0x5 | 0x9, _) => Self::Skip,
+            (0x6 | 0x7 | 0x8 | 0xC, _) => Self::Alu,
+            (0xE, _) | (0xF, 0x0A) => Self::Input,
+            (0xF, 0x07 | 0x15 | 0x18) => Self::Timer,
+            (0xA, _) | (0xF, _) => Self::Memory,
+            _ => Self::System,
+        }
+    }
+}
+
+// Which executed instructions make it into the trace, everything by default
+#[derive(Clone, Default)]
+pub struct Filter {
+    classes: Vec<Class>,
+    range: Option<RangeInclusive<u16>>,
+}
+
+impl Filter {
+    // Comma separated class names, e.g. `draw,jump`
+    pub fn set_classes(&mut self, names: &str) {
+        self.classes = names
+            .split(',')
+            .map(|name| Class::parse(name.trim()))
+            .collect();
+    }
+
+    // An inclusive hex address range, e.g. `200-2FF`
+    pub fn set_range(&mut self, range: &str) {
+        let parse = |address: &str| {
+            u16::from_str_radix(address.trim().trim_start_matches("0x"), 16)
+                .unwrap_or_else(|_| panic!("Invalid trace range '{}'", range))
+        };
+
+        let (start, end) = range
+            .split_once('-')
+            .unwrap_or_else(|| panic!("Invalid trace range '{}', expected START-END", range));
+
+        self.range = Some(parse(start)..=parse(end));
+    }
+
+    pub fn matches(&self, address: u16, opcode: u16) -> bool {
+        let in_range = self
+            .range
+            .as_ref()
+            .is_none_or(|range| range.contains(&address));
+
+        in_range && (self.classes.is_empty() || self.classes.contains(&Class::of(opcode)))
+    }
+}
+
+// Writes every executed instruction that passes the filter to a file, one
+// line each with the frame, address, opcode and its disassembly
+pub struct Tracer {
+    out: BufWriter<File>,
+    filter: Filter,
+}
+
+impl Tracer {
+    pub fn new(path: &Path, filter: Filter) -> Self {
+        let mut out = BufWriter::new(File::create(path).unwrap());
+        writeln!(out, "# frame\taddress\topcode\tinstruction").unwrap();
+
+        info!("Tracing to '{}'", path.display());
+
+        Self { out, filter }
+    }
+
+    // `code` is memory from the instruction onwards
+    pub fn record(&mut self, frame: u64, address: u16, code: &[u8]) {
+        let opcode = u16::from_be_bytes([code[0], code.get(1).copied().unwrap_or_default()]);
+
+        if !self.filter.matches(address, opcode) {
+            return;
+        }
+
+        let mnemonic = disasm::disassemble(code)
+            .map_or_else(|| "???".to_owned(), |instruction| instruction.mnemonic);
+
+        writeln!(
+            self.out,
+            "{}\t{:03X}\t{:04X}\t{}",
+            frame, address, opcode, mnemonic
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_opcodes() {
+        assert_eq!(Class::of(0x00EE), Class::Jump);
+        assert_eq!(Class::of(0xD125), Class::Draw);
+        assert_eq!(Class::of(0xF065), Class::Memory);
+        assert_eq!(Class::of(0xF00A), Class::Input);
+        assert_eq!(Class::of(0xF015), Class::Timer);
+        assert_eq!(Class::of(0x8124), Class::Alu);
+    }
+
+    #[test]
+    fn filters_by_class_and_range() {
+        let mut filter = Filter::default();
+        assert!(filter.matches(0x300, 0x6001));
+
+        filter.set_classes("draw,jump");
+        filter.set_range("200-2FF");
+
+        assert!(filter.matches(0x200, 0xD125));
+        assert!(!filter.matches(0x200, 0x6001));
+        assert!(!filter.matches(0x300, 0x1200));
+    }
+}
@@ -0,0 +1,439 @@
+use crate::device::Device;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::state::{Reader, Writer};
+
+use log::info;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write as _};
+use std::path::Path;
+
+// Session trace layout, all values little endian:
+//
+//   magic      4 bytes  "C8TD"
+//   records    ...
+//   frames     u64      the frame after the last one recorded
+//   index      u32      number of keyframes, then the frame and file offset
+//                       of each as two u64s
+//   offset     u64      where the frame count starts
+//   magic      4 bytes  "C8TI"
+//
+// Each record holds either the inputs of one frame, as a u16 count and then
+// two bytes for each, or a keyframe: 0xFFFF, the frame it comes before, the
+// settings to replay with and the machine state. Frames follow a keyframe
+// in order, so they aren't numbered.
+const MAGIC: &[u8; 4] = b"C8TD";
+const INDEX_MAGIC: &[u8; 4] = b"C8TI";
+const KEYFRAME: u16 = 0xFFFF;
+
+// Frames between keyframes, the most a query replays to reach any frame
+pub const KEYFRAME_INTERVAL: u64 = 600;
+
+// What the machine can't work out for itself while it runs, so replaying
+// these from a keyframe runs the same instructions as the recording
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Input {
+    Key(u8, bool),
+    // On the CHIP-8X's second keypad
+    SecondKey(u8, bool),
+    ReleaseKeys,
+    CancelWait(u8),
+    // A byte drawn for CXKK
+    Random(u8),
+}
+
+impl Input {
+    fn encode(&self) -> [u8; 2] {
+        match *self {
+            Self::Key(key, pressed) => [u8::from(pressed), key],
+            Self::SecondKey(key, pressed) => [2 + u8::from(pressed), key],
+            Self::ReleaseKeys => [4, 0],
+            Self::CancelWait(value) => [5, value],
+            Self::Random(byte) => [6, byte],
+        }
+    }
+
+    fn decode([kind, value]: [u8; 2]) -> Self {
+        match kind {
+            0 | 1 => Self::Key(value, kind == 1),
+            2 | 3 => Self::SecondKey(value, kind == 3),
+            4 => Self::ReleaseKeys,
+            5 => Self::CancelWait(value),
+            6 => Self::Random(value),
+            _ => panic!("Unknown input {} in trace", kind),
+        }
+    }
+}
+
+// The settings that change which instructions run, so a replay can be
+// made to run the same ones
+#[derive(Clone, Copy)]
+pub struct Settings {
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub memory_size: usize,
+    pub stack_depth: usize,
+    pub vip_timing: bool,
+    pub slow_memory: bool,
+    pub banking: bool,
+    pub lenient: bool,
+}
+
+pub struct Keyframe {
+    pub frame: u64,
+    pub settings: Settings,
+    pub state: Vec<u8>,
+}
+
+// A write to the watched address while replaying
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Write {
+    pub frame: u64,
+    // Where the instruction that wrote it is
+    pub pc: u16,
+    pub value: u8,
+}
+
+// An address the replaying device reports writes to
+pub(crate) struct Watch {
+    pub address: usize,
+    // The instruction being run wrote to the address
+    pub pending: Option<u16>,
+    pub writes: Vec<Write>,
+}
+
+// Writes a session as it runs, finishing the file with its index when
+// dropped
+pub struct TraceDbWriter {
+    out: BufWriter<File>,
+    offset: u64,
+    index: Vec<(u64, u64)>,
+    inputs: Vec<Input>,
+    next_keyframe: u64,
+    frames: u64,
+}
+
+impl TraceDbWriter {
+    pub fn new(path: &Path) -> Self {
+        let mut out = BufWriter::new(File::create(path).unwrap());
+        out.write_all(MAGIC).unwrap();
+
+        info!("Recording trace database to '{}'", path.display());
+
+        Self {
+            out,
+            offset: MAGIC.len() as u64,
+            index: Vec::new(),
+            inputs: Vec::new(),
+            next_keyframe: 0,
+            frames: 0,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.out.write_all(bytes).unwrap();
+        self.offset += bytes.len() as u64;
+    }
+
+    pub fn input(&mut self, input: Input) {
+        self.inputs.push(input);
+    }
+
+    // Whether the state should be saved before running `frame`
+    pub fn due(&self, frame: u64) -> bool {
+        frame >= self.next_keyframe
+    }
+
+    // Save a keyframe before the next frame, after the host changed the
+    // machine in a way the inputs can't replay
+    pub fn invalidate(&mut self) {
+        self.next_keyframe = 0;
+    }
+
+    // The inputs so far are part of the state, so they're dropped
+    pub fn keyframe(&mut self, frame: u64, settings: &Settings, state: &[u8]) {
+        let mut writer = Writer::default();
+        let name = settings.platform.name();
+
+        writer.u16(KEYFRAME);
+        writer.u64(frame);
+        writer.u8(name.len() as u8);
+        writer.bytes(name.as_bytes());
+        writer.u32(settings.quirks.bits());
+        writer.u32(settings.memory_size as u32);
+        writer.u16(settings.stack_depth as u16);
+        writer.u8([
+            settings.vip_timing,
+            settings.slow_memory,
+            settings.banking,
+            settings.lenient,
+        ]
+        .iter()
+        .enumerate()
+        .map(|(bit, &enabled)| u8::from(enabled) << bit)
+        .sum());
+        writer.u32(state.len() as u32);
+        writer.bytes(state);
+
+        self.index.push((frame, self.offset));
+        self.write(&writer.0);
+        self.inputs.clear();
+        self.next_keyframe = frame + KEYFRAME_INTERVAL;
+        self.frames = frame;
+    }
+
+    pub fn end_frame(&mut self) {
+        let mut writer = Writer::default();
+        writer.u16(self.inputs.len() as u16);
+
+        for input in self.inputs.drain(..) {
+            writer.bytes(&input.encode());
+        }
+
+        self.write(&writer.0);
+        self.frames += 1;
+    }
+}
+
+impl Drop for TraceDbWriter {
+    fn drop(&mut self) {
+        let mut writer = Writer::default();
+        let start = self.offset;
+
+        writer.u64(self.frames);
+        writer.u32(self.index.len() as u32);
+
+        for &(frame, offset) in &self.index {
+            writer.u64(frame);
+            writer.u64(offset);
+        }
+
+        writer.u64(start);
+        writer.bytes(INDEX_MAGIC);
+
+        self.write(&writer.0);
+        self.out.flush().unwrap();
+    }
+}
+
+// A recorded session, queried by replaying it from its keyframes
+pub struct TraceDb {
+    data: Vec<u8>,
+    // Where the records stop and the index starts
+    end: usize,
+    frames: u64,
+    index: Vec<(u64, usize)>,
+}
+
+impl TraceDb {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        Self::parse(fs::read(path).map_err(|err| err.to_string())?)
+    }
+
+    pub fn parse(data: Vec<u8>) -> Result<Self, String> {
+        let footer = INDEX_MAGIC.len() + 8;
+
+        if !data.starts_with(MAGIC) || data.len() < MAGIC.len() + footer {
+            return Err("not a trace database".to_owned());
+        }
+
+        if !data.ends_with(INDEX_MAGIC) {
+            return Err("trace has no index, the recording may have been cut short".to_owned());
+        }
+
+        let mut reader = Reader::new(&data[data.len() - footer..]);
+        let start = reader.u64() as usize;
+        let index = data
+            .get(start..data.len() - footer)
+            .filter(|index| index.len() >= 12)
+            .ok_or("trace index is out of place")?;
+
+        let mut reader = Reader::new(index);
+        let frames = reader.u64();
+        let count = reader.u32() as usize;
+
+        if index.len() != 12 + count * 16 {
+            return Err("trace index is the wrong size".to_owned());
+        }
+
+        let index = (0..count)
+            .map(|_| (reader.u64(), reader.u64() as usize))
+            .collect();
+
+        Ok(Self {
+            data,
+            end: start,
+            frames,
+            index,
+        })
+    }
+
+    // The frame after the last one recorded
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn keyframes(&self) -> usize {
+        self.index.len()
+    }
+
+    // The keyframe at `offset` and the inputs of the frames after it, up to
+    // the next keyframe
+    fn segment(&self, offset: usize) -> (Keyframe, Vec<Vec<Input>>) {
+        let mut reader = Reader::new(&self.data[offset..]);
+
+        assert_eq!(reader.u16(), KEYFRAME, "Trace index points past a keyframe");
+
+        let frame = reader.u64();
+        let name = reader.u8();
+        let platform = Platform::parse(&String::from_utf8_lossy(reader.bytes(name.into())));
+        let quirks = Quirks::from_bits(reader.u32());
+        let memory_size = reader.u32() as usize;
+        let stack_depth = usize::from(reader.u16());
+        let flags = reader.u8();
+        let length = reader.u32() as usize;
+        let state = reader.bytes(length).to_vec();
+
+        let keyframe = Keyframe {
+            frame,
+            settings: Settings {
+                platform,
+                quirks,
+                memory_size,
+                stack_depth,
+                vip_timing: flags & 1 != 0,
+                slow_memory: flags & 2 != 0,
+                banking: flags & 4 != 0,
+                lenient: flags & 8 != 0,
+            },
+            state,
+        };
+
+        let end = self
+            .index
+            .iter()
+            .map(|&(_, start)| start)
+            .find(|&start| start > offset)
+            .unwrap_or(self.end);
+        let mut frames = Vec::new();
+
+        while offset + reader.position() < end {
+            let count = reader.u16();
+
+            if count == KEYFRAME {
+                break;
+            }
+
+            frames.push(
+                (0..count)
+                    .map(|_| Input::decode([reader.u8(), reader.u8()]))
+                    .collect(),
+            );
+        }
+
+        (keyframe, frames)
+    }
+
+    // The segments to replay `frame` from, the latest first
+    fn keyframes_before(&self, frame: u64) -> impl Iterator<Item = usize> + '_ {
+        self.index
+            .iter()
+            .rev()
+            .filter(move |&&(start, _)| start <= frame)
+            .map(|&(_, offset)| offset)
+    }
+
+    // The machine as it was at the start of `frame`, replayed from the
+    // keyframe before it
+    pub fn machine_at(&self, frame: u64) -> Option<Device> {
+        if frame > self.frames {
+            return None;
+        }
+
+        let (keyframe, frames) = self.segment(self.keyframes_before(frame).next()?);
+        let mut device = Device::from_keyframe(&keyframe);
+
+        for inputs in frames.iter().take((frame - keyframe.frame) as usize) {
+            device.replay_frame(inputs);
+        }
+
+        Some(device)
+    }
+
+    // The last instruction to write to `address` in the frames before
+    // `before`, found by replaying one keyframe's frames at a time, going
+    // further back until there's a write
+    pub fn last_write(&self, address: usize, before: u64) -> Option<Write> {
+        for offset in self.keyframes_before(before.saturating_sub(1)) {
+            let (keyframe, frames) = self.segment(offset);
+            let mut device = Device::from_keyframe(&keyframe);
+            device.watch(address);
+
+            for inputs in frames
+                .iter()
+                .take(before.saturating_sub(keyframe.frame) as usize)
+            {
+                device.replay_frame(inputs);
+            }
+
+            if let Some(&write) = device.writes().last() {
+                return Some(write);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::RomBuilder;
+    use crate::device::Config;
+    use crate::rng::Rng;
+
+    #[test]
+    fn finds_writes_by_replaying_the_session() {
+        let path = std::env::temp_dir().join(format!("chip8-{}.tracedb", std::process::id()));
+        let rom = RomBuilder::new()
+            .ld_i(0x3A4)
+            .rnd(0, 0xFF)
+            .store(0)
+            .jump(0x202)
+            .build();
+
+        let mut device = Device::new(Config {
+            trace_db: Some(path.clone()),
+            rng: Rng::parse("seed:7"),
+            quirks: Quirks {
+                memory_increment: false,
+                ..Quirks::default()
+            },
+            ..Config::default()
+        });
+        device.load_rom(&rom);
+
+        let mut written = Vec::new();
+
+        for _ in 0..KEYFRAME_INTERVAL + 100 {
+            device.run_frame();
+            written.push(device.octo_state().memory[0x3A4]);
+        }
+        drop(device);
+
+        let db = TraceDb::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(db.frames(), KEYFRAME_INTERVAL + 100);
+        assert_eq!(db.keyframes(), 2);
+
+        let write = db.last_write(0x3A4, 650).unwrap();
+        assert_eq!(write.frame, 649);
+        assert_eq!(write.pc, 0x204);
+        assert_eq!(write.value, written[649]);
+
+        let machine = db.machine_at(KEYFRAME_INTERVAL + 50).unwrap();
+        assert_eq!(machine.octo_state().memory[0x3A4], written[649]);
+        assert!(db.last_write(0x3A5, 650).is_none());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::disasm;
+
+// Frames between instructions, so a ROM runs at 2 instructions a second
+pub const FRAMES_PER_STEP: u64 = 30;
+
+// Run by `chip8 tutorial`: counts from 0 to 9 in the middle of the screen,
+// using only a handful of the most common instructions
+#[rustfmt::skip]
+pub const ROM: [u8; 22] = [
+    0x00, 0xE0, // 200  CLS
+    0x60, 0x00, // 202  LD V0, 0           the digit to show
+    0x61, 0x1C, // 204  LD V1, 28          x and y of the middle of the screen
+    0x62, 0x0D, // 206  LD V2, 13
+    0xF0, 0x29, // 208  LD F, V0           point I at the digit in the font
+    0xD1, 0x25, // 20A  DRW V1, V2, 5
+    0xD1, 0x25, // 20C  DRW V1, V2, 5      drawing it again erases it
+    0x70, 0x01, // 20E  ADD V0, 1
+    0x30, 0x0A, // 210  SE V0, 10          start again after 9
+    0x12, 0x08, // 212  JP 208
+    0x12, 0x02, // 214  JP 202
+];
+
+// The parts of the machine an instruction visibly changes
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Registers {
+    pub v: [u8; 16],
+    pub i: u32,
+    pub pc: u16,
+    pub dt: u8,
+    pub st: u8,
+}
+
+// The instruction at the start of `bytes`, what it does in plain language
+// and what it changed, such as:
+//
+//     208  LD F, V0
+//          Point I at the font sprite for the digit in V0
+//          I: 000 -> 05A
+pub fn explain(bytes: &[u8], before: &Registers, after: &Registers) -> String {
+    let opcode = u16::from_be_bytes([bytes[0], bytes[1]]);
+    let mnemonic = disasm::disassemble(bytes)
+        .map(|instruction| instruction.mnemonic)
+        .unwrap_or_else(|| "unknown".to_owned());
+
+    let mut lines = vec![
+        format!("{:03X}  {}", before.pc, mnemonic),
+        format!("     {}", describe(opcode)),
+    ];
+
+    for (index, (old, new)) in before.v.iter().zip(&after.v).enumerate() {
+        if old != new {
+            lines.push(format!("     V{:X}: {} -> {}", index, old, new));
+        }
+    }
+
+    if before.i != after.i {
+        lines.push(format!("     I: {:03X} -> {:03X}", before.i, after.i));
+    }
+
+    if after.dt > before.dt {
+        lines.push(format!("     DT: {} -> {}", before.dt, after.dt));
+    }
+
+    if after.st > before.st {
+        lines.push(format!("     ST: {} -> {}", before.st, after.st));
+    }
+
+    // only worth pointing out when it didn't just move on to the next one
+    if after.pc != before.pc + 2 {
+        lines.push(format!("     PC: {:03X} -> {:03X}", before.pc, after.pc));
+    }
+
+    lines.join("\n")
+}
+
+fn describe(opcode: u16) -> String {
+    let nnn = opcode & 0x0FFF;
+    let x = (opcode >> 8) & 0xF;
+    let y = (opcode >> 4) & 0xF;
+    let n = opcode & 0xF;
+    let kk = opcode & 0xFF;
+
+    match opcode >> 12 {
+        0x0 => match opcode {
+            0x00E0 => "Clear the screen, turning every pixel off".to_owned(),
+            0x00EE => "Return from a subroutine to the instruction after the CALL that started it"
+                .to_owned(),
+            _ => "Call a machine code routine of the original computer".to_owned(),
+        },
+        0x1 => format!("Jump to {:03X} and carry on from there", nnn),
+        0x2 => format!(
+            "Call the subroutine at {:03X}, remembering on the stack where to return to",
+            nnn
+        ),
+        0x3 => format!("Skip the next instruction if V{:X} is {}", x, kk),
+        0x4 => format!("Skip the next instruction unless V{:X} is {}", x, kk),
+        0x5 if n == 0 => format!("Skip the next instruction if V{:X} equals V{:X}", x, y),
+        0x6 => format!("Set V{:X} to {}", x, kk),
+        0x7 => format!(
+            "Add {} to V{:X}, wrapping around past 255 without setting VF",
+            kk, x
+        ),
+        0x8 => match n {
+            0x0 => format!("Copy V{:X} into V{:X}", y, x),
+            0x1 => format!("Set V{:X} to V{:X} OR V{:X}, bit by bit", x, x, y),
+            0x2 => format!("Set V{:X} to V{:X} AND V{:X}, bit by bit", x, x, y),
+            0x3 => format!("Set V{:X} to V{:X} XOR V{:X}, bit by bit", x, x, y),
+            0x4 => format!(
+                "Add V{:X} to V{:X}, setting VF to 1 if the sum went past 255",
+                y, x
+            ),
+            0x5 => format!(
+                "Subtract V{:X} from V{:X}, setting VF to 0 if it had to borrow",
+                y, x
+            ),
+            0x6 => format!(
+                "Shift V{:X} right a bit, setting VF to the bit shifted out",
+                x
+            ),
+            0x7 => format!(
+                "Set V{:X} to V{:X} minus V{:X}, setting VF to 0 if it had to borrow",
+                x, y, x
+            ),
+            0xE => format!(
+                "Shift V{:X} left a bit, setting VF to the bit shifted out",
+                x
+            ),
+            _ => "Not a CHIP-8 instruction".to_owned(),
+        },
+        0x9 if n == 0 => format!("Skip the next instruction unless V{:X} equals V{:X}", x, y),
+        0xA => format!(
+            "Point I at {:03X}, where sprites and other memory instructions look",
+            nnn
+        ),
+        0xB => format!("Jump to {:03X} plus V0", nnn),
+        0xC => format!(
+            "Set V{:X} to a random number, keeping only the bits set in {:02X}",
+            x, kk
+        ),
+        0xD => format!(
+            "Draw the {} byte sprite at I at the position in V{:X} and V{:X}, flipping \
+             pixels, and set VF to 1 if any lit pixel was turned off",
+            n, x, y
+        ),
+        0xE => match kk {
+            0x9E => format!("Skip the next instruction if the key in V{:X} is held", x),
+            0xA1 => format!(
+                "Skip the next instruction unless the key in V{:X} is held",
+                x
+            ),
+            _ => "Not a CHIP-8 instruction".to_owned(),
+        },
+        0xF => match kk {
+            0x07 => format!("Copy the delay timer into V{:X}", x),
+            0x0A => format!("Wait for a key to be pressed and put it in V{:X}", x),
+            0x15 => format!(
+                "Set the delay timer to V{:X}, it counts down 60 times a second",
+                x
+            ),
+            0x18 => format!(
+                "Set the sound timer to V{:X}, it beeps until it counts down to 0",
+                x
+            ),
+            0x1E => format!("Add V{:X} to I", x),
+            0x29 => format!("Point I at the font sprite for the digit in V{:X}", x),
+            0x33 => format!("Store V{:X} as three decimal digits at I, I+1 and I+2", x),
+            0x55 => format!("Store V0 to V{:X} in memory, starting at I", x),
+            0x65 => format!("Load V0 to V{:X} from memory, starting at I", x),
+            _ => "An instruction from a later extension of CHIP-8".to_owned(),
+        },
+        _ => "An instruction from a later extension of CHIP-8".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explains_what_changed() {
+        let before = Registers {
+            v: [0; 16],
+            i: 0,
+            pc: 0x20E,
+            dt: 0,
+            st: 0,
+        };
+        let mut after = before;
+        after.v[0] = 1;
+        after.pc = 0x210;
+
+        assert_eq!(
+            explain(&[0x70, 0x01], &before, &after),
+            "20E  ADD V0, 0x01\n     \
+             Add 1 to V0, wrapping around past 255 without setting VF\n     \
+             V0: 0 -> 1"
+        );
+
+        after.pc = 0x208;
+
+        assert!(explain(&[0x12, 0x08], &before, &after).ends_with("PC: 20E -> 208"));
+    }
+}
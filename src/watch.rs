@@ -0,0 +1,118 @@
+use chip8::device::{Config, Device, Event, Stop};
+use chip8::screen::Screen;
+
+use log::info;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Finds new `.ch8` files in a directory, only reporting a file once its
+// size has stopped changing so half-copied entries aren't picked up
+struct Watcher {
+    dir: PathBuf,
+    sizes: HashMap<PathBuf, u64>,
+    seen: HashSet<PathBuf>,
+}
+
+impl Watcher {
+    fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            sizes: HashMap::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    fn poll(&mut self) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        let mut ready = Vec::new();
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            let is_rom = path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("ch8"));
+
+            if !is_rom || self.seen.contains(&path) {
+                continue;
+            }
+
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+
+            let size = metadata.len();
+
+            if self.sizes.insert(path.clone(), size) == Some(size) && size > 0 {
+                self.sizes.remove(&path);
+                self.seen.insert(path.clone());
+                ready.push((metadata.modified().ok(), path));
+            }
+        }
+
+        // oldest entries first
+        ready.sort();
+        ready.into_iter().map(|(_, path)| path).collect()
+    }
+}
+
+// Play every ROM that appears in `dir` in turn, moving on when the current
+// one is skipped with `Event::Next`, for judging game jam entries as they
+// come in
+pub fn run(dir: &Path, config: &Config, screen: &mut Screen, channel: &Receiver<Event>) {
+    info!("Watching '{}' for ROMs", dir.display());
+
+    let mut watcher = Watcher::new(dir);
+    let mut queue = VecDeque::new();
+    let mut last_poll: Option<Instant> = None;
+
+    loop {
+        if last_poll.is_none_or(|time| time.elapsed() >= POLL_INTERVAL) {
+            last_poll = Some(Instant::now());
+
+            for path in watcher.poll() {
+                info!("Queued '{}'", path.display());
+                queue.push_back(path);
+            }
+        }
+
+        let Some(path) = queue.pop_front() else {
+            screen.set_name(Some("waiting for ROMs".to_owned()));
+
+            // keep the window responsive while the queue is empty
+            loop {
+                match channel.try_recv() {
+                    Ok(Event::Resize(width, height)) => screen.resize(width, height),
+                    Ok(Event::Off) | Err(TryRecvError::Disconnected) => return,
+                    Ok(_) => {}
+                    Err(TryRecvError::Empty) => break,
+                }
+            }
+
+            thread::sleep(Duration::from_millis(100));
+            continue;
+        };
+
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned());
+
+        screen.set_name(name);
+
+        let mut device = Device::new(config.clone());
+        device.load(&path);
+
+        if device.run(screen, channel) == Stop::Off {
+            return;
+        }
+    }
+}
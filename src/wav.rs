@@ -0,0 +1,91 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+const HEADER_SIZE: u32 = 44;
+
+// A mono 16-bit PCM WAV file written as samples arrive, with the sizes in
+// the header filled in once it's finished
+pub struct WavWriter {
+    out: BufWriter<File>,
+    samples: u32,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(&header(0))?;
+
+        Ok(Self { out, samples: 0 })
+    }
+
+    pub fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+        for sample in samples {
+            self.out.write_all(&sample.to_le_bytes())?;
+        }
+
+        self.samples += samples.len() as u32;
+
+        Ok(())
+    }
+
+    // Rewrite the header with the final sizes
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(&header(self.samples))?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()
+    }
+}
+
+impl Drop for WavWriter {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+fn header(samples: u32) -> Vec<u8> {
+    let data_size = samples * 2;
+    let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(HEADER_SIZE - 8 + data_size).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, mono
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    // byte rate, block alignment and bits per sample
+    header.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    header.extend_from_slice(&2u16.to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_size.to_le_bytes());
+
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_sizes_when_finished() {
+        let path = std::env::temp_dir().join(format!("chip8-wav-{}.wav", std::process::id()));
+
+        let mut wav = WavWriter::create(&path).unwrap();
+        wav.write(&[0, 1000, -1000]).unwrap();
+        drop(wav);
+
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(data.len(), 44 + 6);
+        assert_eq!(data[4..8], 42u32.to_le_bytes());
+        assert_eq!(data[40..44], 6u32.to_le_bytes());
+        assert_eq!(data[46..48], 1000i16.to_le_bytes());
+    }
+}
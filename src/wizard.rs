@@ -0,0 +1,75 @@
+use chip8::device::{Config, Device, ExitAction};
+use chip8::profiles::Profiles;
+use chip8::quirks::Quirks;
+
+use log::info;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+// Walk through every quirk, running the ROM headlessly with it off and on
+// and asking which of the two final frames looks right, then save the
+// answers so the ROM always runs with them. Quirks that make no visible
+// difference are skipped, so only the ones the game depends on are asked
+// about.
+pub fn run(path: &Path, config: &Config, frames: u32) {
+    let rom = fs::read(path).unwrap();
+    let config = Config {
+        exit_action: ExitAction::Halt,
+        saved_quirks: false,
+        ..config.clone()
+    };
+
+    let final_frame = |name: &str, enabled: bool| {
+        let mut config = config.clone();
+        config.quirks.set(name, enabled);
+
+        let mut device = Device::new(config);
+        device.load(path);
+
+        for _ in 0..frames {
+            device.run_frame();
+        }
+
+        device.display().ascii()
+    };
+
+    let mut chosen = Vec::new();
+    let mut answers = io::stdin().lock().lines();
+
+    for name in Quirks::NAMES {
+        let (off, on) = (final_frame(name, false), final_frame(name, true));
+
+        if off == on {
+            println!("{}: no visible difference, skipping", name);
+            continue;
+        }
+
+        println!("1) {} off\n{}", name, off);
+        println!("2) {} on\n{}", name, on);
+        print!("Which looks right? [1/2, anything else to skip] ");
+        io::stdout().flush().unwrap();
+
+        let Some(Ok(answer)) = answers.next() else {
+            break;
+        };
+
+        match answer.trim() {
+            "1" => chosen.push((name.to_owned(), false)),
+            "2" => chosen.push((name.to_owned(), true)),
+            _ => {}
+        }
+    }
+
+    if chosen.is_empty() {
+        println!("No quirks chosen, nothing saved");
+        return;
+    }
+
+    let mut profiles = Profiles::load(&config.storage);
+    let file = path.file_name().map(|name| name.to_string_lossy());
+    profiles.set(&rom, file.as_deref(), chosen);
+    profiles.save();
+
+    info!("Saved quirks for '{}'", path.display());
+}